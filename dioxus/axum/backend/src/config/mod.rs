@@ -0,0 +1,300 @@
+use anyhow::{anyhow, bail, Context};
+use axum::http::HeaderValue;
+use getopts::Options;
+use serde::Deserialize;
+use std::{
+    net::{SocketAddr, ToSocketAddrs},
+    path::{Path, PathBuf},
+};
+use tracing_subscriber::EnvFilter;
+
+/// TOML file read if it exists and no other file is given
+pub const CONFIG_FILE: &str = "backend.toml";
+
+/// Settings of the backend. Each one is read from the TOML file, then the environment and then the CLI flags
+/// with the later ones taking precedence e.g. `port = 3000`, `PORT=3000` and `--port 3000`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub database_url: String,
+    pub host: String,
+    pub port: u16,
+    /// Frontend to serve for any route that isn't part of the API
+    pub static_dir: PathBuf,
+    /// Tracing filter e.g. `info,sqlx=warn`
+    pub log: String,
+    /// Origins allowed to call the API from a browser, none if empty
+    pub cors_origins: Vec<String>,
+    /// Admin ensured to exist if a password is given
+    pub admin_name: String,
+    /// Best kept out of the file and given in the environment
+    pub admin_password: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            database_url: "sqlite:./sqlite.db?mode=rwc".into(),
+            host: "127.0.0.1".into(),
+            port: 3000,
+            static_dir: "frontend/dist".into(),
+            log: "trace,sqlx=warn,mio=warn,hyper=warn".into(),
+            cors_origins: vec![],
+            admin_name: "admin".into(),
+            admin_password: None,
+        }
+    }
+}
+
+impl Config {
+    /// Read the given TOML file over the defaults
+    pub fn from_file(path: &Path) -> Result<Self, anyhow::Error> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("invalid config file {}", path.display()))
+    }
+
+    /// Override the settings with those set in the environment
+    /// * `var: impl Fn(&str) -> Option<String>` looks up an environment variable e.g. `|x| env::var(x).ok()`
+    pub fn merge_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), anyhow::Error> {
+        if let Some(x) = var("DATABASE_URL") {
+            self.database_url = x;
+        }
+        if let Some(x) = var("HOST") {
+            self.host = x;
+        }
+        if let Some(x) = var("PORT") {
+            self.port = parse_port(&x).context("invalid PORT")?;
+        }
+        if let Some(x) = var("STATIC_DIR") {
+            self.static_dir = x.into();
+        }
+        if let Some(x) = var("RUST_LOG") {
+            self.log = x;
+        }
+        if let Some(x) = var("CORS_ORIGINS") {
+            self.cors_origins = x.split(',').map(|x| x.trim().to_owned()).filter(|x| !x.is_empty()).collect();
+        }
+        if let Some(x) = var("ADMIN_NAME") {
+            self.admin_name = x;
+        }
+        if let Some(x) = var("ADMIN_PASSWORD") {
+            self.admin_password = Some(x);
+        }
+        Ok(())
+    }
+
+    /// Check the settings make sense so that mistakes are reported before anything is started
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.database_url.is_empty() {
+            bail!("database_url must not be empty");
+        }
+        self.addr()?;
+        EnvFilter::try_new(&self.log).with_context(|| format!("invalid log filter '{}'", self.log))?;
+        for origin in self.cors_origins.iter() {
+            if !(origin.starts_with("http://") || origin.starts_with("https://")) || origin.ends_with('/') {
+                bail!("invalid CORS origin '{}', expected e.g. http://localhost:8080", origin);
+            }
+            HeaderValue::from_str(origin).with_context(|| format!("invalid CORS origin '{}'", origin))?;
+        }
+        if self.admin_name.trim().is_empty() {
+            bail!("admin_name must not be empty");
+        }
+        Ok(())
+    }
+
+    /// Resolve the host and port to the address to listen on
+    pub fn addr(&self) -> Result<SocketAddr, anyhow::Error> {
+        (self.host.as_str(), self.port)
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut x| x.next())
+            .ok_or_else(|| anyhow!("invalid host '{}'", self.host))
+    }
+}
+
+/// Direction to run the migrations in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Migrate {
+    Up,
+    Down(u32), // Number of migrations to roll back
+    Status,
+}
+
+/// What the binary was asked to do
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Serve,
+    Migrate(Migrate),
+    Seed,
+    Help(String), // Usage to print
+}
+
+/// Parse the command and settings from the given arguments without the program name and environment
+/// * `args: &[String]` e.g. `["--port", "8080", "serve"]`
+/// * `var: impl Fn(&str) -> Option<String>` looks up an environment variable e.g. `|x| env::var(x).ok()`
+pub fn load(args: &[String], var: impl Fn(&str) -> Option<String>) -> Result<(Command, Config), anyhow::Error> {
+    let opts = options();
+    let matches = opts.parse(args)?;
+    if matches.opt_present("help") {
+        return Ok((Command::Help(usage(&opts)), Config::default()));
+    }
+
+    // Only a missing default file is fine
+    let file = matches.opt_str("config").or_else(|| var("CONFIG_FILE"));
+    let mut config = match file {
+        Some(path) => Config::from_file(Path::new(&path))?,
+        None if Path::new(CONFIG_FILE).exists() => Config::from_file(Path::new(CONFIG_FILE))?,
+        None => Config::default(),
+    };
+    config.merge_env(var)?;
+
+    if let Some(x) = matches.opt_str("database-url") {
+        config.database_url = x;
+    }
+    if let Some(x) = matches.opt_str("host") {
+        config.host = x;
+    }
+    if let Some(x) = matches.opt_str("port") {
+        config.port = parse_port(&x).context("invalid --port")?;
+    }
+    if let Some(x) = matches.opt_str("static-dir") {
+        config.static_dir = x.into();
+    }
+    if let Some(x) = matches.opt_str("log") {
+        config.log = x;
+    }
+    if matches.opt_present("cors-origin") {
+        config.cors_origins = matches.opt_strs("cors-origin");
+    }
+    config.validate()?;
+
+    let free = matches.free.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+    let command = match free.as_slice() {
+        [] | ["serve"] => Command::Serve,
+        ["migrate"] | ["migrate", "up"] => Command::Migrate(Migrate::Up),
+        ["migrate", "down"] => Command::Migrate(Migrate::Down(1)),
+        ["migrate", "down", steps] => {
+            Command::Migrate(Migrate::Down(steps.parse().with_context(|| format!("invalid steps '{}'", steps))?))
+        },
+        ["migrate", "status"] => Command::Migrate(Migrate::Status),
+        ["seed"] => Command::Seed,
+        _ => bail!("unknown command '{}'", free.join(" ")),
+    };
+    Ok((command, config))
+}
+
+// Flags the binary accepts
+fn options() -> Options {
+    let mut opts = Options::new();
+    opts.optopt(
+        "c",
+        "config",
+        &format!("TOML file to read the settings from, {} if it exists", CONFIG_FILE),
+        "FILE",
+    );
+    opts.optopt("", "database-url", "Database to connect to", "URL");
+    opts.optopt("", "host", "Host to listen on", "HOST");
+    opts.optopt("", "port", "Port to listen on", "PORT");
+    opts.optopt("", "static-dir", "Directory of the frontend to serve", "DIR");
+    opts.optopt("", "log", "Tracing filter e.g. info,sqlx=warn", "FILTER");
+    opts.optmulti("", "cors-origin", "Origin allowed to call the API from a browser, can be repeated", "ORIGIN");
+    opts.optflag("h", "help", "Print this help");
+    opts
+}
+
+// Describe the commands and flags
+fn usage(opts: &Options) -> String {
+    let brief = "Usage: backend [options] [command]\n\n\
+        Commands:\n    \
+        serve                   Run the server, the default\n    \
+        migrate [up]            Apply all pending migrations\n    \
+        migrate down [STEPS]    Roll back the given number of migrations, 1 by default\n    \
+        migrate status          List the migrations and whether they are applied\n    \
+        seed                    Load example data";
+    opts.usage(brief)
+}
+
+fn parse_port(value: &str) -> Result<u16, anyhow::Error> {
+    value.parse::<u16>().with_context(|| format!("'{}' is not a port number", value))
+}
+
+// Unit tests
+// -------------------------------------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{load, Command, Config, Migrate};
+    use std::collections::HashMap;
+
+    fn args(args: &str) -> Vec<String> {
+        args.split_whitespace().map(|x| x.to_owned()).collect()
+    }
+
+    // Look up the given variables with the config file pointing at an empty one unless given so that a local
+    // backend.toml doesn't change the outcome
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let empty = std::env::temp_dir().join(format!("backend-test-empty-{}.toml", std::process::id()));
+        std::fs::write(&empty, "").unwrap();
+        let mut vars = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<HashMap<_, _>>();
+        vars.entry("CONFIG_FILE".into()).or_insert_with(|| empty.display().to_string());
+        move |x| vars.get(x).cloned()
+    }
+
+    #[test]
+    fn test_load() {
+        let (command, config) = load(&[], env(&[])).unwrap();
+        assert!(command == Command::Serve);
+        assert!(config == Config::default());
+        assert!(config.addr().unwrap().to_string() == "127.0.0.1:3000");
+
+        // Flags win over the environment
+        let var = env(&[("PORT", "4000"), ("HOST", "0.0.0.0"), ("CORS_ORIGINS", "http://a.com, http://b.com")]);
+        let (command, config) = load(&args("--port 5000 --static-dir dist migrate down 2"), var).unwrap();
+        assert!(command == Command::Migrate(Migrate::Down(2)));
+        assert!(config.port == 5000);
+        assert!(config.host == "0.0.0.0");
+        assert!(config.static_dir.to_str() == Some("dist"));
+        assert!(config.cors_origins == ["http://a.com", "http://b.com"]);
+
+        let (command, _) = load(&args("migrate status"), env(&[])).unwrap();
+        assert!(command == Command::Migrate(Migrate::Status));
+        let (command, _) = load(&args("seed"), env(&[])).unwrap();
+        assert!(command == Command::Seed);
+        let (command, _) = load(&args("--help"), env(&[])).unwrap();
+        assert!(matches!(command, Command::Help(usage) if usage.contains("migrate status")));
+    }
+
+    #[test]
+    fn test_file() {
+        let path = std::env::temp_dir().join(format!("backend-test-{}.toml", std::process::id()));
+        std::fs::write(&path, "port = 8080\nlog = \"info\"\ncors_origins = [\"http://localhost:8080\"]\n")
+            .unwrap();
+
+        let var = env(&[("CONFIG_FILE", path.to_str().unwrap()), ("RUST_LOG", "debug")]);
+        let (_, config) = load(&[], var).unwrap();
+        assert!(config.port == 8080);
+        assert!(config.log == "debug"); // The environment wins over the file
+        assert!(config.cors_origins == ["http://localhost:8080"]);
+        assert!(config.database_url == Config::default().database_url);
+
+        std::fs::write(&path, "prot = 8080\n").unwrap();
+        let err = load(&args(&format!("--config {}", path.display())), env(&[])).unwrap_err();
+        assert!(format!("{:#}", err).contains("unknown field"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_errors() {
+        for (args_, var) in [
+            ("--port 70000", None),
+            ("--log =[", None),
+            ("--cors-origin localhost:8080", None),
+            ("--config does-not-exist.toml", None),
+            ("--bogus", None),
+            ("migrate sideways", None),
+            ("", Some(("PORT", "abc"))),
+        ] {
+            let result = load(&args(args_), env(&var.into_iter().collect::<Vec<_>>()));
+            assert!(result.is_err(), "{} should fail", args_);
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use super::errors::*;
+use crate::model::*;
+use crate::state::AppState;
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, State},
+    http::{
+        header::{AUTHORIZATION, COOKIE, SET_COOKIE},
+        request::Parts,
+        HeaderMap, HeaderName,
+    },
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+/// Name of the cookie holding the session token
+pub const SESSION_COOKIE: &str = "session";
+
+/// User logged in with a session token sent as `Authorization: Bearer <token>` or in the session cookie
+pub struct AuthUser(pub user::Model);
+
+/// Logged in user with the admin role
+pub struct AdminUser(pub user::Model);
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthUser {
+    type Rejection = Errors;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let token = session_token(&parts.headers).ok_or(ErrorKind::Unauthorized)?; // error out if not logged in
+        let user = session::get_user(&state.db, token)
+            .await? // error out if failed on db
+            .ok_or(ErrorKind::Unauthorized)?; // error out if the session is unknown or expired
+        Ok(AuthUser(user))
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for AdminUser {
+    type Rejection = Errors;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let AuthUser(user) = AuthUser::from_request_parts(parts, state).await?;
+        match user.role {
+            user::Role::Admin => Ok(AdminUser(user)),
+            user::Role::User => Err(ErrorKind::Forbidden.into()), // error out if not allowed
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LoginDTO {
+    pub name: String,
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LoginResponse {
+    pub token: String,
+    pub user: user::Model,
+}
+
+/// Log in with a name and password, the session token is returned and set as a cookie
+pub async fn login(
+    State(state): State<AppState>, Json(dto): Json<LoginDTO>,
+) -> Result<([(HeaderName, String); 1], Json<LoginResponse>), Errors> {
+    let session = session::login(&state.db, &dto.name, &dto.password)
+        .await? // error out if failed on db
+        .ok_or(ErrorKind::Unauthorized)?; // error out if the name or password is wrong
+    let _ = session::delete_expired(&state.db).await?; // Sessions that ran out are left behind otherwise
+    let user = user::get_by_id(&state.db, session.user_id)
+        .await? // error out if failed on db
+        .ok_or(ErrorKind::InternalServerError)?;
+    let cookie = format!(
+        "{}={}; Path=/; HttpOnly; SameSite=Strict; Max-Age={}",
+        SESSION_COOKIE,
+        session.token,
+        session::SESSION_DAYS * 24 * 60 * 60
+    );
+    Ok(([(SET_COOKIE, cookie)], Json(LoginResponse { token: session.token, user })))
+}
+
+/// Log out by ending the session the request was made with and clearing the cookie
+pub async fn logout(
+    State(state): State<AppState>, headers: HeaderMap,
+) -> Result<[(HeaderName, String); 1], Errors> {
+    let token = session_token(&headers).ok_or(ErrorKind::Unauthorized)?; // error out if not logged in
+    let _ = session::delete(&state.db, token).await?;
+    Ok([(SET_COOKIE, format!("{}=; Path=/; HttpOnly; SameSite=Strict; Max-Age=0", SESSION_COOKIE))])
+}
+
+/// Get the logged in user
+pub async fn me(AuthUser(user): AuthUser) -> Json<user::Model> {
+    Json(user)
+}
+
+// Get the session token from the authorization header falling back on the session cookie
+fn session_token(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers
+        .get(AUTHORIZATION)
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.strip_prefix("Bearer "))
+        .map(str::trim);
+    bearer.or_else(|| {
+        headers
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|x| x.to_str().ok())
+            .flat_map(|x| x.split(';'))
+            .filter_map(|x| x.trim().split_once('='))
+            .find(|(name, _)| *name == SESSION_COOKIE)
+            .map(|(_, token)| token)
+    })
+}
+
+/// Log in as a new admin and get the authorization header value to make requests with
+#[cfg(test)]
+pub async fn test_admin(db: &sea_orm::DatabaseConnection) -> String {
+    let events = Events::default();
+    let admin = user::create(db, &events, "admin").await.unwrap();
+    user::set_role(db, &events, admin.id, user::Role::Admin).await.unwrap();
+    test_login(db, admin.id).await
+}
+
+/// Log in as the given user and get the authorization header value to make requests with
+#[cfg(test)]
+pub async fn test_login(db: &sea_orm::DatabaseConnection, user_id: i32) -> String {
+    format!("Bearer {}", session::create(db, user_id).await.unwrap().token)
+}
+
+// Unit tests
+// -------------------------------------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{LoginDTO, LoginResponse};
+    use crate::model::test_db;
+    use crate::prelude::*;
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        response::Response,
+    };
+    use hyper::header::{AUTHORIZATION, CONTENT_TYPE, COOKIE, SET_COOKIE};
+    use sea_orm::{ActiveModelTrait, ActiveValue::Set, EntityTrait};
+    use tower::ServiceExt; // for `oneshot` and `ready`
+
+    async fn login(db: &DatabaseConnection, name: &str, password: &str) -> Response {
+        let dto = LoginDTO { name: name.into(), password: password.into() };
+        app(db.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/login")
+                    .method("POST")
+                    .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(serde_json::to_string(&dto).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn me(db: &DatabaseConnection, header: (&str, &str)) -> Response {
+        app(db.clone())
+            .oneshot(Request::builder().uri("/api/me").header(header.0, header.1).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_login_logout() {
+        let db = test_db().await;
+        let events = Events::default();
+        user::create(&db, &events, "foo").await.unwrap();
+        user::set_password(&db, 1, "secret").await.unwrap();
+
+        let mut expired: session::ActiveModel = session::create(&db, 1).await.unwrap().into();
+        expired.expires_at = Set(model::now());
+        expired.update(&db).await.unwrap();
+
+        let res = login(&db, "foo", "wrong").await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        // Logging in clears out the expired session
+        let res = login(&db, "foo", "secret").await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(session::Entity::find().all(&db).await.unwrap().len() == 1);
+        let cookie = res.headers()[SET_COOKIE].to_str().unwrap().to_owned();
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(json["user"].get("password_hash").is_none());
+        let login: LoginResponse = serde_json::from_value(json).unwrap();
+        assert!(login.user.name == "foo");
+        assert!(cookie.starts_with(&format!("session={};", login.token)));
+
+        // Both the bearer token and the cookie identify the user
+        let bearer = format!("Bearer {}", login.token);
+        let cookie = format!("theme=dark; session={}", login.token);
+        for header in [("authorization", bearer.as_str()), ("cookie", cookie.as_str())] {
+            let res = me(&db, header).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+            let user: user::Model = serde_json::from_slice(&body).unwrap();
+            assert!(user.id == 1);
+        }
+
+        let res = app(db.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/logout")
+                    .method("POST")
+                    .header(COOKIE, &cookie)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers()[SET_COOKIE].to_str().unwrap().contains("Max-Age=0"));
+
+        // Session no longer works
+        let res = me(&db, ("authorization", &bearer)).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_authorization() {
+        let db = test_db().await;
+        let events = Events::default();
+        let admin = handlers::test_admin(&db).await;
+        user::create(&db, &events, "foo").await.unwrap();
+        let user = handlers::test_login(&db, 2).await;
+
+        // Not logged in
+        let res = app(db.clone())
+            .oneshot(Request::builder().uri("/api/user").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(error["error"] == "Unauthorized");
+
+        let res = me(&db, ("authorization", "Bearer unknown")).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        // Regular users can look but not change things only admins can
+        let res = app(db.clone())
+            .oneshot(Request::builder().uri("/api/user").header(AUTHORIZATION, &user).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        for (method, uri, body) in [
+            ("DELETE", "/api/user/1", ""),
+            ("POST", "/api/category", r#"{ "name": "category1", "value": 1 }"#),
+            ("POST", "/api/points", r#"{ "value": 5, "user_id": 2, "category_id": 1 }"#),
+            ("PUT", "/api/rewards/1", r#"{ "id": 1, "value": 5, "user_id": 2 }"#),
+        ] {
+            let res = app(db.clone())
+                .oneshot(
+                    Request::builder()
+                        .uri(uri)
+                        .method(method)
+                        .header(AUTHORIZATION, &user)
+                        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                        .body(Body::from(body))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(res.status(), StatusCode::FORBIDDEN, "{} {}", method, uri);
+        }
+
+        // Admins can
+        let res = app(db.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/category")
+                    .method("POST")
+                    .header(AUTHORIZATION, &admin)
+                    .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(r#"{ "name": "category1", "value": 1 }"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(user::get(&db).await.unwrap().len() == 2);
+    }
+}
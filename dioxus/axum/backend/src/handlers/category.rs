@@ -0,0 +1,244 @@
+use super::{auth::*, errors::*, validate::*};
+use crate::model::list::{Listing, Page};
+use crate::model::*;
+use crate::state::AppState;
+use axum::{
+    async_trait,
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CategoryDTO {
+    pub name: String,
+    pub value: i32,
+}
+
+#[async_trait]
+impl Validate for CategoryDTO {
+    async fn validate(&mut self, v: &mut Validator<'_>) -> Result<(), Errors> {
+        trim(&mut self.name);
+        v.length("name", &self.name, 1..=64).range("value", self.value, 1..);
+        Ok(())
+    }
+}
+
+/// What to do with the points of a category being deleted. Without either option deleting a category that
+/// still has points is rejected with a conflict.
+/// * `cascade=true` deletes the points along with the category
+/// * `reassign=<id>` moves the points to the given category
+#[derive(Debug, Default, Deserialize)]
+pub struct DeleteCategoryQuery {
+    pub cascade: Option<bool>,
+    pub reassign: Option<i32>,
+}
+
+/// Get a specified category
+pub async fn category(
+    _: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<category::Model>, Errors> {
+    Ok(Json(
+        category::get_by_id(&state.db, id)
+            .await? // error out if failed on db
+            .ok_or(ErrorKind::NotFound)?, // error out if not found
+    ))
+}
+
+/// Get a page of categories optionally only those whose name contains the search text
+pub async fn categories(
+    _: AuthUser, State(state): State<AppState>, listing: Listing<category::Entity>,
+) -> Result<Page<category::Model>, Errors> {
+    Ok(list::list(&state.db, &listing).await?)
+}
+
+/// Create a new category if the name is not a duplicate of an existing one
+pub async fn create_category(
+    _: AdminUser, State(state): State<AppState>, Valid(dto): Valid<CategoryDTO>,
+) -> Result<Json<category::Model>, Errors> {
+    category::get_by_name(&state.db, &dto.name)
+        .await? // error out if failed on db
+        .map_or_else(|| Ok(false), |_| Err(ErrorKind::Conflict))?; // error out if exists
+    Ok(Json(category::create(&state.db, &dto.name, dto.value).await?))
+}
+
+/// Update the given category unless another category already has the new name
+pub async fn update_category(
+    _: AdminUser, State(state): State<AppState>, Path(id): Path<i32>, Valid(dto): Valid<CategoryDTO>,
+) -> Result<Json<category::Model>, Errors> {
+    category::get_by_id(&state.db, id)
+        .await? // error out if failed on db
+        .ok_or(ErrorKind::NotFound)?; // error out if not found
+    category::get_by_name(&state.db, &dto.name)
+        .await? // error out if failed on db
+        .filter(|x| x.id != id)
+        .map_or_else(|| Ok(false), |_| Err(ErrorKind::Conflict))?; // error out if another category has the name
+    Ok(Json(category::update(&state.db, id, &dto.name, dto.value).await?))
+}
+
+/// Delete the given category applying the requested policy to any points in it
+pub async fn delete_category(
+    _: AdminUser, State(state): State<AppState>, Path(id): Path<i32>, Query(query): Query<DeleteCategoryQuery>,
+) -> Result<(), Errors> {
+    category::get_by_id(&state.db, id)
+        .await? // error out if failed on db
+        .ok_or(ErrorKind::NotFound)?; // error out if not found
+
+    let _ = match (query.cascade.unwrap_or(false), query.reassign) {
+        (true, Some(_)) => {
+            // error out if both policies were asked for
+            return Err(ErrorKind::BadRequest.details("cascade and reassign can't be used together"));
+        },
+        (true, None) => category::delete_with_points(&state.db, &state.events, id).await,
+        (false, Some(to)) => {
+            category::get_by_id(&state.db, to)
+                .await? // error out if failed on db
+                .filter(|x| x.id != id)
+                .ok_or(ErrorKind::BadRequest)?; // error out if there's nowhere to move the points to
+            category::delete_reassigning_points(&state.db, &state.events, id, to).await
+        },
+        (false, None) => {
+            let count = category::count_points(&state.db, id).await?;
+            if count > 0 {
+                return Err(ErrorKind::Conflict.details(json!({ "points": count })));
+                // error out if points would be orphaned
+            }
+            category::delete_by_id(&state.db, id).await
+        },
+    }?;
+    Ok(())
+}
+
+// Unit tests
+// -------------------------------------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::CategoryDTO;
+    use crate::model::test_db;
+    use crate::prelude::*;
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
+    use tower::ServiceExt; // for `oneshot` and `ready`
+
+    fn send(auth: &str, method: &str, uri: &str, dto: Option<&CategoryDTO>) -> Request<Body> {
+        let body = dto.map_or_else(Body::empty, |x| Body::from(serde_json::to_string(x).unwrap()));
+        Request::builder()
+            .uri(uri)
+            .method(method)
+            .header(AUTHORIZATION, auth)
+            .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(body)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_create_category() {
+        let db = test_db().await;
+        let auth = handlers::test_admin(&db).await;
+        let dto = CategoryDTO { name: "chores".into(), value: 5 };
+
+        let res = app(db.clone()).oneshot(send(&auth, "POST", "/api/category", Some(&dto))).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let category1: category::Model = serde_json::from_slice(&body).unwrap();
+
+        // Read from the db and compare
+        let category2 = category::get_by_name(&db, "chores").await.unwrap().unwrap();
+        assert!(category1.id == 1);
+        assert!(category1 == category2);
+        assert!(category2.value == 5);
+
+        // Duplicate name
+        let res = app(db.clone()).oneshot(send(&auth, "POST", "/api/category", Some(&dto))).await.unwrap();
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        assert!(category::get(&db).await.unwrap().len() == 1);
+    }
+
+    #[tokio::test]
+    async fn test_update_category() {
+        let db = test_db().await;
+        category::create(&db, "category1", 1).await.unwrap();
+        category::create(&db, "category2", 10).await.unwrap();
+        let auth = handlers::test_admin(&db).await;
+
+        // Keeping the same name is fine
+        let dto = CategoryDTO { name: "category1".into(), value: 2 };
+        let res = app(db.clone()).oneshot(send(&auth, "PUT", "/api/category/1", Some(&dto))).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let category: category::Model = serde_json::from_slice(&body).unwrap();
+        assert!(category.id == 1);
+        assert!(category.value == 2);
+
+        let dto = CategoryDTO { name: "category3".into(), value: 3 };
+        let res = app(db.clone()).oneshot(send(&auth, "PUT", "/api/category/1", Some(&dto))).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let category = category::get_by_id(&db, 1).await.unwrap().unwrap();
+        assert!(category.name == "category3");
+        assert!(category.value == 3);
+
+        // Taking another category's name
+        let dto = CategoryDTO { name: "category2".into(), value: 3 };
+        let res = app(db.clone()).oneshot(send(&auth, "PUT", "/api/category/1", Some(&dto))).await.unwrap();
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+
+        let res = app(db.clone()).oneshot(send(&auth, "PUT", "/api/category/9", Some(&dto))).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_delete_category() {
+        let db = test_db().await;
+        let events = Events::default();
+        user::create(&db, &events, "user1").await.unwrap();
+        category::create(&db, "category1", 1).await.unwrap();
+        category::create(&db, "category2", 10).await.unwrap();
+        category::create(&db, "category3", 100).await.unwrap();
+        points::create(&db, &events, 1, 1, 10).await.unwrap();
+        points::create(&db, &events, 1, 2, 20).await.unwrap();
+        let auth = handlers::test_admin(&db).await;
+
+        // Rejected while it has points
+        let res = app(db.clone()).oneshot(send(&auth, "DELETE", "/api/category/1", None)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        assert!(category::get_by_id(&db, 1).await.unwrap().is_some());
+
+        // Conflicting or invalid policies
+        for uri in [
+            "/api/category/1?cascade=true&reassign=2",
+            "/api/category/1?reassign=1",
+            "/api/category/1?reassign=9",
+        ] {
+            let res = app(db.clone()).oneshot(send(&auth, "DELETE", uri, None)).await.unwrap();
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        }
+        assert!(points::get(&db).await.unwrap().len() == 2);
+
+        // Reassign
+        let res =
+            app(db.clone()).oneshot(send(&auth, "DELETE", "/api/category/1?reassign=2", None)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(category::get_by_id(&db, 1).await.unwrap().is_none());
+        assert!(points::get_by_category_id(&db, 2).await.unwrap().len() == 2);
+
+        // Cascade
+        let res =
+            app(db.clone()).oneshot(send(&auth, "DELETE", "/api/category/2?cascade=true", None)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(category::get_by_id(&db, 2).await.unwrap().is_none());
+        assert!(points::get(&db).await.unwrap().is_empty());
+
+        // No points
+        let res = app(db.clone()).oneshot(send(&auth, "DELETE", "/api/category/3", None)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(category::get(&db).await.unwrap().is_empty());
+
+        let res = app(db.clone()).oneshot(send(&auth, "DELETE", "/api/category/3", None)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+}
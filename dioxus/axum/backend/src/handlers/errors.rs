@@ -1,24 +1,219 @@
 use axum::{http::StatusCode, response::IntoResponse, Json};
-use serde_json::json;
+use sea_orm::{DbErr, RuntimeErr, SqlxError};
+use serde_json::{json, Value};
+use tracing::{error, warn};
 
-pub enum Errors {
+// SQLite extended result codes for constraint violations
+const SQLITE_CONSTRAINT_FOREIGNKEY: &str = "787";
+const SQLITE_CONSTRAINT_PRIMARYKEY: &str = "1555";
+const SQLITE_CONSTRAINT_UNIQUE: &str = "2067";
+
+/// Kind of error deciding the status code and `code` of the response
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
     BadRequest,
     Conflict,
+    Forbidden,
     NotFound,
+    Unauthorized,
+    UnprocessableEntity,
+    UnsupportedMediaType,
     InternalServerError,
 }
 
+impl ErrorKind {
+    /// Status code the error is returned with
+    pub fn status(self) -> StatusCode {
+        match self {
+            Self::BadRequest => StatusCode::BAD_REQUEST,
+            Self::Conflict => StatusCode::CONFLICT,
+            Self::Forbidden => StatusCode::FORBIDDEN,
+            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::UnprocessableEntity => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Machine readable code of the error e.g. `not_found`
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::BadRequest => "bad_request",
+            Self::Conflict => "conflict",
+            Self::Forbidden => "forbidden",
+            Self::NotFound => "not_found",
+            Self::Unauthorized => "unauthorized",
+            Self::UnprocessableEntity => "unprocessable_entity",
+            Self::UnsupportedMediaType => "unsupported_media_type",
+            Self::InternalServerError => "internal_server_error",
+        }
+    }
+
+    /// Create an error of this kind with details for the client
+    pub fn details(self, details: impl Into<Value>) -> Errors {
+        Errors::from(self).details(details)
+    }
+}
+
+/// Error returned from handlers. The source is logged but only the details are shown to the client e.g.
+/// `{"error": "Conflict", "code": "conflict", "details": "UNIQUE constraint failed: user.name"}`
+#[derive(Debug)]
+pub struct Errors {
+    pub kind: ErrorKind,
+    pub details: Option<Value>,        // Shown to the client
+    pub source: Option<anyhow::Error>, // Logged with the request it failed
+}
+
+impl Errors {
+    /// Add details for the client
+    pub fn details(mut self, details: impl Into<Value>) -> Self {
+        self.details = Some(details.into());
+        self
+    }
+}
+
+impl From<ErrorKind> for Errors {
+    fn from(kind: ErrorKind) -> Self {
+        Self { kind, details: None, source: None }
+    }
+}
+
+/// Maps not found and constraint violations to client errors, anything else is an internal error
+impl From<DbErr> for Errors {
+    fn from(err: DbErr) -> Self {
+        let kind = match &err {
+            DbErr::RecordNotFound(_) | DbErr::RecordNotUpdated => ErrorKind::NotFound,
+            DbErr::Exec(RuntimeErr::SqlxError(SqlxError::Database(e)))
+            | DbErr::Query(RuntimeErr::SqlxError(SqlxError::Database(e))) => match e.code().as_deref() {
+                Some(SQLITE_CONSTRAINT_UNIQUE | SQLITE_CONSTRAINT_PRIMARYKEY) => ErrorKind::Conflict,
+                Some(SQLITE_CONSTRAINT_FOREIGNKEY) => ErrorKind::BadRequest,
+                _ => ErrorKind::InternalServerError,
+            },
+            _ => ErrorKind::InternalServerError,
+        };
+
+        // Tell the client what went wrong unless it's something on our end
+        let details = match kind {
+            ErrorKind::InternalServerError => None,
+            _ => Some(Value::from(match &err {
+                DbErr::Exec(RuntimeErr::SqlxError(SqlxError::Database(e)))
+                | DbErr::Query(RuntimeErr::SqlxError(SqlxError::Database(e))) => e.message().to_owned(),
+                _ => err.to_string(),
+            })),
+        };
+        Self { kind, details, source: Some(err.into()) }
+    }
+}
+
+/// Database errors are mapped as for [`DbErr`], any other error is an internal error
+impl From<anyhow::Error> for Errors {
+    fn from(err: anyhow::Error) -> Self {
+        match err.downcast::<DbErr>() {
+            Ok(err) => err.into(),
+            Err(err) => Self { kind: ErrorKind::InternalServerError, details: None, source: Some(err) },
+        }
+    }
+}
+
 /// Provides a custom json formatted error message
 impl IntoResponse for Errors {
     fn into_response(self) -> axum::response::Response {
-        let (status, error_message) = match self {
-            Self::BadRequest => (StatusCode::BAD_REQUEST, "Bad Request"),
-            Self::Conflict => (StatusCode::CONFLICT, "Conflict"),
-            Self::InternalServerError => (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error"),
-            Self::NotFound => (StatusCode::NOT_FOUND, "Not Found"),
-        };
+        // Logged within the request's span so it comes with the request id
+        match (&self.source, self.kind) {
+            (Some(source), ErrorKind::InternalServerError) => error!("{:?}", source),
+            (None, ErrorKind::InternalServerError) => error!("Internal server error without a source"),
+            (Some(source), _) => warn!("{}", source),
+            (None, _) => {},
+        }
+
+        let status = self.kind.status();
+        let error = status.canonical_reason().unwrap_or_default();
+        let body = json!({ "error": error, "code": self.kind.code(), "details": self.details });
+        (status, Json(body)).into_response()
+    }
+}
+
+// Unit tests
+// -------------------------------------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{ErrorKind, Errors};
+    use crate::model::test_db;
+    use crate::prelude::*;
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        response::IntoResponse,
+    };
+    use hyper::header::AUTHORIZATION;
+    use tower::ServiceExt; // for `oneshot` and `ready`
+
+    async fn error_json(err: Errors) -> (ErrorKind, serde_json::Value) {
+        let kind = err.kind;
+        let res = err.into_response();
+        assert_eq!(res.status(), kind.status());
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        (kind, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_json() {
+        let (_, json) = error_json(ErrorKind::NotFound.into()).await;
+        assert!(json == serde_json::json!({ "error": "Not Found", "code": "not_found", "details": null }));
+
+        let (_, json) = error_json(ErrorKind::Conflict.details(serde_json::json!({ "balance": 4 }))).await;
+        assert!(json["error"] == "Conflict");
+        assert!(json["details"]["balance"] == 4);
+
+        // Internal details are never shown
+        let (kind, json) = error_json(anyhow::anyhow!("secret").into()).await;
+        assert!(kind == ErrorKind::InternalServerError);
+        assert!(json["code"] == "internal_server_error");
+        assert!(json["details"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_db_errors() {
+        let db = test_db().await;
+        let events = Events::default();
+        user::create(&db, &events, "user1").await.unwrap();
+        category::create(&db, "category1", 1).await.unwrap();
+
+        // Unique violation
+        let err = Errors::from(user::create(&db, &events, "user1").await.unwrap_err());
+        assert!(err.kind == ErrorKind::Conflict);
+        assert!(err.details.unwrap().as_str().unwrap().contains("user.name"));
+
+        // Foreign key violation
+        let err = Errors::from(points::create(&db, &events, 9, 1, 10).await.unwrap_err());
+        assert!(err.kind == ErrorKind::BadRequest);
+
+        // Record not updated
+        let err = Errors::from(category::update(&db, 9, "category9", 1).await.unwrap_err());
+        assert!(err.kind == ErrorKind::NotFound);
+
+        let err = Errors::from(DbErr::Custom("oops".into()));
+        assert!(err.kind == ErrorKind::InternalServerError);
+        assert!(err.details.is_none());
+        assert!(err.source.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_response() {
+        let db = test_db().await;
+        let auth = handlers::test_admin(&db).await;
 
-        // Custom json formatted error message
-        (status, Json(json!({ "error": error_message }))).into_response()
+        let res = app(db.clone())
+            .oneshot(
+                Request::builder().uri("/api/points/9").header(AUTHORIZATION, &auth).body(Body::empty()).unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert!(res.headers()[crate::REQUEST_ID].len() == 36); // A UUID to find the request in the logs with
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(json["code"] == "not_found");
     }
 }
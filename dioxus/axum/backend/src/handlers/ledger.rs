@@ -0,0 +1,137 @@
+use super::{auth::*, errors::*};
+use crate::model::*;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use sea_orm::DatabaseConnection;
+
+/// Get the specified user's points per category, total redeemed and current balance
+pub async fn get_balance(
+    _: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<ledger::Balance>, Errors> {
+    check_user(&state.db, id).await?;
+    Ok(Json(ledger::balance(&state.db, id).await?))
+}
+
+/// Get the specified user's points and rewards as one history with a running balance
+pub async fn get_ledger(
+    _: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<Vec<ledger::LedgerEntry>>, Errors> {
+    check_user(&state.db, id).await?;
+    Ok(Json(ledger::ledger(&state.db, id).await?))
+}
+
+// Error out with not found if the user doesn't exist
+async fn check_user(db: &DatabaseConnection, id: i32) -> Result<(), Errors> {
+    user::get_by_id(db, id)
+        .await? // error out if failed on db
+        .ok_or(ErrorKind::NotFound)?; // error out if not found
+    Ok(())
+}
+
+// Unit tests
+// -------------------------------------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use crate::model::ledger::{Balance, EntryKind, LedgerEntry};
+    use crate::model::test_db;
+    use crate::prelude::*;
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use hyper::header::AUTHORIZATION;
+    use tower::ServiceExt; // for `oneshot` and `ready`
+
+    #[tokio::test]
+    async fn test_get_balance() {
+        let db = test_db().await;
+        let events = Events::default();
+        user::create(&db, &events, "user1").await.unwrap();
+        category::create(&db, "category1", 1).await.unwrap();
+        category::create(&db, "category2", 10).await.unwrap();
+        points::create(&db, &events, 1, 1, 10).await.unwrap();
+        points::create(&db, &events, 1, 2, 20).await.unwrap();
+        rewards::create(&db, 1, 5).await.unwrap();
+        let auth = handlers::test_admin(&db).await;
+
+        let res = app(db.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/user/1/balance")
+                    .header(AUTHORIZATION, &auth)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let balance: Balance = serde_json::from_slice(&body).unwrap();
+        assert!(balance.categories.len() == 2);
+        assert!(balance.categories[1].name == "category2");
+        assert!(balance.categories[1].total == 20);
+        assert!(balance.earned == 30);
+        assert!(balance.redeemed == 5);
+        assert!(balance.balance == 25);
+
+        let res = app(db.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/user/9/balance")
+                    .header(AUTHORIZATION, &auth)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_ledger() {
+        let db = test_db().await;
+        let events = Events::default();
+        user::create(&db, &events, "user1").await.unwrap();
+        category::create(&db, "category1", 1).await.unwrap();
+        points::create(&db, &events, 1, 1, 10).await.unwrap();
+        rewards::create(&db, 1, 5).await.unwrap();
+        let auth = handlers::test_admin(&db).await;
+
+        let res = app(db.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/user/1/ledger")
+                    .header(AUTHORIZATION, &auth)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(json[1]["kind"] == "reward");
+        let ledger: Vec<LedgerEntry> = serde_json::from_value(json).unwrap();
+        assert!(ledger.len() == 2);
+        assert!(ledger[0].kind == EntryKind::Points);
+        assert!(ledger[0].balance == 10);
+        assert!(ledger[1].balance == 5);
+
+        let res = app(db.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/user/9/ledger")
+                    .header(AUTHORIZATION, &auth)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,148 @@
+use super::{errors::*, validate::Validator};
+use crate::model::list::{Listable, Listing, Page};
+use crate::state::AppState;
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::{request::Parts, HeaderName, HeaderValue},
+    response::{IntoResponse, Response},
+    Json,
+};
+use sea_orm::Order;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Header holding the number of rows across all pages
+pub const TOTAL_COUNT: HeaderName = HeaderName::from_static("x-total-count");
+
+/// Header holding the number of pages
+pub const TOTAL_PAGES: HeaderName = HeaderName::from_static("x-total-pages");
+
+// Rows per page if not given and the most that can be asked for
+const PER_PAGE: i32 = 20;
+const MAX_PER_PAGE: i32 = 100;
+
+// Query string of list endpoints e.g. `?page=2&per_page=20&sort=created_at&order=desc&q=foo`
+#[derive(Debug, Deserialize)]
+struct ListQuery {
+    page: Option<i32>,
+    per_page: Option<i32>,
+    sort: Option<String>,
+    order: Option<String>,
+    q: Option<String>,
+}
+
+/// Reads the page, sorting and search from the query string. Sorts by id in ascending order if not given.
+#[async_trait]
+impl<E: Listable> FromRequestParts<AppState> for Listing<E> {
+    type Rejection = Errors;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<ListQuery>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| ErrorKind::BadRequest.details(e.body_text()))?;
+
+        let mut v = Validator::new(&state.db);
+        let page = query.page.unwrap_or(1);
+        let per_page = query.per_page.unwrap_or(PER_PAGE);
+        v.range("page", page, 1..).range("per_page", per_page, 1..=MAX_PER_PAGE);
+
+        let sort = query.sort.as_deref().unwrap_or("id");
+        let column = match E::SORT.contains(&sort) {
+            true => E::Column::from_str(sort).ok(),
+            false => None,
+        };
+        if column.is_none() {
+            v.error("sort", format!("must be one of {}", E::SORT.join(", ")));
+        }
+        let order = match query.order.as_deref() {
+            None | Some("asc") => Order::Asc,
+            Some("desc") => Order::Desc,
+            Some(_) => {
+                v.error("order", "must be one of asc, desc");
+                Order::Asc
+            },
+        };
+        v.finish()?;
+
+        Ok(Listing {
+            page: page as u64,
+            per_page: per_page as u64,
+            sort: column.expect("checked by the validator"),
+            order,
+            q: query.q.filter(|x| !x.trim().is_empty()),
+        })
+    }
+}
+
+/// Returns the rows as a JSON array with the totals in the `x-total-count` and `x-total-pages` headers
+impl<T: Serialize> IntoResponse for Page<T> {
+    fn into_response(self) -> Response {
+        let headers = [
+            (TOTAL_COUNT, HeaderValue::from(self.total)),
+            (TOTAL_PAGES, HeaderValue::from(self.pages)),
+        ];
+        (headers, Json(self.items)).into_response()
+    }
+}
+
+// Unit tests
+// -------------------------------------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{TOTAL_COUNT, TOTAL_PAGES};
+    use crate::model::test_db;
+    use crate::prelude::*;
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        response::Response,
+    };
+    use hyper::header::AUTHORIZATION;
+    use tower::ServiceExt; // for `oneshot` and `ready`
+
+    async fn get(db: &DatabaseConnection, auth: &str, uri: &str) -> Response {
+        app(db.clone())
+            .oneshot(Request::builder().uri(uri).header(AUTHORIZATION, auth).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_listing() {
+        let db = test_db().await;
+        for i in 1..=25 {
+            category::create(&db, &format!("category{:02}", i), i).await.unwrap();
+        }
+        let auth = handlers::test_admin(&db).await;
+
+        // 20 per page by default
+        let res = get(&db, &auth, "/api/category").await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers()[TOTAL_COUNT] == "25");
+        assert!(res.headers()[TOTAL_PAGES] == "2");
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let categories: Vec<category::Model> = serde_json::from_slice(&body).unwrap();
+        assert!(categories.len() == 20);
+        assert!(categories[0].id == 1);
+
+        let res = get(&db, &auth, "/api/category?page=2&per_page=4&sort=value&order=desc&q=category1").await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers()[TOTAL_COUNT] == "10");
+        assert!(res.headers()[TOTAL_PAGES] == "3");
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let categories: Vec<category::Model> = serde_json::from_slice(&body).unwrap();
+        assert!(categories.iter().map(|x| x.value).collect::<Vec<_>>() == [15, 14, 13, 12]);
+
+        let res = get(&db, &auth, "/api/category?page=0&per_page=101&sort=password_hash&order=up").await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        for field in ["page", "per_page", "sort", "order"] {
+            assert!(error["details"][field].is_array());
+        }
+
+        let res = get(&db, &auth, "/api/category?page=one").await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+}
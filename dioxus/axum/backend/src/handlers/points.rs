@@ -0,0 +1,335 @@
+use super::{auth::*, errors::*, validate::*};
+use crate::model::*;
+use crate::state::AppState;
+use axum::{
+    async_trait,
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PointsDTO {
+    pub value: i32,
+    pub user_id: i32,
+    pub category_id: i32,
+}
+
+#[async_trait]
+impl Validate for PointsDTO {
+    async fn validate(&mut self, v: &mut Validator<'_>) -> Result<(), Errors> {
+        v.range("value", self.value, 1..);
+        v.exists::<user::Entity>("user_id", self.user_id).await?;
+        v.exists::<category::Entity>("category_id", self.category_id).await?;
+        Ok(())
+    }
+}
+
+/// Optional filters for listing points e.g. `/api/points?user_id=1&category_id=2`
+#[derive(Debug, Default, Deserialize)]
+pub struct PointsQuery {
+    pub user_id: Option<i32>,
+    pub category_id: Option<i32>,
+}
+
+/// Get all points optionally only those of the given user and/or category
+pub async fn get_points(
+    _: AuthUser, State(state): State<AppState>, Query(query): Query<PointsQuery>,
+) -> Result<Json<Vec<points::Model>>, Errors> {
+    let result = match (query.user_id, query.category_id) {
+        (Some(user_id), category_id) => points::get_by_user_id(&state.db, user_id)
+            .await? // error out if failed on db
+            .into_iter()
+            .filter(|x| category_id.is_none_or(|id| x.category_id == id))
+            .collect(),
+        (None, Some(category_id)) => points::get_by_category_id(&state.db, category_id).await?,
+        (None, None) => points::get(&state.db).await?,
+    };
+    Ok(Json(result))
+}
+
+/// Get the specified points
+pub async fn get_points_by_id(
+    _: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<points::Model>, Errors> {
+    Ok(Json(
+        points::get_by_id(&state.db, id)
+            .await? // error out if failed on db
+            .ok_or(ErrorKind::NotFound)?, // error out if not found
+    ))
+}
+
+/// Award points to a user in a category if both exist
+pub async fn create_points(
+    _: AdminUser, State(state): State<AppState>, Valid(dto): Valid<PointsDTO>,
+) -> Result<Json<points::Model>, Errors> {
+    Ok(Json(points::create(&state.db, &state.events, dto.user_id, dto.category_id, dto.value).await?))
+}
+
+/// Update the specified points if the user and category they refer to exist
+pub async fn update_points(
+    _: AdminUser, State(state): State<AppState>, Path(id): Path<i32>, Valid(dto): Valid<PointsDTO>,
+) -> Result<Json<points::Model>, Errors> {
+    points::get_by_id(&state.db, id)
+        .await? // error out if failed on db
+        .ok_or(ErrorKind::NotFound)?; // error out if not found
+    Ok(Json(points::update(&state.db, &state.events, id, dto.user_id, dto.category_id, dto.value).await?))
+}
+
+/// Delete the specified points
+pub async fn delete_points(
+    _: AdminUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<(), Errors> {
+    points::get_by_id(&state.db, id)
+        .await? // error out if failed on db
+        .ok_or(ErrorKind::NotFound)?; // error out if not found
+    let _ = points::delete_by_id(&state.db, &state.events, id).await?;
+    Ok(())
+}
+
+// Unit tests
+// -------------------------------------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::PointsDTO;
+    use crate::model::test_db;
+    use crate::prelude::*;
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
+    use tower::ServiceExt; // for `oneshot` and `ready`
+
+    // Two users with points in two categories and an admin to make requests as
+    async fn load(db: &DatabaseConnection) -> String {
+        let events = Events::default();
+        user::create(db, &events, "user1").await.unwrap();
+        user::create(db, &events, "user2").await.unwrap();
+        category::create(db, "category1", 1).await.unwrap();
+        category::create(db, "category2", 10).await.unwrap();
+        points::create(db, &events, 1, 1, 10).await.unwrap();
+        points::create(db, &events, 1, 2, 20).await.unwrap();
+        points::create(db, &events, 2, 2, 30).await.unwrap();
+        handlers::test_admin(db).await
+    }
+
+    async fn get_points(db: &DatabaseConnection, auth: &str, uri: &str) -> Vec<points::Model> {
+        let res = app(db.clone())
+            .oneshot(Request::builder().uri(uri).header(AUTHORIZATION, auth).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_points() {
+        let db = test_db().await;
+        let auth = load(&db).await;
+
+        let points = get_points(&db, &auth, "/api/points").await;
+        assert!(points.len() == 3);
+
+        let points = get_points(&db, &auth, "/api/points?user_id=1").await;
+        assert!(points.len() == 2);
+        assert!(points.iter().all(|x| x.user_id == 1));
+
+        let points = get_points(&db, &auth, "/api/points?category_id=2").await;
+        assert!(points.len() == 2);
+        assert!(points.iter().all(|x| x.category_id == 2));
+
+        let points = get_points(&db, &auth, "/api/points?user_id=1&category_id=2").await;
+        assert!(points.len() == 1);
+        assert!(points[0].value == 20);
+
+        let res = app(db.clone())
+            .oneshot(
+                Request::builder().uri("/api/points/2").header(AUTHORIZATION, &auth).body(Body::empty()).unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let points: points::Model = serde_json::from_slice(&body).unwrap();
+        assert!(points.id == 2);
+        assert!(points.value == 20);
+
+        let res = app(db.clone())
+            .oneshot(
+                Request::builder().uri("/api/points/4").header(AUTHORIZATION, &auth).body(Body::empty()).unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_create_points() {
+        let db = test_db().await;
+        let auth = load(&db).await;
+
+        let res = app(db.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/points")
+                    .method("POST")
+                    .header(AUTHORIZATION, &auth)
+                    .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(
+                        serde_json::to_string(&PointsDTO { value: 5, user_id: 2, category_id: 1 }).unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let points1: points::Model = serde_json::from_slice(&body).unwrap();
+
+        // Read from the db and compare
+        let points2 = points::get_by_id(&db, 4).await.unwrap().unwrap();
+        assert!(points1.id == 4);
+        assert!(points1 == points2);
+        assert!(points2.user_id == 2);
+        assert!(points2.category_id == 1);
+        assert!(points2.value == 5);
+    }
+
+    #[tokio::test]
+    async fn test_create_points_missing_references() {
+        let db = test_db().await;
+        let auth = load(&db).await;
+
+        for dto in [PointsDTO { value: 5, user_id: 9, category_id: 1 }, PointsDTO {
+            value: 5,
+            user_id: 1,
+            category_id: 3,
+        }] {
+            let res = app(db.clone())
+                .oneshot(
+                    Request::builder()
+                        .uri("/api/points")
+                        .method("POST")
+                        .header(AUTHORIZATION, &auth)
+                        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                        .body(Body::from(serde_json::to_string(&dto).unwrap()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+            let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+            let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert!(error["code"] == "unprocessable_entity");
+            let field = if dto.user_id == 9 { "user_id" } else { "category_id" };
+            assert!(error["details"][field][0] == "does not exist");
+        }
+
+        // Nothing was written
+        assert!(points::get(&db).await.unwrap().len() == 3);
+    }
+
+    #[tokio::test]
+    async fn test_update_points() {
+        let db = test_db().await;
+        let auth = load(&db).await;
+
+        let res = app(db.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/points/1")
+                    .method("PUT")
+                    .header(AUTHORIZATION, &auth)
+                    .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(r#"{ "value": 50, "user_id": 2, "category_id": 2 }"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let points1: points::Model = serde_json::from_slice(&body).unwrap();
+
+        // Read from the db and compare
+        let points2 = points::get_by_id(&db, 1).await.unwrap().unwrap();
+        assert!(points1.id == 1);
+        assert!(points1.value == 50);
+        assert!(points2.value == 50);
+        assert!(points2.user_id == 2);
+        assert!(points2.category_id == 2);
+
+        // Unknown points and unknown references
+        let res = app(db.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/points/9")
+                    .method("PUT")
+                    .header(AUTHORIZATION, &auth)
+                    .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(r#"{ "value": 50, "user_id": 2, "category_id": 2 }"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let res = app(db.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/points/1")
+                    .method("PUT")
+                    .header(AUTHORIZATION, &auth)
+                    .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(r#"{ "value": 50, "user_id": 9, "category_id": 2 }"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn test_delete_points() {
+        let db = test_db().await;
+        let auth = load(&db).await;
+
+        let res = app(db.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/points/1")
+                    .header(AUTHORIZATION, &auth)
+                    .method("DELETE")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        assert!(body.is_empty());
+
+        // Check that it no longer exists in db
+        let points = points::get(&db).await.unwrap();
+        assert!(points.len() == 2);
+        assert!(points.iter().all(|x| x.id != 1));
+
+        let res = app(db.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/points/1")
+                    .header(AUTHORIZATION, &auth)
+                    .method("DELETE")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -1,62 +1,415 @@
-use super::errors::*;
+use super::{auth::*, errors::*, validate::*};
+use crate::model::list::{Listing, Page};
 use crate::model::*;
 use crate::state::AppState;
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::{Html, IntoResponse},
+    async_trait,
+    extract::{Path, State},
     Json,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct CreateRewardDTO {
     pub value: i32,
     pub user_id: i32,
 }
 
-/// Create a new reward
+#[async_trait]
+impl Validate for CreateRewardDTO {
+    async fn validate(&mut self, v: &mut Validator<'_>) -> Result<(), Errors> {
+        v.range("value", self.value, 1..);
+        v.exists::<user::Entity>("user_id", self.user_id).await?;
+        Ok(())
+    }
+}
+
+/// Create a new reward if the user has the balance to redeem it. Only admins can redeem rewards
+/// for other users.
 pub async fn create_reward(
-    State(state): State<AppState>, Json(dto): Json<CreateRewardDTO>,
+    AuthUser(auth): AuthUser, State(state): State<AppState>, Valid(dto): Valid<CreateRewardDTO>,
 ) -> Result<Json<rewards::Model>, Errors> {
-    Ok(Json(rewards::create(&state.db, dto.user_id, dto.value).await.map_err(|_| Errors::InternalServerError)?))
+    if auth.role != user::Role::Admin && auth.id != dto.user_id {
+        return Err(ErrorKind::Forbidden.into()); // error out if redeeming for someone else
+    }
+    respond(ledger::redeem(&state.db, &state.events, dto.user_id, dto.value).await?)
 }
 
 /// Get a specified reward
 pub async fn get_reward(
-    State(state): State<AppState>, Path(id): Path<i32>,
+    _: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
 ) -> Result<Json<rewards::Model>, Errors> {
     Ok(Json(
         rewards::get_by_id(&state.db, id)
-            .await
-            .map_err(|_| Errors::InternalServerError)? // error out if failed on db
-            .ok_or_else(|| Errors::NotFound)?, // error out if not found
+            .await? // error out if failed on db
+            .ok_or(ErrorKind::NotFound)?, // error out if not found
     ))
 }
 
-/// Get all rewards
-pub async fn get_rewards(State(state): State<AppState>) -> Result<Json<Vec<rewards::Model>>, Errors> {
-    Ok(Json(rewards::get(&state.db).await.map_err(|_| Errors::InternalServerError)?))
+/// Get a page of rewards optionally only those with the given status or of users whose name contains the
+/// search text
+pub async fn get_rewards(
+    _: AuthUser, State(state): State<AppState>, listing: Listing<rewards::Entity>,
+) -> Result<Page<rewards::Model>, Errors> {
+    Ok(list::list(&state.db, &listing).await?)
 }
 
-#[derive(Debug, Deserialize)]
+/// Replaces the value and user of a reward. The id is optional but has to match the path if given.
+#[derive(Debug, Deserialize, Serialize)]
 pub struct UpdateRewardDTO {
-    pub id: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i32>,
     pub value: i32,
     pub user_id: i32,
 }
 
-/// Update the given reward
+#[async_trait]
+impl Validate for UpdateRewardDTO {
+    async fn validate(&mut self, v: &mut Validator<'_>) -> Result<(), Errors> {
+        v.range("value", self.value, 1..);
+        v.exists::<user::Entity>("user_id", self.user_id).await?;
+        Ok(())
+    }
+}
+
+/// Update the value and user of the specified reward as long as it isn't redeemed yet and doesn't overdraw the
+/// user's balance
 pub async fn update_reward(
-    State(state): State<AppState>, Json(dto): Json<UpdateRewardDTO>,
+    _: AdminUser, State(state): State<AppState>, Path(id): Path<i32>, Valid(dto): Valid<UpdateRewardDTO>,
 ) -> Result<Json<rewards::Model>, Errors> {
-    rewards::get_by_id(&state.db, dto.id)
-        .await
-        .map_err(|_| Errors::InternalServerError)? // error out if failed on db
-        .ok_or_else(|| Errors::NotFound)?; // error out if not found
-    Ok(Json(
-        rewards::update(&state.db, dto.id, dto.user_id, dto.value)
+    check_id(id, dto.id)?;
+    rewards::get_by_id(&state.db, id)
+        .await? // error out if failed on db
+        .ok_or(ErrorKind::NotFound)?; // error out if not found
+    amend(&state, id, Some(dto.user_id), Some(dto.value), None).await
+}
+
+/// Changes only the fields given of a reward. The id is optional but has to match the path if given.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct PatchRewardDTO {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<rewards::Status>,
+}
+
+#[async_trait]
+impl Validate for PatchRewardDTO {
+    async fn validate(&mut self, v: &mut Validator<'_>) -> Result<(), Errors> {
+        if let Some(value) = self.value {
+            v.range("value", value, 1..);
+        }
+        if let Some(user_id) = self.user_id {
+            v.exists::<user::Entity>("user_id", user_id).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Update the given fields of the specified reward. The status can only move forward a step at a time from
+/// requested to approved to redeemed and the value and user are fixed once redeemed.
+pub async fn patch_reward(
+    _: AdminUser, State(state): State<AppState>, Path(id): Path<i32>, Valid(dto): Valid<PatchRewardDTO>,
+) -> Result<Json<rewards::Model>, Errors> {
+    check_id(id, dto.id)?;
+    rewards::get_by_id(&state.db, id)
+        .await? // error out if failed on db
+        .ok_or(ErrorKind::NotFound)?; // error out if not found
+    amend(&state, id, dto.user_id, dto.value, dto.status).await
+}
+
+/// Delete the specified reward giving its value back to the user as long as it isn't redeemed yet
+pub async fn delete_reward(
+    _: AdminUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<(), Errors> {
+    let withdrawn = ledger::withdraw(&state.db, &state.events, id)
+        .await? // error out if failed on db
+        .ok_or(ErrorKind::NotFound)?; // error out if not found
+    respond(withdrawn).map(|_| ())
+}
+
+// Error out if the body names a different reward than the path
+fn check_id(path: i32, body: Option<i32>) -> Result<(), Errors> {
+    match body {
+        Some(id) if id != path => {
+            Err(ErrorKind::UnprocessableEntity.details(json!({ "id": ["does not match the path"] })))
+        },
+        _ => Ok(()),
+    }
+}
+
+// Apply the changes to the reward or error out if they overdraw the balance or the status forbids them
+async fn amend(
+    state: &AppState, id: i32, user_id: Option<i32>, value: Option<i32>, status: Option<rewards::Status>,
+) -> Result<Json<rewards::Model>, Errors> {
+    respond(ledger::amend(&state.db, &state.events, id, user_id, value, status).await?)
+}
+
+// Respond with the reward or the reason it couldn't be written
+fn respond(redeemed: ledger::Redeemed) -> Result<Json<rewards::Model>, Errors> {
+    match redeemed {
+        ledger::Redeemed::Reward(reward) => Ok(Json(reward)),
+        ledger::Redeemed::Overdrawn { balance } => {
+            Err(ErrorKind::Conflict.details(json!({ "balance": balance }))) // error out if not enough points
+        },
+        ledger::Redeemed::Locked { status } => {
+            Err(ErrorKind::Conflict.details(json!({ "status": status }))) // error out if the status forbids it
+        },
+    }
+}
+
+// Unit tests
+// -------------------------------------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::CreateRewardDTO;
+    use crate::model::test_db;
+    use crate::prelude::*;
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        response::Response,
+    };
+    use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
+    use tower::ServiceExt; // for `oneshot` and `ready`
+
+    async fn send(db: &DatabaseConnection, auth: &str, method: &str, uri: &str, body: &str) -> Response {
+        app(db.clone())
+            .oneshot(
+                Request::builder()
+                    .uri(uri)
+                    .header(AUTHORIZATION, auth)
+                    .method(method)
+                    .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(body.to_owned()))
+                    .unwrap(),
+            )
             .await
-            .map_err(|_| Errors::InternalServerError)?,
-    ))
+            .unwrap()
+    }
+
+    // A user with 10 points who has requested a reward worth 6 and an admin to make requests as
+    async fn load(db: &DatabaseConnection) -> String {
+        let events = Events::default();
+        user::create(db, &events, "user1").await.unwrap();
+        user::create(db, &events, "user2").await.unwrap();
+        category::create(db, "category1", 1).await.unwrap();
+        points::create(db, &events, 1, 1, 10).await.unwrap();
+        rewards::create(db, 1, 6).await.unwrap();
+        handlers::test_admin(db).await
+    }
+
+    async fn create_reward(db: &DatabaseConnection, auth: &str, dto: CreateRewardDTO) -> Response {
+        app(db.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/rewards")
+                    .header(AUTHORIZATION, auth)
+                    .method("POST")
+                    .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(serde_json::to_string(&dto).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_create_reward() {
+        let db = test_db().await;
+        let events = Events::default();
+        user::create(&db, &events, "user1").await.unwrap();
+        category::create(&db, "category1", 1).await.unwrap();
+        points::create(&db, &events, 1, 1, 10).await.unwrap();
+        let auth = handlers::test_admin(&db).await;
+
+        let res = create_reward(&db, &auth, CreateRewardDTO { value: 8, user_id: 1 }).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let reward1: rewards::Model = serde_json::from_slice(&body).unwrap();
+
+        // Read from the db and compare
+        let reward2 = rewards::get_by_id(&db, 1).await.unwrap().unwrap();
+        assert!(reward1 == reward2);
+        assert!(reward2.user_id == 1);
+        assert!(reward2.value == 8);
+
+        // Only 2 left to redeem
+        let res = create_reward(&db, &auth, CreateRewardDTO { value: 3, user_id: 1 }).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+
+        // No such user
+        let res = create_reward(&db, &auth, CreateRewardDTO { value: 1, user_id: 9 }).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        // Nothing to redeem
+        let res = create_reward(&db, &auth, CreateRewardDTO { value: -1, user_id: 1 }).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(error["details"]["value"][0] == "must be at least 1");
+
+        // Regular users can only redeem their own points
+        let auth = handlers::test_login(&db, 1).await;
+        let res = create_reward(&db, &auth, CreateRewardDTO { value: 1, user_id: 2 }).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = create_reward(&db, &auth, CreateRewardDTO { value: 2, user_id: 1 }).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // Nothing more was written
+        assert!(rewards::get(&db).await.unwrap().len() == 2);
+    }
+
+    #[tokio::test]
+    async fn test_update_reward() {
+        let db = test_db().await;
+        let auth = load(&db).await;
+
+        // The path decides which reward is updated
+        let res = send(&db, &auth, "PUT", "/api/rewards/1", r#"{ "value": 8, "user_id": 1 }"#).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let reward: rewards::Model = serde_json::from_slice(&body).unwrap();
+        assert!(reward.id == 1);
+        assert!(reward.value == 8);
+        assert!(reward.status == rewards::Status::Requested);
+
+        let res = send(&db, &auth, "PUT", "/api/rewards/1", r#"{ "id": 2, "value": 8, "user_id": 1 }"#).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(error["details"]["id"][0] == "does not match the path");
+
+        let res = send(&db, &auth, "PUT", "/api/rewards/9", r#"{ "value": 8, "user_id": 1 }"#).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        // Only 10 points to spend and none for the other user
+        let res = send(&db, &auth, "PUT", "/api/rewards/1", r#"{ "id": 1, "value": 11, "user_id": 1 }"#).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let res = send(&db, &auth, "PUT", "/api/rewards/1", r#"{ "value": 8, "user_id": 2 }"#).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(error["details"]["balance"] == 0);
+
+        // Nothing else was written
+        let reward = rewards::get_by_id(&db, 1).await.unwrap().unwrap();
+        assert!(reward.user_id == 1);
+        assert!(reward.value == 8);
+
+        // Only admins can update rewards
+        let auth = handlers::test_login(&db, 1).await;
+        let res = send(&db, &auth, "PUT", "/api/rewards/1", r#"{ "value": 1, "user_id": 1 }"#).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_patch_reward() {
+        let db = test_db().await;
+        let auth = load(&db).await;
+
+        let res = send(&db, &auth, "PATCH", "/api/rewards/1", r#"{ "status": "approved" }"#).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(json["status"] == "approved");
+        assert!(json["value"] == 6);
+
+        let res = send(&db, &auth, "PATCH", "/api/rewards/1", r#"{ "id": 1, "value": 10 }"#).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let reward = rewards::get_by_id(&db, 1).await.unwrap().unwrap();
+        assert!(reward.status == rewards::Status::Approved);
+        assert!(reward.value == 10);
+
+        // No going back
+        let res = send(&db, &auth, "PATCH", "/api/rewards/1", r#"{ "status": "requested" }"#).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(error["details"]["status"] == "approved");
+
+        for (uri, body, status) in [
+            ("/api/rewards/1", r#"{ "status": "lost" }"#, StatusCode::UNPROCESSABLE_ENTITY),
+            ("/api/rewards/1", r#"{ "value": 0 }"#, StatusCode::UNPROCESSABLE_ENTITY),
+            ("/api/rewards/1", r#"{ "id": 2 }"#, StatusCode::UNPROCESSABLE_ENTITY),
+            ("/api/rewards/1", r#"{ "value": 11 }"#, StatusCode::CONFLICT),
+            ("/api/rewards/9", r#"{ "value": 1 }"#, StatusCode::NOT_FOUND),
+        ] {
+            let res = send(&db, &auth, "PATCH", uri, body).await;
+            assert_eq!(res.status(), status);
+        }
+
+        let res = send(&db, &auth, "PATCH", "/api/rewards/1", r#"{ "status": "redeemed" }"#).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let reward = rewards::get_by_id(&db, 1).await.unwrap().unwrap();
+        assert!(reward.status == rewards::Status::Redeemed);
+        assert!(reward.value == 10);
+
+        // Redeemed rewards are handed out so their value and user are fixed
+        for (method, body) in [
+            ("PATCH", r#"{ "value": 5 }"#),
+            ("PATCH", r#"{ "user_id": 2 }"#),
+            ("PUT", r#"{ "value": 5, "user_id": 1 }"#),
+        ] {
+            let res = send(&db, &auth, method, "/api/rewards/1", body).await;
+            assert_eq!(res.status(), StatusCode::CONFLICT);
+            let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+            let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert!(error["details"]["status"] == "redeemed");
+        }
+        assert!(rewards::get_by_id(&db, 1).await.unwrap().unwrap().value == 10);
+    }
+
+    #[tokio::test]
+    async fn test_patch_reward_skip() {
+        let db = test_db().await;
+        let auth = load(&db).await;
+
+        // Approval can't be skipped
+        let res = send(&db, &auth, "PATCH", "/api/rewards/1", r#"{ "status": "redeemed" }"#).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(error["details"]["status"] == "requested");
+        assert!(rewards::get_by_id(&db, 1).await.unwrap().unwrap().status == rewards::Status::Requested);
+    }
+
+    #[tokio::test]
+    async fn test_delete_reward() {
+        let db = test_db().await;
+        let auth = load(&db).await;
+
+        let user = handlers::test_login(&db, 1).await;
+        let res = send(&db, &user, "DELETE", "/api/rewards/1", "").await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let res = send(&db, &auth, "DELETE", "/api/rewards/1", "").await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(rewards::get(&db).await.unwrap().is_empty());
+        assert!(ledger::balance(&db, 1).await.unwrap().balance == 10);
+
+        let res = send(&db, &auth, "DELETE", "/api/rewards/1", "").await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_delete_redeemed_reward() {
+        let db = test_db().await;
+        let auth = load(&db).await;
+        rewards::patch(&db, 1, None, None, Some(rewards::Status::Redeemed)).await.unwrap();
+
+        // Redeemed rewards are handed out so deleting them can't give their value back
+        let res = send(&db, &auth, "DELETE", "/api/rewards/1", "").await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(error["details"]["status"] == "redeemed");
+        assert!(rewards::get(&db).await.unwrap().len() == 1);
+        assert!(ledger::balance(&db, 1).await.unwrap().balance == 4);
+    }
 }
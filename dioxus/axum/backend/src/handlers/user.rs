@@ -1,62 +1,95 @@
-use super::errors::*;
+use super::{auth::*, errors::*, validate::*};
+use crate::model::list::{Listing, Page};
 use crate::model::*;
 use crate::state::AppState;
 use axum::{
+    async_trait,
     extract::{Path, State},
     Json,
 };
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct UserDTO {
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>, // Left unchanged if not given, users without one can't log in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<user::Role>, // Left unchanged if not given, new users default to regular users
+}
+
+#[async_trait]
+impl Validate for UserDTO {
+    async fn validate(&mut self, v: &mut Validator<'_>) -> Result<(), Errors> {
+        trim(&mut self.name);
+        v.length("name", &self.name, 1..=64);
+        if let Some(password) = &self.password {
+            v.length("password", password, 8..=128);
+        }
+        Ok(())
+    }
 }
 
 /// Create a new user if the user name is not a duplicate of an existing one
 pub async fn create_user(
-    State(state): State<AppState>, Json(dto): Json<UserDTO>,
+    _: AdminUser, State(state): State<AppState>, Valid(dto): Valid<UserDTO>,
 ) -> Result<Json<user::Model>, Errors> {
     user::get_by_name(&state.db, &dto.name)
-        .await
-        .map_err(|_| Errors::InternalServerError)? // error out if failed on db
-        .map_or_else(|| Ok(false), |_| Err(Errors::Conflict))?; // error out if exists
-    Ok(Json(user::create(&state.db, &dto.name).await.map_err(|_| Errors::InternalServerError)?))
+        .await? // error out if failed on db
+        .map_or_else(|| Ok(false), |_| Err(ErrorKind::Conflict))?; // error out if exists
+    let model = user::create(&state.db, &state.events, &dto.name).await?;
+    Ok(Json(set_credentials(&state, model, &dto).await?))
 }
 
 /// Get a specified user
-pub async fn get_user(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<user::Model>, Errors> {
+pub async fn get_user(
+    _: AuthUser, State(state): State<AppState>, Path(id): Path<i32>,
+) -> Result<Json<user::Model>, Errors> {
     Ok(Json(
         user::get_by_id(&state.db, id)
-            .await
-            .map_err(|_| Errors::InternalServerError)? // error out if failed on db
-            .ok_or_else(|| Errors::NotFound)?, // error out if not found
+            .await? // error out if failed on db
+            .ok_or(ErrorKind::NotFound)?, // error out if not found
     ))
 }
 
 /// Delete the specified user
-pub async fn delete_user(State(state): State<AppState>, Path(id): Path<i32>) -> Result<(), Errors> {
+pub async fn delete_user(_: AdminUser, State(state): State<AppState>, Path(id): Path<i32>) -> Result<(), Errors> {
     user::get_by_id(&state.db, id)
-        .await
-        .map_err(|_| Errors::InternalServerError)? // error out if failed on db
-        .ok_or_else(|| Errors::NotFound)?; // error out if not found
-    let _ = user::delete(&state.db, id).await.map_err(|_| Errors::InternalServerError)?;
+        .await? // error out if failed on db
+        .ok_or(ErrorKind::NotFound)?; // error out if not found
+    let _ = user::delete(&state.db, &state.events, id).await?;
     Ok(())
 }
 
-/// Get all users
-pub async fn get_users(State(state): State<AppState>) -> Result<Json<Vec<user::Model>>, Errors> {
-    Ok(Json(user::get(&state.db).await.map_err(|_| Errors::InternalServerError)?))
+/// Get a page of users optionally only those whose name contains the search text
+pub async fn get_users(
+    _: AuthUser, State(state): State<AppState>, listing: Listing<user::Entity>,
+) -> Result<Page<user::Model>, Errors> {
+    Ok(list::list(&state.db, &listing).await?)
 }
 
 /// Update the given user
 pub async fn update_user(
-    State(state): State<AppState>, Path(id): Path<i32>, Json(dto): Json<UserDTO>,
+    _: AdminUser, State(state): State<AppState>, Path(id): Path<i32>, Valid(dto): Valid<UserDTO>,
 ) -> Result<Json<user::Model>, Errors> {
     user::get_by_id(&state.db, id)
-        .await
-        .map_err(|_| Errors::InternalServerError)? // error out if failed on db
-        .ok_or_else(|| Errors::NotFound)?; // error out if not found
-    Ok(Json(user::update(&state.db, id, &dto.name).await.map_err(|_| Errors::InternalServerError)?))
+        .await? // error out if failed on db
+        .ok_or(ErrorKind::NotFound)?; // error out if not found
+    let model = user::update(&state.db, &state.events, id, &dto.name).await?;
+    Ok(Json(set_credentials(&state, model, &dto).await?))
+}
+
+// Set the password and role of the given user if they were given
+async fn set_credentials(
+    state: &AppState, mut model: user::Model, dto: &UserDTO,
+) -> Result<user::Model, Errors> {
+    if let Some(password) = &dto.password {
+        model = user::set_password(&state.db, model.id, password).await?;
+    }
+    if let Some(role) = dto.role {
+        model = user::set_role(&state.db, &state.events, model.id, role).await?;
+    }
+    Ok(model)
 }
 
 // Unit tests
@@ -70,16 +103,20 @@ mod tests {
         body::Body,
         http::{Request, StatusCode},
     };
-    use hyper::header::CONTENT_TYPE;
+    use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
     use tower::ServiceExt; // for `oneshot` and `ready`
 
     #[tokio::test]
     async fn test_get_user() {
         let db = test_db().await;
-        user::create(&db, "foo").await.unwrap();
+        let events = Events::default();
+        user::create(&db, &events, "foo").await.unwrap();
+        let auth = handlers::test_admin(&db).await;
 
         let res = app(db.clone())
-            .oneshot(Request::builder().uri("/api/user/1").body(Body::empty()).unwrap())
+            .oneshot(
+                Request::builder().uri("/api/user/1").header(AUTHORIZATION, &auth).body(Body::empty()).unwrap(),
+            )
             .await
             .unwrap();
 
@@ -93,18 +130,20 @@ mod tests {
     #[tokio::test]
     async fn test_get_users() {
         let db = test_db().await;
-        user::create(&db, "foo1").await.unwrap();
-        user::create(&db, "foo2").await.unwrap();
+        let events = Events::default();
+        user::create(&db, &events, "foo1").await.unwrap();
+        user::create(&db, &events, "foo2").await.unwrap();
+        let auth = handlers::test_admin(&db).await;
 
         let res = app(db.clone())
-            .oneshot(Request::builder().uri("/api/user").body(Body::empty()).unwrap())
+            .oneshot(Request::builder().uri("/api/user").header(AUTHORIZATION, &auth).body(Body::empty()).unwrap())
             .await
             .unwrap();
 
         assert_eq!(res.status(), StatusCode::OK);
         let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
         let users: Vec<user::Model> = serde_json::from_slice(&body).unwrap();
-        assert!(users.len() == 2);
+        assert!(users.len() == 3);
         assert!(users.iter().find(|x| x.name == "foo1").is_some());
         assert!(users.iter().find(|x| x.name == "foo2").is_some());
     }
@@ -112,14 +151,18 @@ mod tests {
     #[tokio::test]
     async fn test_create_user() {
         let db = test_db().await;
+        let auth = handlers::test_admin(&db).await;
 
         let res = app(db.clone())
             .oneshot(
                 Request::builder()
                     .uri("/api/user")
                     .method("POST")
+                    .header(AUTHORIZATION, &auth)
                     .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
-                    .body(Body::from(serde_json::to_string(&UserDTO { name: "foo".into() }).unwrap()))
+                    .body(Body::from(
+                        serde_json::to_string(&UserDTO { name: "foo".into(), ..Default::default() }).unwrap(),
+                    ))
                     .unwrap(),
             )
             .await
@@ -131,22 +174,77 @@ mod tests {
 
         // Read from the db and compare
         let user2 = user::get_by_name(&db, "foo").await.unwrap().unwrap();
-        assert!(user1.id == 1);
+        assert!(user1.id == 2);
         assert!(user1.name == "foo");
+        assert!(user1.role == user::Role::User);
         assert!(user1.id == user2.id);
         assert!(user1.name == user2.name);
     }
 
+    #[tokio::test]
+    async fn test_create_user_invalid() {
+        let db = test_db().await;
+        let auth = handlers::test_admin(&db).await;
+
+        for (body, status, field) in [
+            (r#"{ "name": " \t " }"#, StatusCode::UNPROCESSABLE_ENTITY, Some("name")),
+            (r#"{ "name": "foo", "password": "short" }"#, StatusCode::UNPROCESSABLE_ENTITY, Some("password")),
+            (r#"{ "name": 5 }"#, StatusCode::UNPROCESSABLE_ENTITY, None),
+            (r#"{ "name": "foo""#, StatusCode::BAD_REQUEST, None),
+        ] {
+            let res = app(db.clone())
+                .oneshot(
+                    Request::builder()
+                        .uri("/api/user")
+                        .method("POST")
+                        .header(AUTHORIZATION, &auth)
+                        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                        .body(Body::from(body))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(res.status(), status);
+            let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+            let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            match field {
+                Some(field) => assert!(error["details"][field].is_array()),
+                None => assert!(error["details"].is_string()),
+            }
+        }
+
+        // Names are stored trimmed
+        let res = app(db.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/user")
+                    .method("POST")
+                    .header(AUTHORIZATION, &auth)
+                    .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(r#"{ "name": "  foo " }"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(user::get_by_name(&db, "foo").await.unwrap().is_some());
+        assert!(user::get(&db).await.unwrap().len() == 2);
+    }
+
     #[tokio::test]
     async fn test_update_user() {
         let db = test_db().await;
-        user::create(&db, "foo1").await.unwrap();
+        let events = Events::default();
+        user::create(&db, &events, "foo1").await.unwrap();
+        let auth = handlers::test_admin(&db).await;
 
         let res = app(db.clone())
             .oneshot(
                 Request::builder()
                     .uri("/api/user/1") // id in URI as should be same as GET
                     .method("PUT")
+                    .header(AUTHORIZATION, &auth)
                     .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
                     .body(Body::from(r#"{ "name" : "foo2" }"#))
                     .unwrap(),
@@ -169,10 +267,19 @@ mod tests {
     #[tokio::test]
     async fn test_delete_user() {
         let db = test_db().await;
-        user::create(&db, "foo1").await.unwrap();
+        let events = Events::default();
+        user::create(&db, &events, "foo1").await.unwrap();
+        let auth = handlers::test_admin(&db).await;
 
         let res = app(db.clone())
-            .oneshot(Request::builder().uri("/api/user/1").method("DELETE").body(Body::empty()).unwrap())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/user/1")
+                    .header(AUTHORIZATION, &auth)
+                    .method("DELETE")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
             .await
             .unwrap();
 
@@ -182,6 +289,7 @@ mod tests {
 
         // Check that it no longer exists in db
         let users = user::get(&db).await.unwrap();
-        assert!(users.len() == 0);
+        assert!(users.len() == 1);
+        assert!(users[0].name == "admin");
     }
 }
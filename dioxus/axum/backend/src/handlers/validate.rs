@@ -0,0 +1,182 @@
+use super::errors::*;
+use crate::state::AppState;
+use axum::{
+    async_trait,
+    body::HttpBody,
+    extract::{rejection::JsonRejection, FromRequest},
+    http::{Request, StatusCode},
+    BoxError, Json,
+};
+use sea_orm::{DatabaseConnection, EntityTrait, PrimaryKeyTrait};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    ops::{Bound, RangeBounds, RangeInclusive},
+};
+
+/// Rules a DTO has to pass before a handler gets it
+#[async_trait]
+pub trait Validate {
+    /// Normalize the fields e.g. trim them then check them with the given validator
+    async fn validate(&mut self, v: &mut Validator<'_>) -> Result<(), Errors>;
+}
+
+/// Gathers the messages of the rules that failed per field
+pub struct Validator<'a> {
+    db: &'a DatabaseConnection,
+    fields: BTreeMap<&'static str, Vec<String>>, // Messages of the failed rules per field
+}
+
+impl<'a> Validator<'a> {
+    /// Create a validator checking references against the given database
+    pub fn new(db: &'a DatabaseConnection) -> Self {
+        Self { db, fields: BTreeMap::new() }
+    }
+
+    /// Add a message for the given field
+    pub fn error(&mut self, field: &'static str, message: impl Into<String>) -> &mut Self {
+        self.fields.entry(field).or_default().push(message.into());
+        self
+    }
+
+    /// Check the number of characters in the given value
+    pub fn length(&mut self, field: &'static str, value: &str, range: RangeInclusive<usize>) -> &mut Self {
+        if !range.contains(&value.chars().count()) {
+            let message = match (*range.start(), *range.end()) {
+                (1, usize::MAX) => "must not be empty".to_owned(),
+                (min, usize::MAX) => format!("must be at least {} characters", min),
+                (min, max) => format!("must be between {} and {} characters", min, max),
+            };
+            self.error(field, message);
+        }
+        self
+    }
+
+    /// Check the given value is within range
+    pub fn range(&mut self, field: &'static str, value: i32, range: impl RangeBounds<i32>) -> &mut Self {
+        if !range.contains(&value) {
+            let min = match range.start_bound() {
+                Bound::Included(x) => Some(*x as i64),
+                Bound::Excluded(x) => Some(*x as i64 + 1),
+                Bound::Unbounded => None,
+            };
+            let max = match range.end_bound() {
+                Bound::Included(x) => Some(*x as i64),
+                Bound::Excluded(x) => Some(*x as i64 - 1),
+                Bound::Unbounded => None,
+            };
+            let message = match (min, max) {
+                (Some(min), Some(max)) => format!("must be between {} and {}", min, max),
+                (Some(min), None) => format!("must be at least {}", min),
+                (None, Some(max)) => format!("must be at most {}", max),
+                (None, None) => unreachable!("an unbounded range contains everything"),
+            };
+            self.error(field, message);
+        }
+        self
+    }
+
+    /// Check the given id refers to an existing row of the entity
+    pub async fn exists<E>(&mut self, field: &'static str, id: i32) -> Result<&mut Self, Errors>
+    where
+        E: EntityTrait,
+        i32: Into<<E::PrimaryKey as PrimaryKeyTrait>::ValueType>,
+    {
+        if E::find_by_id(id).one(self.db).await?.is_none() {
+            self.error(field, "does not exist");
+        }
+        Ok(self)
+    }
+
+    /// Error out with an unprocessable entity listing the messages per field if any rule failed
+    pub fn finish(self) -> Result<(), Errors> {
+        if self.fields.is_empty() {
+            return Ok(());
+        }
+        let fields = self.fields.into_iter().map(|(field, messages)| (field.to_owned(), Value::from(messages)));
+        Err(ErrorKind::UnprocessableEntity.details(Value::Object(fields.collect())))
+    }
+}
+
+/// Trim the whitespace from both ends of the given string in place
+pub fn trim(value: &mut String) {
+    let trimmed = value.trim();
+    if trimmed.len() != value.len() {
+        *value = trimmed.to_owned();
+    }
+}
+
+/// JSON body that has passed the rules of its [`Validate`] implementation
+pub struct Valid<T>(pub T);
+
+#[async_trait]
+impl<T, B> FromRequest<AppState, B> for Valid<T>
+where
+    T: DeserializeOwned + Validate + Send,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Rejection = Errors;
+
+    async fn from_request(req: Request<B>, state: &AppState) -> Result<Self, Self::Rejection> {
+        let Json(mut dto) = Json::<T>::from_request(req, state).await.map_err(json_error)?;
+        let mut validator = Validator::new(&state.db);
+        dto.validate(&mut validator).await?;
+        validator.finish()?;
+        Ok(Valid(dto))
+    }
+}
+
+// Report a body that isn't JSON or doesn't fit the DTO in the same format as the other errors
+fn json_error(rejection: JsonRejection) -> Errors {
+    let kind = match rejection.status() {
+        StatusCode::UNPROCESSABLE_ENTITY => ErrorKind::UnprocessableEntity,
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => ErrorKind::UnsupportedMediaType,
+        _ => ErrorKind::BadRequest,
+    };
+    kind.details(rejection.body_text())
+}
+
+// Unit tests
+// -------------------------------------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{trim, Validator};
+    use crate::model::test_db;
+    use crate::prelude::*;
+
+    #[tokio::test]
+    async fn test_rules() {
+        let db = test_db().await;
+        let events = Events::default();
+        user::create(&db, &events, "user1").await.unwrap();
+
+        let mut name = "  foo \n".to_owned();
+        trim(&mut name);
+        assert!(name == "foo");
+
+        let mut v = Validator::new(&db);
+        v.length("name", &name, 1..=3).length("name", &name, 4..=usize::MAX).length("empty", "", 1..=usize::MAX);
+        v.range("value", 0, 1..).range("value", 5, 1..=4).range("ok", 3, ..=3);
+        v.exists::<user::Entity>("user_id", 1).await.unwrap().exists::<user::Entity>("other_id", 2).await.unwrap();
+
+        let err = v.finish().unwrap_err();
+        assert!(err.kind == handlers::ErrorKind::UnprocessableEntity);
+        let details = err.details.unwrap();
+        assert!(
+            details
+                == serde_json::json!({
+                    "empty": ["must not be empty"],
+                    "name": ["must be at least 4 characters"],
+                    "other_id": ["does not exist"],
+                    "value": ["must be at least 1", "must be between 1 and 4"],
+                })
+        );
+
+        let mut v = Validator::new(&db);
+        v.length("name", &name, 1..=3).range("value", 1, 1..);
+        assert!(v.finish().is_ok());
+    }
+}
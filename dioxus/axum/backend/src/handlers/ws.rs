@@ -0,0 +1,87 @@
+use super::auth::*;
+use crate::model::events::Event;
+use crate::state::AppState;
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::Response,
+};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use tracing::{error, warn};
+
+/// Upgrade to a websocket pushing every change made to the data as a JSON text message, see
+/// [`Event`]. Browsers send the session cookie with the upgrade request to log in with.
+pub async fn ws(_: AuthUser, State(state): State<AppState>, upgrade: WebSocketUpgrade) -> Response {
+    let events = state.events.subscribe(); // Subscribe before upgrading so no event is missed
+    upgrade.on_upgrade(move |socket| push_events(socket, events))
+}
+
+// Send the events to the client until either side closes
+async fn push_events(mut socket: WebSocket, mut events: Receiver<Event>) {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let text = match serde_json::to_string(&event) {
+                        Ok(text) => text,
+                        Err(e) => {
+                            error!("Failed to serialize event: {}", e);
+                            continue;
+                        },
+                    };
+                    if socket.send(Message::Text(text)).await.is_err() {
+                        break; // client went away
+                    }
+                },
+                Err(RecvError::Lagged(missed)) => warn!("Websocket client missed {} events", missed),
+                Err(RecvError::Closed) => break,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}, // Nothing is expected from the client, pings are answered by axum
+            },
+        }
+    }
+}
+
+// Unit tests
+// -------------------------------------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use crate::model::events::Event;
+    use crate::model::test_db;
+    use crate::prelude::*;
+    use futures::StreamExt;
+    use hyper::header::AUTHORIZATION;
+    use std::net::SocketAddr;
+    use tokio_tungstenite::tungstenite::{client::IntoClientRequest, Message};
+
+    #[tokio::test]
+    async fn test_ws() {
+        let db = test_db().await;
+        let auth = handlers::test_admin(&db).await;
+
+        let state = AppState::new(db.clone());
+        let router = crate::router(state.clone(), &Default::default());
+        let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(router.into_make_service());
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        // Only logged in users can listen
+        let err = tokio_tungstenite::connect_async(format!("ws://{}/api/ws", addr)).await.unwrap_err();
+        assert!(matches!(err, tokio_tungstenite::tungstenite::Error::Http(res) if res.status() == 401));
+
+        let mut req = format!("ws://{}/api/ws", addr).into_client_request().unwrap();
+        req.headers_mut().insert(AUTHORIZATION, auth.parse().unwrap());
+        let (mut socket, _) = tokio_tungstenite::connect_async(req).await.unwrap();
+
+        let user = user::create(&db, &state.events, "user1").await.unwrap();
+        let text = match socket.next().await.unwrap().unwrap() {
+            Message::Text(text) => text,
+            x => panic!("unexpected {:?}", x),
+        };
+        assert!(serde_json::from_str::<Event>(&text).unwrap() == Event::UserCreated(user));
+    }
+}
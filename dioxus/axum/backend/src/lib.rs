@@ -1,3 +1,4 @@
+pub mod config;
 pub mod handlers;
 pub mod migrations;
 pub mod model;
@@ -14,41 +15,74 @@ pub mod prelude {
     pub use sea_orm_migration::prelude::*;
 }
 
-use std::path::PathBuf;
-
 use axum::{
     body::{boxed, Body},
-    http::{Response, StatusCode},
-    routing::get,
+    http::{
+        header::{AUTHORIZATION, CONTENT_TYPE},
+        HeaderName, HeaderValue, Method, Request, Response, StatusCode,
+    },
+    routing::{get, post},
     Router,
 };
 use sea_orm::DatabaseConnection;
 use tokio::fs;
 use tower::ServiceExt;
 use tower_http::{
+    cors::CorsLayer,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
     services::fs::ServeDir,
     trace::{self, TraceLayer},
 };
 
-// Configure the router
+/// Header holding the id of a request
+pub const REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+// Configure the router with the default config
 pub fn app(db: DatabaseConnection) -> Router {
-    Router::new()
+    router(state::AppState::new(db), &config::Config::default())
+}
+
+/// Configure the router serving the frontend from the configured directory and allowing the configured
+/// origins to call the API
+pub fn router(state: state::AppState, config: &config::Config) -> Router {
+    let static_dir = config.static_dir.clone();
+    let router = Router::new()
         // API handlers
+        .route("/api/login", post(handlers::login))
+        .route("/api/logout", post(handlers::logout))
+        .route("/api/me", get(handlers::me))
+        .route("/api/ws", get(handlers::ws))
         .route("/api/user", get(handlers::get_users).post(handlers::create_user))
         .route("/api/user/:user", get(handlers::get_user).put(handlers::update_user).delete(handlers::delete_user))
-        .route("/api/category", get(handlers::categories))
-        .route("/api/category/:category", get(handlers::category))
+        .route("/api/user/:user/balance", get(handlers::get_balance))
+        .route("/api/user/:user/ledger", get(handlers::get_ledger))
+        .route("/api/category", get(handlers::categories).post(handlers::create_category))
+        .route(
+            "/api/category/:category",
+            get(handlers::category).put(handlers::update_category).delete(handlers::delete_category),
+        )
+        .route("/api/points", get(handlers::get_points).post(handlers::create_points))
+        .route(
+            "/api/points/:points",
+            get(handlers::get_points_by_id).put(handlers::update_points).delete(handlers::delete_points),
+        )
         .route("/api/rewards", get(handlers::get_rewards).post(handlers::create_reward))
-        .route("/api/rewards/:reward", get(handlers::get_reward).put(handlers::update_reward))
+        .route(
+            "/api/rewards/:reward",
+            get(handlers::get_reward)
+                .put(handlers::update_reward)
+                .patch(handlers::patch_reward)
+                .delete(handlers::delete_reward),
+        )
 
         // Redirect all other routes to out WASM SPA
         .fallback_service(get(|req| async move {
-            match ServeDir::new("frontend/dist").oneshot(req).await {
+            match ServeDir::new(&static_dir).oneshot(req).await {
                 Ok(res) => {
                     let status = res.status();
                     match status {
                         StatusCode::NOT_FOUND => {
-                            let index_path = PathBuf::from("frontend/dist").join("index.html");
+                            let index_path = static_dir.join("index.html");
                             let index_content = fs::read_to_string(index_path).await.expect("index file not found");
                             Response::builder()
                                 .status(StatusCode::OK)
@@ -67,18 +101,32 @@ pub fn app(db: DatabaseConnection) -> Router {
 
         // Request/response logging
         .layer(TraceLayer::new_for_http()
+            // Include the request id in the span so it's on every log line of the request
+            .make_span_with(|req: &Request<Body>| {
+                let request_id = req.headers().get(REQUEST_ID).and_then(|x| x.to_str().ok()).unwrap_or_default();
+                tracing::info_span!("request", method = %req.method(), uri = %req.uri(), request_id)
+            })
             // Make on_response INFO level logging, its DEBUG by default
             .on_response(trace::DefaultOnResponse::new()
                 .level(tracing::Level::INFO)),
         )
 
-        // TODO: are cores restrictions needed
-        // .layer(CorsLayer::new()
-        //     .allow_methods([Method::GET, Method::POST])
-        //     .allow_origin(Any)
-        //     .allow_headers(Any)
-        // )
+        // Tag each request with an id and return it in the response to find it in the logs with
+        .layer(PropagateRequestIdLayer::new(REQUEST_ID))
+        .layer(SetRequestIdLayer::new(REQUEST_ID, MakeRequestUuid));
+
+    // Only browsers on the configured origins may call the API, with their session cookie
+    let router = match config.cors_origins.is_empty() {
+        true => router,
+        false => router.layer(CorsLayer::new()
+            .allow_origin(config.cors_origins.iter().filter_map(|x| x.parse().ok()).collect::<Vec<HeaderValue>>())
+            .allow_methods([Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE])
+            .allow_headers([AUTHORIZATION, CONTENT_TYPE])
+            .expose_headers([REQUEST_ID, handlers::TOTAL_COUNT, handlers::TOTAL_PAGES])
+            .allow_credentials(true)
+        ),
+    };
 
-        // Add custom state object with db handle
-        .with_state(state::AppState { db })
+    // Add custom state object with db handle
+    router.with_state(state)
 }
@@ -1,8 +1,9 @@
+use ::backend::config::{self, Command, Config, Migrate};
 use ::backend::prelude::*;
-use std::{env, net::SocketAddr, str::FromStr};
+use std::{env, process};
 use tokio::signal::{self, unix};
 use tracing::{error, info, warn};
-use tracing_subscriber::{filter::LevelFilter, EnvFilter};
+use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
 async fn main() {
@@ -11,43 +12,95 @@ async fn main() {
         error!("{}", msg);
     }));
 
-    // Parse arguments
-    // let args = env::args().collect::<Vec<String>>();
+    // Load configuration from the optional TOML file, .env file, environment and arguments
+    dotenvy::dotenv().ok();
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let (command, config) = match config::load(&args, |x| env::var(x).ok()) {
+        Ok((Command::Help(usage), _)) => {
+            println!("{}", usage);
+            return;
+        },
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Invalid configuration: {:#}\nRun with --help for usage", e);
+            process::exit(2);
+        },
+    };
 
     // Initialize logging
     tracing_subscriber::fmt()
-        .with_env_filter(
-            EnvFilter::builder()
-                .with_default_directive(LevelFilter::TRACE.into())
-                .from_env_lossy()
-                .add_directive("sqlx=warn".parse().unwrap())
-                .add_directive("mio=warn".parse().unwrap())
-                .add_directive("hyper=warn".parse().unwrap()),
-        )
+        .with_env_filter(EnvFilter::try_new(&config.log).expect("validated with the config"))
         //.with_writer(tracing_appender::rolling::daily("./logs", "info").with_max_level(tracing::Level::INFO))
         .init();
     info!("Booting API for Axum example...");
     info!("Logging initialized!");
 
-    // Load configuration
-    // Database url can be overridden with "sqlite::memory:" for testing
-    info!("Loading configuration...");
-    dotenvy::dotenv().ok();
-    let db_url = env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite:./sqlite.db?mode=rwc".into());
-    let host = env::var("HOST").unwrap_or_else(|_| "127.0.0.1".into());
-    let port = env::var("PORT").unwrap_or_else(|_| "3000".into());
-    let server_url = format!("{host}:{port}");
-    let db = init_db(&db_url).await.expect("Failed to initialize the db connection!");
-
-    info!("Listening on {}", server_url);
-    let addr = SocketAddr::from_str(&server_url).unwrap();
-    axum::Server::bind(&addr)
-        .serve(crate::app(db).into_make_service())
+    let result = match command {
+        Command::Serve => serve(&config).await,
+        Command::Migrate(x) => migrate(&config, x).await,
+        Command::Seed => seed(&config).await,
+        Command::Help(_) => unreachable!("printed above"),
+    };
+    if let Err(e) = result {
+        error!("{:#}", e);
+        process::exit(1);
+    }
+}
+
+// Run the API and serve the frontend until shut down
+async fn serve(config: &Config) -> Result<(), anyhow::Error> {
+    let state = AppState::new(init_db(&config.database_url).await?);
+
+    // Ensure there is an admin to log in as and create the other users with
+    if let Some(password) = &config.admin_password {
+        info!("Ensuring admin user '{}' exists...", config.admin_name);
+        user::create_admin_if_not(&state.db, &state.events, &config.admin_name, password).await?;
+    }
+    if !config.static_dir.is_dir() {
+        warn!("Frontend directory '{}' doesn't exist", config.static_dir.display());
+    }
+
+    let addr = config.addr()?;
+    info!("Listening on {}", addr);
+    axum::Server::try_bind(&addr)?
+        .serve(::backend::router(state, config).into_make_service())
         .with_graceful_shutdown(shutdown_signals())
-        .await
-        .expect("Unable to start server!");
+        .await?;
 
     warn!("Exiting");
+    Ok(())
+}
+
+// Apply or roll back the migrations or list them with whether they are applied
+async fn migrate(config: &Config, command: Migrate) -> Result<(), anyhow::Error> {
+    info!("Connecting to '{}' database!", config.database_url);
+    let db = Database::connect(&config.database_url).await?;
+    match command {
+        Migrate::Up => migrations::Migrator::up(&db, None).await?,
+        Migrate::Down(steps) => migrations::Migrator::down(&db, Some(steps)).await?,
+        Migrate::Status => {
+            let applied = migrations::Migrator::get_migration_models(&db)
+                .await?
+                .into_iter()
+                .map(|x| x.version)
+                .collect::<Vec<_>>();
+            for migration in migrations::Migrator::migrations() {
+                let status = match applied.iter().any(|x| x == migration.name()) {
+                    true => "applied",
+                    false => "pending",
+                };
+                println!("{:<48} {}", migration.name(), status);
+            }
+        },
+    }
+    Ok(())
+}
+
+// Load the example data after applying any pending migrations
+async fn seed(config: &Config) -> Result<(), anyhow::Error> {
+    let db = init_db(&config.database_url).await?;
+    info!("Loading example data...");
+    model::seed(&db).await
 }
 
 // Signal detection for graceful shutdown
@@ -73,10 +126,13 @@ async fn shutdown_signals() {
 mod tests {
     use crate::app;
     use crate::test_db;
+    use crate::AppState;
     use axum::{
         body::Body,
         http::{Request, StatusCode},
     };
+    use backend::config::Config;
+    use hyper::header::{ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN};
     use tower::ServiceExt; // for `oneshot` and `ready`
 
     #[tokio::test]
@@ -90,4 +146,25 @@ mod tests {
         let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
         assert!(body.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_cors() {
+        let config = Config { cors_origins: vec!["http://localhost:8080".into()], ..Default::default() };
+        for (origin, allowed) in [("http://localhost:8080", true), ("http://evil.com", false)] {
+            let res = ::backend::router(AppState::new(test_db().await), &config)
+                .oneshot(
+                    Request::builder()
+                        .uri("/api/user")
+                        .method("OPTIONS")
+                        .header(ORIGIN, origin)
+                        .header(ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+            assert!(res.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_some() == allowed);
+        }
+    }
 }
@@ -0,0 +1,80 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Users log in with a password and are either an admin or a regular user.
+/// * `PasswordHash` is the argon2 hash of the password, users without one can't log in
+/// * `Role` is either `admin` or `user`
+#[derive(Iden)]
+enum User {
+    Table,
+    Id,
+    PasswordHash,
+    Role,
+}
+
+/// Session of a logged in user identified by a random token
+#[derive(Iden)]
+enum Session {
+    Table,
+    Token,
+    UserId,
+    CreatedAt,
+    ExpiresAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite can only add one column at a time
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(ColumnDef::new(User::PasswordHash).string())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(ColumnDef::new(User::Role).string_len(16).not_null().default("user"))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create session table
+        manager
+            .create_table(
+                Table::create()
+                    .table(Session::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Session::Token).string().not_null().primary_key())
+                    .col(ColumnDef::new(Session::UserId).integer().not_null())
+                    .col(
+                        ColumnDef::new(Session::CreatedAt)
+                            .date_time()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(ColumnDef::new(Session::ExpiresAt).date_time().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-session-user-id")
+                            .from(Session::Table, Session::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(Session::Table).to_owned()).await?;
+        manager.alter_table(Table::alter().table(User::Table).drop_column(User::Role).to_owned()).await?;
+        manager.alter_table(Table::alter().table(User::Table).drop_column(User::PasswordHash).to_owned()).await
+    }
+}
@@ -0,0 +1,32 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Rewards are requested by a user, approved by an admin and then redeemed.
+/// * `Status` is one of `requested`, `approved` or `redeemed`
+///
+/// Rewards created before the workflow existed were handed out right away so they default to `redeemed`.
+#[derive(Iden)]
+enum Rewards {
+    Table,
+    Status,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Rewards::Table)
+                    .add_column(ColumnDef::new(Rewards::Status).string_len(16).not_null().default("redeemed"))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.alter_table(Table::alter().table(Rewards::Table).drop_column(Rewards::Status).to_owned()).await
+    }
+}
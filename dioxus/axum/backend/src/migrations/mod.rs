@@ -1,12 +1,18 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20230519_000001_create_tables;
+mod m20230601_000001_add_auth;
+mod m20230615_000001_add_reward_status;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20230519_000001_create_tables::Migration)]
+        vec![
+            Box::new(m20230519_000001_create_tables::Migration),
+            Box::new(m20230601_000001_add_auth::Migration),
+            Box::new(m20230615_000001_add_reward_status::Migration),
+        ]
     }
 }
@@ -1,10 +1,13 @@
 pub use crate::model::entities::category::*;
 
 use sea_orm::{
-    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DeleteResult, EntityTrait, QueryFilter,
-    Unchanged,
+    sea_query::Expr, ActiveModelTrait, ActiveValue::Set, ColumnTrait, Condition, DatabaseConnection, DeleteResult,
+    EntityTrait, PaginatorTrait, QueryFilter, TransactionTrait, Unchanged,
 };
 
+use super::events::{Event, Events};
+use super::list::{self, Listable};
+
 /// Create the given category in the database
 pub async fn create(db: &DatabaseConnection, name: &str, value: i32) -> Result<Model, anyhow::Error> {
     Ok(ActiveModel { name: Set(name.to_owned()), value: Set(value), ..Default::default() }.insert(db).await?)
@@ -23,6 +26,15 @@ pub async fn get(db: &DatabaseConnection) -> Result<Vec<Model>, anyhow::Error> {
     Ok(Entity::find().all(db).await?)
 }
 
+/// Categories are searched by name
+impl Listable for Entity {
+    const SORT: &'static [&'static str] = &["id", "name", "value", "created_at", "modified_at"];
+
+    fn search(q: &str) -> Condition {
+        Condition::all().add(list::contains(Column::Name, q))
+    }
+}
+
 /// Get the given category from the database by id
 pub async fn get_by_id(db: &DatabaseConnection, id: i32) -> Result<Option<Model>, anyhow::Error> {
     Ok(Entity::find_by_id(id).one(db).await?)
@@ -48,11 +60,59 @@ pub async fn update(db: &DatabaseConnection, id: i32, name: &str, value: i32) ->
     Ok(entity)
 }
 
+/// Count the points entries in the given category
+pub async fn count_points(db: &DatabaseConnection, id: i32) -> Result<u64, anyhow::Error> {
+    Ok(super::points::Entity::find().filter(super::points::Column::CategoryId.eq(id)).count(db).await?)
+}
+
 /// Delete the given category from the database
 pub async fn delete_by_id(db: &DatabaseConnection, id: i32) -> Result<DeleteResult, anyhow::Error> {
     Ok(ActiveModel { id: Set(id), ..Default::default() }.delete(db).await?)
 }
 
+/// Delete the given category from the database along with all of its points
+pub async fn delete_with_points(
+    db: &DatabaseConnection, events: &Events, id: i32,
+) -> Result<DeleteResult, anyhow::Error> {
+    let tx = db.begin().await?;
+    let points = super::points::Entity::find().filter(super::points::Column::CategoryId.eq(id)).all(&tx).await?;
+    super::points::Entity::delete_many().filter(super::points::Column::CategoryId.eq(id)).exec(&tx).await?;
+    let result = ActiveModel { id: Set(id), ..Default::default() }.delete(&tx).await?;
+    tx.commit().await?;
+    for x in points {
+        events.emit(Event::PointsDeleted { id: x.id });
+    }
+    Ok(result)
+}
+
+/// Delete the given category from the database moving all of its points to another category
+/// * `to: i32` id of the category to move the points to
+pub async fn delete_reassigning_points(
+    db: &DatabaseConnection, events: &Events, id: i32, to: i32,
+) -> Result<DeleteResult, anyhow::Error> {
+    let tx = db.begin().await?;
+    let ids = super::points::Entity::find()
+        .filter(super::points::Column::CategoryId.eq(id))
+        .all(&tx)
+        .await?
+        .into_iter()
+        .map(|x| x.id)
+        .collect::<Vec<_>>();
+    super::points::Entity::update_many()
+        .col_expr(super::points::Column::CategoryId, Expr::value(to))
+        .col_expr(super::points::Column::ModifiedAt, Expr::value(super::timestamp()))
+        .filter(super::points::Column::Id.is_in(ids.clone()))
+        .exec(&tx)
+        .await?;
+    let points = super::points::Entity::find().filter(super::points::Column::Id.is_in(ids)).all(&tx).await?;
+    let result = ActiveModel { id: Set(id), ..Default::default() }.delete(&tx).await?;
+    tx.commit().await?;
+    for x in points {
+        events.emit(Event::PointsUpdated(x));
+    }
+    Ok(result)
+}
+
 // Unit tests
 // -------------------------------------------------------------------------------------------------
 #[cfg(test)]
@@ -92,4 +152,41 @@ mod tests {
         assert!(categories.iter().find(|x| x.id == 1).is_some());
         assert!(categories.iter().find(|x| x.id == 3).is_some());
     }
+
+    #[tokio::test]
+    async fn test_delete_with_points() {
+        let db = test_db().await;
+        let events = Events::default();
+        user::create(&db, &events, "user1").await.unwrap();
+        category::create(&db, "category1", 1).await.unwrap();
+        category::create(&db, "category2", 10).await.unwrap();
+        points::create(&db, &events, 1, 1, 10).await.unwrap();
+        points::create(&db, &events, 1, 1, 20).await.unwrap();
+        points::create(&db, &events, 1, 2, 30).await.unwrap();
+        assert!(category::count_points(&db, 1).await.unwrap() == 2);
+        let mut rx = events.subscribe();
+
+        // Reassign
+        let result = category::delete_reassigning_points(&db, &events, 1, 2).await.unwrap();
+        assert!(result.rows_affected == 1);
+        assert!(category::get_by_id(&db, 1).await.unwrap().is_none());
+        let points = points::get(&db).await.unwrap();
+        assert!(points.len() == 3);
+        assert!(points.iter().all(|x| x.category_id == 2));
+        for id in [1, 2] {
+            let x = points.iter().find(|x| x.id == id).unwrap().clone();
+            assert!(rx.try_recv().unwrap() == events::Event::PointsUpdated(x));
+        }
+        assert!(rx.try_recv().is_err());
+
+        // Cascade
+        let result = category::delete_with_points(&db, &events, 2).await.unwrap();
+        assert!(result.rows_affected == 1);
+        assert!(category::get(&db).await.unwrap().is_empty());
+        assert!(points::get(&db).await.unwrap().is_empty());
+        for id in [1, 2, 3] {
+            assert!(rx.try_recv().unwrap() == events::Event::PointsDeleted { id });
+        }
+        assert!(rx.try_recv().is_err());
+    }
 }
@@ -1,4 +1,5 @@
 pub mod category;
 pub mod points;
 pub mod rewards;
+pub mod session;
 pub mod user;
@@ -11,10 +11,30 @@ pub struct Model {
     pub id: i32,
     pub value: i32,
     pub user_id: i32,
+    pub status: Status,
     pub created_at: NaiveDateTime,
     pub modified_at: NaiveDateTime,
 }
 
+/// Where a reward is in its workflow, it can only move forward in the order given here
+#[derive(
+    Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, EnumIter, DeriveActiveEnum, Deserialize, Serialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// Asked for by the user, the value is already taken from their balance
+    #[default]
+    #[sea_orm(string_value = "requested")]
+    Requested,
+    /// Agreed to by an admin but not handed out yet
+    #[sea_orm(string_value = "approved")]
+    Approved,
+    /// Handed out to the user
+    #[sea_orm(string_value = "redeemed")]
+    Redeemed,
+}
+
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(
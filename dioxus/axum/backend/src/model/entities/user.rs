@@ -10,16 +10,35 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
     pub name: String,
+    #[serde(skip)]
+    pub password_hash: Option<String>,
+    pub role: Role,
     pub created_at: NaiveDateTime,
     pub modified_at: NaiveDateTime,
 }
 
+/// What a user is allowed to do
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Serialize)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Manages categories, users and awards points e.g. a parent
+    #[sea_orm(string_value = "admin")]
+    Admin,
+    /// Earns points and redeems rewards
+    #[default]
+    #[sea_orm(string_value = "user")]
+    User,
+}
+
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_many = "super::points::Entity")]
     Points,
     #[sea_orm(has_many = "super::rewards::Entity")]
     Rewards,
+    #[sea_orm(has_many = "super::session::Entity")]
+    Session,
 }
 
 impl Related<super::points::Entity> for Entity {
@@ -34,4 +53,10 @@ impl Related<super::rewards::Entity> for Entity {
     }
 }
 
+impl Related<super::session::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Session.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
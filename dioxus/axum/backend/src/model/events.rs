@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use super::{points, rewards, user};
+
+// Events kept for a slow subscriber before it starts missing them
+const CAPACITY: usize = 256;
+
+/// Change made to the data e.g. `{"event": "points_awarded", "data": {"id": 1, "value": 10, ...}}`
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum Event {
+    UserCreated(user::Model),
+    UserUpdated(user::Model),
+    UserDeleted { id: i32 },
+    PointsAwarded(points::Model),
+    PointsUpdated(points::Model),
+    PointsDeleted { id: i32 },
+    RewardRequested(rewards::Model),
+    RewardRedeemed(rewards::Model), // Sent instead of reward_updated once the reward is handed out
+    RewardUpdated(rewards::Model),
+    RewardDeleted { id: i32 },
+}
+
+/// Channel every mutation of the model sends its event on. Clones share the same channel.
+#[derive(Clone, Debug)]
+pub struct Events(broadcast::Sender<Event>);
+
+impl Default for Events {
+    fn default() -> Self {
+        Self(broadcast::channel(CAPACITY).0)
+    }
+}
+
+impl Events {
+    /// Subscribe to the events sent from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.0.subscribe()
+    }
+
+    /// Send the given event to all subscribers, it's dropped if there are none
+    pub fn emit(&self, event: Event) {
+        let _ = self.0.send(event);
+    }
+}
+
+// Unit tests
+// -------------------------------------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::Event;
+    use crate::prelude::*;
+
+    #[tokio::test]
+    async fn test_events() {
+        let db = test_db().await;
+        let events = Events::default();
+        let mut rx = events.subscribe();
+
+        let user = user::create(&db, &events, "user1").await.unwrap();
+        assert!(rx.recv().await.unwrap() == Event::UserCreated(user));
+        assert!(rx.try_recv().is_err());
+
+        // Rewards are only redeemed once they're handed out
+        category::create(&db, "category1", 1).await.unwrap();
+        points::create(&db, &events, 1, 1, 10).await.unwrap();
+        rx.recv().await.unwrap();
+        let reward = match ledger::redeem(&db, &events, 1, 6).await.unwrap() {
+            ledger::Redeemed::Reward(reward) => reward,
+            x => panic!("unexpected {:?}", x),
+        };
+        assert!(rx.recv().await.unwrap() == Event::RewardRequested(reward));
+        for (status, redeemed) in [(rewards::Status::Approved, false), (rewards::Status::Redeemed, true)] {
+            let reward = match ledger::amend(&db, &events, 1, None, None, Some(status)).await.unwrap() {
+                ledger::Redeemed::Reward(reward) => reward,
+                x => panic!("unexpected {:?}", x),
+            };
+            let event = if redeemed { Event::RewardRedeemed(reward) } else { Event::RewardUpdated(reward) };
+            assert!(rx.recv().await.unwrap() == event);
+        }
+        assert!(rx.try_recv().is_err());
+
+        let json = serde_json::to_value(Event::UserDeleted { id: 5 }).unwrap();
+        assert!(json == serde_json::json!({ "event": "user_deleted", "data": { "id": 5 } }));
+    }
+}
@@ -0,0 +1,365 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDateTime;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    category,
+    events::{Event, Events},
+    points, rewards,
+};
+
+/// Points earned by a user in one category
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CategoryTotal {
+    pub category_id: i32,
+    pub name: String,
+    pub total: i64,
+}
+
+/// Totals of what a user has earned and redeemed
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Balance {
+    pub user_id: i32,
+    pub categories: Vec<CategoryTotal>, // Ordered by category id
+    pub earned: i64,                    // Sum of all points
+    pub redeemed: i64,                  // Sum of all rewards
+    pub balance: i64,                   // What is left to redeem
+}
+
+/// Whether a ledger entry added or took away from the balance
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    Points,
+    Reward,
+}
+
+/// Points earned or reward redeemed with the balance after it
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LedgerEntry {
+    pub kind: EntryKind,
+    pub id: i32,                  // Id of the points or reward
+    pub category_id: Option<i32>, // Category of points
+    pub value: i32,               // Always positive, rewards are subtracted from the balance
+    pub balance: i64,             // Running balance after this entry
+    pub created_at: NaiveDateTime,
+}
+
+/// Outcome of trying to redeem a reward
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Redeemed {
+    Reward(rewards::Model),
+    Overdrawn { balance: i64 },        // The reward is worth more than the balance
+    Locked { status: rewards::Status }, // The reward's status doesn't allow the change
+}
+
+/// Get the totals per category, the total redeemed and the balance of the given user
+pub async fn balance<C: ConnectionTrait>(db: &C, user_id: i32) -> Result<Balance, anyhow::Error> {
+    let points = points::Entity::find().filter(points::Column::UserId.eq(user_id)).all(db).await?;
+    let rewards = rewards::Entity::find().filter(rewards::Column::UserId.eq(user_id)).all(db).await?;
+
+    let mut totals = BTreeMap::<i32, i64>::new();
+    for x in points.iter() {
+        *totals.entry(x.category_id).or_default() += x.value as i64;
+    }
+    let names = category::Entity::find()
+        .filter(category::Column::Id.is_in(totals.keys().copied()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|x| (x.id, x.name))
+        .collect::<BTreeMap<_, _>>();
+    let categories = totals
+        .into_iter()
+        .map(|(category_id, total)| CategoryTotal {
+            category_id,
+            name: names.get(&category_id).cloned().unwrap_or_default(),
+            total,
+        })
+        .collect::<Vec<_>>();
+
+    let earned = categories.iter().map(|x| x.total).sum::<i64>();
+    let redeemed = rewards.iter().map(|x| x.value as i64).sum::<i64>();
+    Ok(Balance { user_id, categories, earned, redeemed, balance: earned - redeemed })
+}
+
+/// Get the given user's points and rewards merged into one history in the order they were created. Points come
+/// before rewards with the same timestamp which only happens for rows created before sub-second timestamps.
+pub async fn ledger(db: &DatabaseConnection, user_id: i32) -> Result<Vec<LedgerEntry>, anyhow::Error> {
+    let points = points::Entity::find()
+        .filter(points::Column::UserId.eq(user_id))
+        .order_by_asc(points::Column::CreatedAt)
+        .order_by_asc(points::Column::Id)
+        .all(db)
+        .await?;
+    let rewards = rewards::Entity::find()
+        .filter(rewards::Column::UserId.eq(user_id))
+        .order_by_asc(rewards::Column::CreatedAt)
+        .order_by_asc(rewards::Column::Id)
+        .all(db)
+        .await?;
+
+    let mut entries = points
+        .into_iter()
+        .map(|x| LedgerEntry {
+            kind: EntryKind::Points,
+            id: x.id,
+            category_id: Some(x.category_id),
+            value: x.value,
+            balance: 0,
+            created_at: x.created_at,
+        })
+        .chain(rewards.into_iter().map(|x| LedgerEntry {
+            kind: EntryKind::Reward,
+            id: x.id,
+            category_id: None,
+            value: x.value,
+            balance: 0,
+            created_at: x.created_at,
+        }))
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|x| (x.created_at, x.kind == EntryKind::Reward, x.id));
+
+    let mut balance = 0;
+    for entry in entries.iter_mut() {
+        balance += match entry.kind {
+            EntryKind::Points => entry.value as i64,
+            EntryKind::Reward => -(entry.value as i64),
+        };
+        entry.balance = balance;
+    }
+    Ok(entries)
+}
+
+/// Request a reward for the given user unless it's worth more than their balance. The balance is checked in
+/// the same transaction as the reward is created in so that concurrent redemptions can't overdraw it.
+pub async fn redeem(
+    db: &DatabaseConnection, events: &Events, user_id: i32, value: i32,
+) -> Result<Redeemed, anyhow::Error> {
+    let tx = db.begin().await?;
+    let balance = balance(&tx, user_id).await?.balance;
+    if value as i64 > balance {
+        tx.rollback().await?;
+        return Ok(Redeemed::Overdrawn { balance });
+    }
+    let reward = rewards::create(&tx, user_id, value).await?;
+    tx.commit().await?;
+    events.emit(Event::RewardRequested(reward.clone()));
+    Ok(Redeemed::Reward(reward))
+}
+
+/// Update only the given fields of the reward unless that overdraws the balance of the user it ends up with.
+/// Only a change of value or user is checked against the balance so that the status of a reward can always be
+/// moved forward. The value and user of a redeemed reward are fixed and the status only moves a step at a time.
+/// Moving to redeemed sends [`Event::RewardRedeemed`] rather than [`Event::RewardUpdated`].
+pub async fn amend(
+    db: &DatabaseConnection, events: &Events, id: i32, user_id: Option<i32>, value: Option<i32>,
+    status: Option<rewards::Status>,
+) -> Result<Redeemed, anyhow::Error> {
+    let tx = db.begin().await?;
+    let mut handed_out = false;
+    if let Some(current) = rewards::Entity::find_by_id(id).one(&tx).await? {
+        let redeemed = current.status == rewards::Status::Redeemed && (user_id.is_some() || value.is_some());
+        if redeemed || status.is_some_and(|x| !current.status.can_move_to(x)) {
+            tx.rollback().await?;
+            return Ok(Redeemed::Locked { status: current.status });
+        }
+        handed_out = current.status != rewards::Status::Redeemed && status == Some(rewards::Status::Redeemed);
+    }
+    let reward = rewards::patch(&tx, id, user_id, value, status).await?;
+    if user_id.is_some() || value.is_some() {
+        let balance = balance(&tx, reward.user_id).await?.balance;
+        if balance < 0 {
+            tx.rollback().await?;
+            return Ok(Redeemed::Overdrawn { balance: balance + reward.value as i64 }); // What was left for it
+        }
+    }
+    tx.commit().await?;
+    events.emit(match handed_out {
+        true => Event::RewardRedeemed(reward.clone()),
+        false => Event::RewardUpdated(reward.clone()),
+    });
+    Ok(Redeemed::Reward(reward))
+}
+
+/// Delete the reward giving its value back to its user unless it's redeemed already. Nothing is returned if
+/// there is no such reward.
+pub async fn withdraw(
+    db: &DatabaseConnection, events: &Events, id: i32,
+) -> Result<Option<Redeemed>, anyhow::Error> {
+    let tx = db.begin().await?;
+    let reward = match rewards::Entity::find_by_id(id).one(&tx).await? {
+        Some(reward) if reward.status == rewards::Status::Redeemed => {
+            tx.rollback().await?;
+            return Ok(Some(Redeemed::Locked { status: reward.status }));
+        },
+        Some(reward) => reward,
+        None => {
+            tx.rollback().await?;
+            return Ok(None);
+        },
+    };
+    rewards::delete_by_id(&tx, id).await?;
+    tx.commit().await?;
+    events.emit(Event::RewardDeleted { id });
+    Ok(Some(Redeemed::Reward(reward)))
+}
+
+// Unit tests
+// -------------------------------------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use crate::model::ledger::{EntryKind, Redeemed};
+    use crate::prelude::*;
+
+    #[tokio::test]
+    async fn test_balance() {
+        let db = test_db().await;
+        let events = Events::default();
+
+        // Load test data
+        user::create_if_not(&db, &events, "user1").await.unwrap();
+        user::create_if_not(&db, &events, "user2").await.unwrap();
+
+        category::create_if_not(&db, "category1", 1).await.unwrap();
+        category::create_if_not(&db, "category2", 10).await.unwrap();
+
+        points::create(&db, &events, 1, 2, 10).await.unwrap();
+        points::create(&db, &events, 1, 1, 20).await.unwrap();
+        points::create(&db, &events, 1, 2, 30).await.unwrap();
+        points::create(&db, &events, 2, 1, 1000).await.unwrap();
+
+        rewards::create(&db, 1, 15).await.unwrap();
+        rewards::create(&db, 2, 100).await.unwrap();
+
+        let balance = ledger::balance(&db, 1).await.unwrap();
+        assert!(balance.user_id == 1);
+        assert!(balance.categories.len() == 2);
+        assert!(balance.categories[0].category_id == 1);
+        assert!(balance.categories[0].name == "category1");
+        assert!(balance.categories[0].total == 20);
+        assert!(balance.categories[1].category_id == 2);
+        assert!(balance.categories[1].total == 40);
+        assert!(balance.earned == 60);
+        assert!(balance.redeemed == 15);
+        assert!(balance.balance == 45);
+
+        // Nothing for an unknown user
+        let balance = ledger::balance(&db, 3).await.unwrap();
+        assert!(balance.categories.is_empty());
+        assert!(balance.balance == 0);
+    }
+
+    #[tokio::test]
+    async fn test_ledger() {
+        let db = test_db().await;
+        let events = Events::default();
+        user::create_if_not(&db, &events, "user1").await.unwrap();
+        category::create_if_not(&db, "category1", 1).await.unwrap();
+
+        points::create(&db, &events, 1, 1, 10).await.unwrap();
+        rewards::create(&db, 1, 4).await.unwrap();
+        points::create(&db, &events, 1, 1, 20).await.unwrap();
+
+        // Created within the same second yet still in the order they were created
+        let ledger = ledger::ledger(&db, 1).await.unwrap();
+        assert!(ledger.len() == 3);
+        assert!(ledger.iter().map(|x| x.kind).eq([EntryKind::Points, EntryKind::Reward, EntryKind::Points]));
+        assert!(ledger.iter().map(|x| x.balance).eq([10, 6, 26]));
+        assert!(ledger[1].category_id.is_none());
+        assert!(ledger.windows(2).all(|x| x[0].created_at <= x[1].created_at));
+    }
+
+    #[tokio::test]
+    async fn test_redeem() {
+        let db = test_db().await;
+        let events = Events::default();
+        user::create_if_not(&db, &events, "user1").await.unwrap();
+        category::create_if_not(&db, "category1", 1).await.unwrap();
+        points::create(&db, &events, 1, 1, 10).await.unwrap();
+
+        let reward = match ledger::redeem(&db, &events, 1, 6).await.unwrap() {
+            Redeemed::Reward(reward) => reward,
+            x => panic!("unexpected {:?}", x),
+        };
+        assert!(reward.user_id == 1);
+        assert!(reward.value == 6);
+
+        // Only 4 left
+        assert!(ledger::redeem(&db, &events, 1, 5).await.unwrap() == Redeemed::Overdrawn { balance: 4 });
+        assert!(rewards::get(&db).await.unwrap().len() == 1);
+        assert!(matches!(ledger::redeem(&db, &events, 1, 4).await.unwrap(), Redeemed::Reward(_)));
+        assert!(ledger::balance(&db, 1).await.unwrap().balance == 0);
+    }
+
+    #[tokio::test]
+    async fn test_amend() {
+        let db = test_db().await;
+        let events = Events::default();
+        user::create_if_not(&db, &events, "user1").await.unwrap();
+        user::create_if_not(&db, &events, "user2").await.unwrap();
+        category::create_if_not(&db, "category1", 1).await.unwrap();
+        points::create(&db, &events, 1, 1, 10).await.unwrap();
+        points::create(&db, &events, 2, 1, 3).await.unwrap();
+        rewards::create(&db, 1, 6).await.unwrap();
+
+        // Only 10 to spend on it
+        let result = ledger::amend(&db, &events, 1, None, Some(11), None).await.unwrap();
+        assert!(result == Redeemed::Overdrawn { balance: 10 });
+        let result = ledger::amend(&db, &events, 1, Some(2), None, None).await.unwrap();
+        assert!(result == Redeemed::Overdrawn { balance: 3 });
+        let reward = rewards::get_by_id(&db, 1).await.unwrap().unwrap();
+        assert!(reward.user_id == 1);
+        assert!(reward.value == 6);
+
+        let status = Some(rewards::Status::Approved);
+        let reward = match ledger::amend(&db, &events, 1, None, Some(10), status).await.unwrap() {
+            Redeemed::Reward(reward) => reward,
+            x => panic!("unexpected {:?}", x),
+        };
+        assert!(reward.value == 10);
+        assert!(reward.status == rewards::Status::Approved);
+        assert!(ledger::balance(&db, 1).await.unwrap().balance == 0);
+
+        // Redeemed rewards keep their value and user
+        let approved = Redeemed::Locked { status: rewards::Status::Approved };
+        let result = ledger::amend(&db, &events, 1, None, None, Some(rewards::Status::Requested)).await.unwrap();
+        assert!(result == approved);
+        assert!(matches!(
+            ledger::amend(&db, &events, 1, None, None, Some(rewards::Status::Redeemed)).await.unwrap(),
+            Redeemed::Reward(_)
+        ));
+        let redeemed = Redeemed::Locked { status: rewards::Status::Redeemed };
+        assert!(ledger::amend(&db, &events, 1, None, Some(5), None).await.unwrap() == redeemed);
+        assert!(ledger::amend(&db, &events, 1, Some(2), None, None).await.unwrap() == redeemed);
+        assert!(rewards::get_by_id(&db, 1).await.unwrap().unwrap().value == 10);
+    }
+
+    #[tokio::test]
+    async fn test_withdraw() {
+        let db = test_db().await;
+        let events = Events::default();
+        user::create_if_not(&db, &events, "user1").await.unwrap();
+        category::create_if_not(&db, "category1", 1).await.unwrap();
+        points::create(&db, &events, 1, 1, 10).await.unwrap();
+        rewards::create(&db, 1, 4).await.unwrap();
+        rewards::create(&db, 1, 6).await.unwrap();
+        rewards::patch(&db, 2, None, None, Some(rewards::Status::Redeemed)).await.unwrap();
+
+        // Requested rewards give their value back
+        let reward = rewards::get_by_id(&db, 1).await.unwrap().unwrap();
+        assert!(ledger::withdraw(&db, &events, 1).await.unwrap() == Some(Redeemed::Reward(reward)));
+        assert!(ledger::balance(&db, 1).await.unwrap().balance == 4);
+        assert!(ledger::withdraw(&db, &events, 1).await.unwrap().is_none());
+
+        // Redeemed ones are handed out already
+        let redeemed = Redeemed::Locked { status: rewards::Status::Redeemed };
+        assert!(ledger::withdraw(&db, &events, 2).await.unwrap() == Some(redeemed));
+        assert!(rewards::get(&db).await.unwrap().len() == 1);
+        assert!(ledger::balance(&db, 1).await.unwrap().balance == 4);
+    }
+}
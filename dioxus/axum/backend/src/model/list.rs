@@ -0,0 +1,140 @@
+use sea_orm::{
+    sea_query::{Expr, IntoCondition, LikeExpr, SimpleExpr},
+    ColumnTrait, Condition, DatabaseConnection, EntityTrait, FromQueryResult, Iterable, Order, PaginatorTrait,
+    PrimaryKeyToColumn, QueryFilter, QueryOrder,
+};
+
+/// Entity that can be listed a page at a time
+pub trait Listable: EntityTrait {
+    /// Names of the columns the rows can be sorted by
+    const SORT: &'static [&'static str];
+
+    /// Condition matching the rows for the given search text
+    fn search(q: &str) -> Condition;
+}
+
+/// Which page of rows to get, how to sort them and what to search for
+#[derive(Clone, Debug)]
+pub struct Listing<E: EntityTrait> {
+    pub page: u64,     // Starting at 1
+    pub per_page: u64, // Rows per page
+    pub sort: E::Column,
+    pub order: Order,
+    pub q: Option<String>, // Only rows matching this if given
+}
+
+/// One page of rows with the totals across all pages
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: u64, // Rows across all pages
+    pub pages: u64, // Number of pages
+}
+
+/// Match rows whose column contains the given text as is. LIKE wildcards in the text are escaped so that
+/// searching for `_` or `%` only matches those characters.
+pub fn contains<C: ColumnTrait>(column: C, q: &str) -> SimpleExpr {
+    let q = q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    Expr::col((column.entity_name(), column)).like(LikeExpr::new(format!("%{q}%")).escape('\\'))
+}
+
+/// Get the given page of rows sorted by the given column. Rows that sort the same are ordered by primary key
+/// so that no row shows up on two pages.
+pub async fn list<E>(db: &DatabaseConnection, listing: &Listing<E>) -> Result<Page<E::Model>, anyhow::Error>
+where
+    E: Listable,
+    E::Model: FromQueryResult + Send + Sync,
+{
+    let mut select = E::find().order_by(listing.sort, listing.order.clone());
+    for key in E::PrimaryKey::iter() {
+        select = select.order_by_asc(key.into_column());
+    }
+    if let Some(q) = &listing.q {
+        select = select.filter(E::search(q).into_condition());
+    }
+
+    let paginator = select.paginate(db, listing.per_page);
+    let totals = paginator.num_items_and_pages().await?;
+    let items = paginator.fetch_page(listing.page.saturating_sub(1)).await?;
+    Ok(Page { items, total: totals.number_of_items, pages: totals.number_of_pages })
+}
+
+// Unit tests
+// -------------------------------------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{list, Listing};
+    use crate::prelude::*;
+    use sea_orm::Order;
+
+    #[tokio::test]
+    async fn test_list() {
+        let db = test_db().await;
+        let events = Events::default();
+        for name in ["foo3", "bar", "foo1", "foo2", "baz"] {
+            user::create(&db, &events, name).await.unwrap();
+        }
+
+        let mut listing = Listing::<user::Entity> {
+            page: 1,
+            per_page: 2,
+            sort: user::Column::Name,
+            order: Order::Desc,
+            q: Some("foo".into()),
+        };
+        let page = list(&db, &listing).await.unwrap();
+        assert!(page.total == 3);
+        assert!(page.pages == 2);
+        assert!(page.items.iter().map(|x| x.name.as_str()).collect::<Vec<_>>() == ["foo3", "foo2"]);
+
+        listing.page = 2;
+        let page = list(&db, &listing).await.unwrap();
+        assert!(page.items.len() == 1);
+        assert!(page.items[0].name == "foo1");
+
+        // Past the last page
+        listing.page = 3;
+        assert!(list(&db, &listing).await.unwrap().items.is_empty());
+
+        let listing =
+            Listing::<user::Entity> { page: 1, per_page: 10, sort: user::Column::Id, order: Order::Asc, q: None };
+        let page = list(&db, &listing).await.unwrap();
+        assert!(page.total == 5);
+        assert!(page.pages == 1);
+        assert!(page.items[0].name == "foo3");
+
+        // Wildcards are searched for as is
+        user::create(&db, &events, "foo_bar").await.unwrap();
+        user::create(&db, &events, "50%").await.unwrap();
+        for (q, name) in [("_", "foo_bar"), ("%", "50%"), ("o_b", "foo_bar")] {
+            let listing = Listing::<user::Entity> {
+                page: 1,
+                per_page: 10,
+                sort: user::Column::Id,
+                order: Order::Asc,
+                q: Some(q.into()),
+            };
+            let page = list(&db, &listing).await.unwrap();
+            assert!(page.items.iter().map(|x| x.name.as_str()).eq([name]));
+        }
+
+        // Rewards are found by the name of their user or their status
+        rewards::create(&db, 1, 10).await.unwrap();
+        rewards::create(&db, 2, 20).await.unwrap();
+        rewards::patch(&db, 2, None, None, Some(rewards::Status::Approved)).await.unwrap();
+        let mut listing = Listing::<rewards::Entity> {
+            page: 1,
+            per_page: 10,
+            sort: rewards::Column::Value,
+            order: Order::Asc,
+            q: Some("foo".into()),
+        };
+        let page = list(&db, &listing).await.unwrap();
+        assert!(page.items.len() == 1);
+        assert!(page.items[0].user_id == 1);
+        listing.q = Some("approved".into());
+        let page = list(&db, &listing).await.unwrap();
+        assert!(page.items.len() == 1);
+        assert!(page.items[0].user_id == 2);
+    }
+}
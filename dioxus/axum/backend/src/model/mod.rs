@@ -1,10 +1,18 @@
 mod entities;
 
 pub mod category;
+pub mod events;
+pub mod ledger;
+pub mod list;
 pub mod points;
 pub mod rewards;
+pub mod session;
 pub mod user;
 
+pub use events::Events;
+
+use std::sync::atomic::{AtomicI64, Ordering};
+
 use crate::migrations;
 use sea_orm::{Database, DatabaseConnection, DbErr};
 use sea_orm_migration::MigratorTrait;
@@ -18,11 +26,40 @@ pub async fn init_db(db_url: &str) -> Result<DatabaseConnection, DbErr> {
     let db = Database::connect(db_url).await?;
 
     info!("Applying all pending database migrations...");
-    migrations::Migrator::up(&db, None).await.expect("Failed to execute migrations!");
+    migrations::Migrator::up(&db, None).await?;
 
     Ok(db)
 }
 
+/// Load example users, categories, points and rewards unless the users already exist
+pub async fn seed(db: &DatabaseConnection) -> Result<(), anyhow::Error> {
+    if user::get_by_name(db, "user1").await?.is_some() {
+        info!("Example data already loaded");
+        return Ok(());
+    }
+
+    // Nothing is listening for the changes while seeding
+    let events = Events::default();
+
+    // Look up the ids as an admin may have been created first
+    let user1 = user::create(db, &events, "user1").await?.id;
+    let user2 = user::create(db, &events, "user2").await?.id;
+    let user3 = user::create(db, &events, "user3").await?.id;
+
+    let category1 = category::create_if_not(db, "category1", 1).await?.id;
+    let category2 = category::create_if_not(db, "category2", 10).await?.id;
+    let category3 = category::create_if_not(db, "category3", 100).await?.id;
+
+    points::create(db, &events, user1, category3, 10).await?;
+    points::create(db, &events, user2, category2, 100).await?;
+    points::create(db, &events, user3, category1, 1000).await?;
+
+    rewards::create(db, user1, 10).await?;
+    rewards::create(db, user2, 100).await?;
+    rewards::create(db, user3, 1000).await?;
+    Ok(())
+}
+
 /// Initialize an in memory test database
 pub async fn test_db() -> DatabaseConnection {
     let db = Database::connect("sqlite::memory:").await.expect("Failed to connect to database");
@@ -35,3 +72,38 @@ pub fn now() -> chrono::NaiveDateTime {
     let timestamp = chrono::Utc::now().naive_local().timestamp();
     chrono::NaiveDateTime::from_timestamp_opt(timestamp, 0).unwrap()
 }
+
+/// Get current time with microseconds that is strictly increasing within the process so that rows created in
+/// the same second keep their order. Used for both the created and modified time of points and rewards as
+/// they make up a user's ledger, other rows keep to whole seconds from [`now`].
+pub fn timestamp() -> chrono::NaiveDateTime {
+    use chrono::TimeZone;
+    static LAST: AtomicI64 = AtomicI64::new(0);
+
+    let now = chrono::Utc::now().timestamp_micros();
+    let last = LAST.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| Some(now.max(last + 1))).unwrap();
+    chrono::Utc.timestamp_micros(now.max(last + 1)).unwrap().naive_utc()
+}
+
+// Unit tests
+// -------------------------------------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    #[tokio::test]
+    async fn test_seed() {
+        let db = test_db().await;
+        let events = Events::default();
+        user::create_admin_if_not(&db, &events, "admin", "password").await.unwrap();
+
+        model::seed(&db).await.unwrap();
+        model::seed(&db).await.unwrap(); // Only loaded once
+        assert!(user::get(&db).await.unwrap().len() == 4);
+        assert!(points::get(&db).await.unwrap().len() == 3);
+
+        // Owned by the example users and not the admin
+        let user1 = user::get_by_name(&db, "user1").await.unwrap().unwrap();
+        assert!(ledger::balance(&db, user1.id).await.unwrap().earned == 10);
+    }
+}
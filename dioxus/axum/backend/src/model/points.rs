@@ -5,16 +5,23 @@ use sea_orm::{
     QueryFilter, Unchanged,
 };
 
+use super::events::{Event, Events};
+
 /// Create the given points in the database
-pub async fn create(db: &DatabaseConnection, user_id: i32, category_id: i32, value: i32) -> Result<Model, DbErr> {
-    Ok(ActiveModel {
+pub async fn create(
+    db: &DatabaseConnection, events: &Events, user_id: i32, category_id: i32, value: i32,
+) -> Result<Model, DbErr> {
+    let model = ActiveModel {
         user_id: Set(user_id),
         category_id: Set(category_id),
         value: Set(value),
+        created_at: Set(super::timestamp()),
         ..Default::default()
     }
     .insert(db)
-    .await?)
+    .await?;
+    events.emit(Event::PointsAwarded(model.clone()));
+    Ok(model)
 }
 
 /// Get all points from the database
@@ -22,6 +29,11 @@ pub async fn get(db: &DatabaseConnection) -> Result<Vec<Model>, DbErr> {
     Ok(Entity::find().all(db).await?)
 }
 
+/// Get the given points from the database by id
+pub async fn get_by_id(db: &DatabaseConnection, id: i32) -> Result<Option<Model>, DbErr> {
+    Ok(Entity::find_by_id(id).one(db).await?)
+}
+
 /// Get points from the database for the given user
 pub async fn get_by_user_id(db: &DatabaseConnection, user_id: i32) -> Result<Vec<Model>, DbErr> {
     Ok(Entity::find().filter(Column::UserId.eq(user_id)).all(db).await?)
@@ -34,21 +46,31 @@ pub async fn get_by_category_id(db: &DatabaseConnection, category_id: i32) -> Re
 
 /// Update the given points in the database by id
 pub async fn update(
-    db: &DatabaseConnection, id: i32, user_id: i32, category_id: i32, value: i32,
+    db: &DatabaseConnection, events: &Events, id: i32, user_id: i32, category_id: i32, value: i32,
 ) -> Result<Model, anyhow::Error> {
     let model = ActiveModel {
         id: Unchanged(id),
         user_id: Set(user_id),
         category_id: Set(category_id),
         value: Set(value),
-        modified_at: Set(super::now()),
+        modified_at: Set(super::timestamp()),
         ..Default::default()
     }
     .update(db)
     .await?;
+    events.emit(Event::PointsUpdated(model.clone()));
     Ok(model)
 }
 
+/// Delete the given points from the database
+pub async fn delete_by_id(
+    db: &DatabaseConnection, events: &Events, id: i32,
+) -> Result<DeleteResult, anyhow::Error> {
+    let result = ActiveModel { id: Set(id), ..Default::default() }.delete(db).await?;
+    events.emit(Event::PointsDeleted { id });
+    Ok(result)
+}
+
 /// Delete all points for the given user from the database
 /// * `user_id: i32` id of the user to delete the points for
 pub async fn delete_by_user_id(db: &DatabaseConnection, user_id: i32) -> Result<DeleteResult, anyhow::Error> {
@@ -72,20 +94,21 @@ mod tests {
     #[tokio::test]
     async fn test_crud() {
         let db = test_db().await;
+        let events = Events::default();
 
         // Load test data
-        user::create_if_not(&db, "user1").await.unwrap();
-        user::create_if_not(&db, "user2").await.unwrap();
-        user::create_if_not(&db, "user3").await.unwrap();
+        user::create_if_not(&db, &events, "user1").await.unwrap();
+        user::create_if_not(&db, &events, "user2").await.unwrap();
+        user::create_if_not(&db, &events, "user3").await.unwrap();
 
         category::create_if_not(&db, "category1", 1).await.unwrap();
         category::create_if_not(&db, "category2", 10).await.unwrap();
         category::create_if_not(&db, "category3", 100).await.unwrap();
 
-        points::create(&db, 1, 3, 10).await.unwrap();
-        points::create(&db, 1, 2, 20).await.unwrap();
-        points::create(&db, 2, 2, 100).await.unwrap();
-        points::create(&db, 3, 1, 1000).await.unwrap();
+        points::create(&db, &events, 1, 3, 10).await.unwrap();
+        points::create(&db, &events, 1, 2, 20).await.unwrap();
+        points::create(&db, &events, 2, 2, 100).await.unwrap();
+        points::create(&db, &events, 3, 1, 1000).await.unwrap();
 
         // Get
         let points = points::get_by_user_id(&db, 1).await.unwrap();
@@ -103,12 +126,16 @@ mod tests {
         assert!(points2.user_id == 2);
 
         // Update
-        let points = points::update(&db, 1, 1, 1, 50).await.unwrap();
+        let points = points::update(&db, &events, 1, 1, 1, 50).await.unwrap();
         assert!(points.id == 1);
         assert!(points.user_id == 1);
         assert!(points.category_id == 1);
         assert!(points.value == 50);
 
+        let points = points::get_by_id(&db, 2).await.unwrap().unwrap();
+        assert!(points.value == 20);
+        assert!(points::get_by_id(&db, 5).await.unwrap().is_none());
+
         // Delete by user id
         let points = points::delete_by_user_id(&db, 3).await.unwrap();
         assert!(points.rows_affected == 1);
@@ -124,5 +151,10 @@ mod tests {
         let points = points::get(&db).await.unwrap();
         assert!(points.len() == 1);
         assert!(points.iter().find(|x| x.value == 50).is_some());
+
+        // Delete by id
+        let points = points::delete_by_id(&db, &events, 1).await.unwrap();
+        assert!(points.rows_affected == 1);
+        assert!(points::get(&db).await.unwrap().is_empty());
     }
 }
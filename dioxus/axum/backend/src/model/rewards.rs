@@ -1,14 +1,41 @@
 pub use crate::model::entities::rewards::*;
 
 use sea_orm::{
-    ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DatabaseConnection, DeleteResult,
-    EntityTrait, QueryFilter, TransactionTrait, Unchanged,
+    sea_query::Query,
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, DeleteResult, EntityTrait, QueryFilter,
+    Unchanged,
 };
 
-/// Create the given rewards in the database
+use super::{
+    list::{self, Listable},
+    user,
+};
+
+impl Status {
+    /// Whether a reward can move from this status to the given one. Only a single step forward is allowed with
+    /// staying put being a no-op.
+    pub fn can_move_to(self, to: Status) -> bool {
+        use Status::*;
+        self == to || matches!((self, to), (Requested, Approved) | (Approved, Redeemed))
+    }
+}
+
+/// Create the given rewards in the database as requested. No event is sent as this may be part of a
+/// transaction, see [`super::ledger::redeem`].
 /// * `user_id: i32` id of the user to create the rewards for
-pub async fn create(db: &DatabaseConnection, user_id: i32, value: i32) -> Result<Model, anyhow::Error> {
-    Ok(ActiveModel { user_id: Set(user_id), value: Set(value), ..Default::default() }.insert(db).await?)
+pub async fn create<C: ConnectionTrait>(db: &C, user_id: i32, value: i32) -> Result<Model, anyhow::Error> {
+    let model = ActiveModel {
+        user_id: Set(user_id),
+        value: Set(value),
+        status: Set(Status::Requested), // The column defaults to redeemed for rewards from before the workflow
+        created_at: Set(super::timestamp()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    Ok(model)
 }
 
 /// Get all rewards from the database
@@ -16,6 +43,20 @@ pub async fn get(db: &DatabaseConnection) -> Result<Vec<Model>, anyhow::Error> {
     Ok(Entity::find().all(db).await?)
 }
 
+/// Rewards are searched by the name of their user or their status
+impl Listable for Entity {
+    const SORT: &'static [&'static str] = &["id", "value", "user_id", "status", "created_at", "modified_at"];
+
+    fn search(q: &str) -> Condition {
+        let users = Query::select()
+            .column(user::Column::Id)
+            .from(user::Entity)
+            .and_where(list::contains(user::Column::Name, q))
+            .to_owned();
+        Condition::any().add(Column::UserId.in_subquery(users)).add(Column::Status.eq(q))
+    }
+}
+
 /// Get the given rewards from the database by id
 pub async fn get_by_id(db: &DatabaseConnection, id: i32) -> Result<Option<Model>, anyhow::Error> {
     Ok(Entity::find_by_id(id).one(db).await?)
@@ -27,13 +68,17 @@ pub async fn get_by_user_id(db: &DatabaseConnection, user_id: i32) -> Result<Vec
     Ok(Entity::find().filter(Column::UserId.eq(user_id)).all(db).await?)
 }
 
-/// Update the given reward in the database by id
-pub async fn update(db: &DatabaseConnection, id: i32, user_id: i32, value: i32) -> Result<Model, anyhow::Error> {
+/// Update only the given fields of the reward in the database by id. No event is sent as this may be part of a
+/// transaction, see [`super::ledger::amend`].
+pub async fn patch<C: ConnectionTrait>(
+    db: &C, id: i32, user_id: Option<i32>, value: Option<i32>, status: Option<Status>,
+) -> Result<Model, anyhow::Error> {
     let model = ActiveModel {
         id: Unchanged(id),
-        user_id: Set(user_id),
-        value: Set(value),
-        modified_at: Set(super::now()),
+        user_id: user_id.map_or(NotSet, Set),
+        value: value.map_or(NotSet, Set),
+        status: status.map_or(NotSet, Set),
+        modified_at: Set(super::timestamp()),
         ..Default::default()
     }
     .update(db)
@@ -41,6 +86,12 @@ pub async fn update(db: &DatabaseConnection, id: i32, user_id: i32, value: i32)
     Ok(model)
 }
 
+/// Delete the given reward from the database. No event is sent as this may be part of a transaction, see
+/// [`super::ledger::withdraw`].
+pub async fn delete_by_id<C: ConnectionTrait>(db: &C, id: i32) -> Result<DeleteResult, anyhow::Error> {
+    Ok(ActiveModel { id: Set(id), ..Default::default() }.delete(db).await?)
+}
+
 /// Delete all rewards for the given user from the database
 /// * `user_id: i32` id of the user to delete the rewards for
 pub async fn delete_by_user_id(db: &DatabaseConnection, user_id: i32) -> Result<DeleteResult, anyhow::Error> {
@@ -56,20 +107,21 @@ mod tests {
     #[tokio::test]
     async fn test_crud() {
         let db = test_db().await;
+        let events = Events::default();
 
         // Load test data
-        user::create_if_not(&db, "user1").await.unwrap();
-        user::create_if_not(&db, "user2").await.unwrap();
-        user::create_if_not(&db, "user3").await.unwrap();
+        user::create_if_not(&db, &events, "user1").await.unwrap();
+        user::create_if_not(&db, &events, "user2").await.unwrap();
+        user::create_if_not(&db, &events, "user3").await.unwrap();
 
         category::create_if_not(&db, "category1", 1).await.unwrap();
         category::create_if_not(&db, "category2", 10).await.unwrap();
         category::create_if_not(&db, "category3", 100).await.unwrap();
 
-        points::create(&db, 1, 3, 10).await.unwrap();
-        points::create(&db, 1, 2, 20).await.unwrap();
-        points::create(&db, 2, 2, 100).await.unwrap();
-        points::create(&db, 3, 1, 1000).await.unwrap();
+        points::create(&db, &events, 1, 3, 10).await.unwrap();
+        points::create(&db, &events, 1, 2, 20).await.unwrap();
+        points::create(&db, &events, 2, 2, 500).await.unwrap();
+        points::create(&db, &events, 3, 1, 1000).await.unwrap();
 
         // Create
         rewards::create(&db, 1, 10).await.unwrap();
@@ -84,7 +136,10 @@ mod tests {
         assert!(rewards.iter().find(|x| x.value == 200).is_some());
 
         // Update
-        let reward1 = rewards::update(&db, 2, 2, 150).await.unwrap();
+        let reward1 = match ledger::amend(&db, &events, 2, Some(2), Some(150), None).await.unwrap() {
+            ledger::Redeemed::Reward(reward) => reward,
+            x => panic!("unexpected {:?}", x),
+        };
         let reward2 = rewards::get_by_id(&db, 2).await.unwrap();
         assert!(reward1.user_id == 2);
         assert!(reward2.as_ref().unwrap().user_id == 2);
@@ -101,4 +156,38 @@ mod tests {
         assert!(rewards.iter().find(|x| x.user_id == 1).is_some());
         assert!(rewards.iter().find(|x| x.user_id == 3).is_some());
     }
+
+    #[tokio::test]
+    async fn test_patch_and_delete() {
+        let db = test_db().await;
+        let events = Events::default();
+        user::create_if_not(&db, &events, "user1").await.unwrap();
+        user::create_if_not(&db, &events, "user2").await.unwrap();
+        rewards::create(&db, 1, 100).await.unwrap();
+        rewards::create(&db, 2, 200).await.unwrap();
+
+        // Patch
+        let reward = rewards::patch(&db, 2, None, None, Some(rewards::Status::Approved)).await.unwrap();
+        assert!(reward.status == rewards::Status::Approved);
+        assert!(reward.value == 200);
+        let reward = rewards::patch(&db, 2, None, Some(120), None).await.unwrap();
+        assert!(reward.status == rewards::Status::Approved);
+        assert!(reward.value == 120);
+        assert!(rewards::get_by_id(&db, 1).await.unwrap().unwrap().status == rewards::Status::Requested);
+
+        // Status only moves one step forward at a time
+        use rewards::Status;
+        assert!(Status::Requested.can_move_to(Status::Approved));
+        assert!(Status::Approved.can_move_to(Status::Redeemed));
+        assert!(Status::Approved.can_move_to(Status::Approved));
+        assert!(!Status::Requested.can_move_to(Status::Redeemed));
+        assert!(!Status::Redeemed.can_move_to(Status::Approved));
+
+        // Delete
+        let result = rewards::delete_by_id(&db, 1).await.unwrap();
+        assert!(result.rows_affected == 1);
+        let rewards = rewards::get(&db).await.unwrap();
+        assert!(rewards.len() == 1);
+        assert!(rewards[0].id == 2);
+    }
 }
@@ -0,0 +1,111 @@
+pub use crate::model::entities::session::*;
+
+use chrono::Duration;
+use rand::{rngs::OsRng, RngCore};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DeleteResult, EntityTrait, ModelTrait,
+    QueryFilter,
+};
+
+use super::user;
+
+/// Number of days a session lasts before having to log in again
+pub const SESSION_DAYS: i64 = 7;
+
+/// Create a new session for the given user with a random token
+/// * `user_id: i32` id of the user to log in
+pub async fn create(db: &DatabaseConnection, user_id: i32) -> Result<Model, anyhow::Error> {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let token = bytes.iter().map(|x| format!("{:02x}", x)).collect::<String>();
+
+    let now = super::now();
+    let model = ActiveModel {
+        token: Set(token),
+        user_id: Set(user_id),
+        created_at: Set(now),
+        expires_at: Set(now + Duration::days(SESSION_DAYS)),
+    }
+    .insert(db)
+    .await?;
+    Ok(model)
+}
+
+/// Create a new session for the named user if the password matches theirs
+pub async fn login(db: &DatabaseConnection, name: &str, password: &str) -> Result<Option<Model>, anyhow::Error> {
+    match user::get_by_name(db, name).await? {
+        Some(user) if user::verify_password(&user, password) => Ok(Some(create(db, user.id).await?)),
+        _ => Ok(None),
+    }
+}
+
+/// Get the user logged in with the given token unless the session has expired
+pub async fn get_user(db: &DatabaseConnection, token: &str) -> Result<Option<user::Model>, anyhow::Error> {
+    let session = Entity::find_by_id(token.to_owned()).filter(Column::ExpiresAt.gt(super::now())).one(db).await?;
+    Ok(match session {
+        Some(session) => session.find_related(user::Entity).one(db).await?,
+        None => None,
+    })
+}
+
+/// Delete the session with the given token from the database i.e. log out
+pub async fn delete(db: &DatabaseConnection, token: &str) -> Result<DeleteResult, anyhow::Error> {
+    Ok(Entity::delete_by_id(token.to_owned()).exec(db).await?)
+}
+
+/// Delete all sessions that have expired from the database
+pub async fn delete_expired(db: &DatabaseConnection) -> Result<DeleteResult, anyhow::Error> {
+    Ok(Entity::delete_many().filter(Column::ExpiresAt.lte(super::now())).exec(db).await?)
+}
+
+// Unit tests
+// -------------------------------------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use chrono::Duration;
+    use sea_orm::{ActiveModelTrait, ActiveValue::Set};
+
+    #[tokio::test]
+    async fn test_login() {
+        let db = test_db().await;
+        let events = Events::default();
+        user::create(&db, &events, "user1").await.unwrap();
+        user::create(&db, &events, "user2").await.unwrap();
+        user::set_password(&db, 1, "secret").await.unwrap();
+
+        // Wrong password, unknown user and user without a password
+        assert!(session::login(&db, "user1", "wrong").await.unwrap().is_none());
+        assert!(session::login(&db, "user3", "secret").await.unwrap().is_none());
+        assert!(session::login(&db, "user2", "").await.unwrap().is_none());
+
+        let session = session::login(&db, "user1", "secret").await.unwrap().unwrap();
+        assert!(session.user_id == 1);
+        assert!(session.token.len() == 64);
+        let user = session::get_user(&db, &session.token).await.unwrap().unwrap();
+        assert!(user.name == "user1");
+
+        // Logout
+        session::delete(&db, &session.token).await.unwrap();
+        assert!(session::get_user(&db, &session.token).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_expired() {
+        let db = test_db().await;
+        let events = Events::default();
+        user::create(&db, &events, "user1").await.unwrap();
+        let session1 = session::create(&db, 1).await.unwrap();
+        let session2 = session::create(&db, 1).await.unwrap();
+        assert!(session1.token != session2.token);
+
+        let mut expired: session::ActiveModel = session1.clone().into();
+        expired.expires_at = Set(model::now() - Duration::seconds(1));
+        expired.update(&db).await.unwrap();
+        assert!(session::get_user(&db, &session1.token).await.unwrap().is_none());
+        assert!(session::get_user(&db, &session2.token).await.unwrap().is_some());
+
+        let result = session::delete_expired(&db).await.unwrap();
+        assert!(result.rows_affected == 1);
+    }
+}
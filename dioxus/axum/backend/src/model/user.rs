@@ -1,21 +1,30 @@
 pub use crate::model::entities::user::*;
 
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use rand::rngs::OsRng;
 use sea_orm::{
-    ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DatabaseConnection, DeleteResult,
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, DeleteResult,
     EntityTrait, QueryFilter, TransactionTrait, Unchanged,
 };
 
+use super::events::{Event, Events};
+use super::list::{self, Listable};
+
 /// Create the given user in the database
-pub async fn create(db: &DatabaseConnection, name: &str) -> Result<Model, anyhow::Error> {
+pub async fn create(db: &DatabaseConnection, events: &Events, name: &str) -> Result<Model, anyhow::Error> {
     let entity = ActiveModel { name: Set(name.to_owned()), ..Default::default() }.insert(db).await?;
+    events.emit(Event::UserCreated(entity.clone()));
     Ok(entity)
 }
 
 /// Create the given user in the database if it doesn't exist
-pub async fn create_if_not(db: &DatabaseConnection, name: &str) -> Result<Model, anyhow::Error> {
+pub async fn create_if_not(db: &DatabaseConnection, events: &Events, name: &str) -> Result<Model, anyhow::Error> {
     Ok(match get_by_name(db, name).await? {
         Some(entity) => entity,
-        _ => create(db, name).await?,
+        _ => create(db, events, name).await?,
     })
 }
 
@@ -24,6 +33,15 @@ pub async fn get(db: &DatabaseConnection) -> Result<Vec<Model>, anyhow::Error> {
     Ok(Entity::find().all(db).await?)
 }
 
+/// Users are searched by name
+impl Listable for Entity {
+    const SORT: &'static [&'static str] = &["id", "name", "role", "created_at", "modified_at"];
+
+    fn search(q: &str) -> Condition {
+        Condition::all().add(list::contains(Column::Name, q))
+    }
+}
+
 /// Get the given user from the database by id
 pub async fn get_by_id(db: &DatabaseConnection, id: i32) -> Result<Option<Model>, anyhow::Error> {
     Ok(Entity::find_by_id(id).one(db).await?)
@@ -35,7 +53,9 @@ pub async fn get_by_name(db: &DatabaseConnection, name: &str) -> Result<Option<M
 }
 
 /// Update the given user in the database by id
-pub async fn update(db: &DatabaseConnection, id: i32, name: &str) -> Result<Model, anyhow::Error> {
+pub async fn update(
+    db: &DatabaseConnection, events: &Events, id: i32, name: &str,
+) -> Result<Model, anyhow::Error> {
     // Only fields included with `Set` will be actually updated
     let model = ActiveModel {
         id: Unchanged(id),
@@ -45,17 +65,80 @@ pub async fn update(db: &DatabaseConnection, id: i32, name: &str) -> Result<Mode
     }
     .update(db)
     .await?;
+    events.emit(Event::UserUpdated(model.clone()));
+    Ok(model)
+}
+
+/// Set the given user's password, only its argon2 hash is stored in the database
+pub async fn set_password(db: &DatabaseConnection, id: i32, password: &str) -> Result<Model, anyhow::Error> {
+    let model = ActiveModel {
+        id: Unchanged(id),
+        password_hash: Set(Some(hash_password(password)?)),
+        modified_at: Set(super::now()),
+        ..Default::default()
+    }
+    .update(db)
+    .await?;
     Ok(model)
 }
 
+/// Set the given user's role
+pub async fn set_role(
+    db: &DatabaseConnection, events: &Events, id: i32, role: Role,
+) -> Result<Model, anyhow::Error> {
+    let model =
+        ActiveModel { id: Unchanged(id), role: Set(role), modified_at: Set(super::now()), ..Default::default() }
+            .update(db)
+            .await?;
+    events.emit(Event::UserUpdated(model.clone()));
+    Ok(model)
+}
+
+/// Create the given admin in the database if it doesn't exist and set its password
+pub async fn create_admin_if_not(
+    db: &DatabaseConnection, events: &Events, name: &str, password: &str,
+) -> Result<Model, anyhow::Error> {
+    let model = create_if_not(db, events, name).await?;
+    set_role(db, events, model.id, Role::Admin).await?;
+    set_password(db, model.id, password).await
+}
+
+/// Hash the given password with argon2 and a random salt
+pub fn hash_password(password: &str) -> Result<String, anyhow::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::default().hash_password(password.as_bytes(), &salt).map_err(|e| anyhow::anyhow!(e))?;
+    Ok(hash.to_string())
+}
+
+/// Check the given password against the user's hash. Users without a password can't log in.
+pub fn verify_password(model: &Model, password: &str) -> bool {
+    model
+        .password_hash
+        .as_deref()
+        .and_then(|x| PasswordHash::new(x).ok())
+        .is_some_and(|x| Argon2::default().verify_password(password.as_bytes(), &x).is_ok())
+}
+
 /// Delete the given user from the database. This will also wipe out all associated data
 /// in other data tables for this user.
-pub async fn delete(db: &DatabaseConnection, id: i32) -> Result<DeleteResult, anyhow::Error> {
+pub async fn delete(db: &DatabaseConnection, events: &Events, id: i32) -> Result<DeleteResult, anyhow::Error> {
     let tx = db.begin().await?;
+    let rewards = super::rewards::Entity::find().filter(super::rewards::Column::UserId.eq(id)).all(&tx).await?;
+    let points = super::points::Entity::find().filter(super::points::Column::UserId.eq(id)).all(&tx).await?;
     super::rewards::Entity::delete_many().filter(super::rewards::Column::UserId.eq(id)).exec(&tx).await?;
     super::points::Entity::delete_many().filter(super::points::Column::UserId.eq(id)).exec(&tx).await?;
+    super::session::Entity::delete_many().filter(super::session::Column::UserId.eq(id)).exec(&tx).await?;
     let result = ActiveModel { id: Set(id), ..Default::default() }.delete(&tx).await?;
     tx.commit().await?;
+
+    // Clients drop the user's points and rewards along with the user
+    for x in rewards {
+        events.emit(Event::RewardDeleted { id: x.id });
+    }
+    for x in points {
+        events.emit(Event::PointsDeleted { id: x.id });
+    }
+    events.emit(Event::UserDeleted { id });
     Ok(result)
 }
 
@@ -68,21 +151,22 @@ mod tests {
     #[tokio::test]
     async fn test_crud() {
         let db = test_db().await;
+        let events = Events::default();
 
         // Create
-        user::create(&db, "foo1").await.unwrap();
-        user::create(&db, "foo2").await.unwrap();
-        user::create(&db, "foo3").await.unwrap();
-        user::create_if_not(&db, "foo3").await.unwrap();
+        user::create(&db, &events, "foo1").await.unwrap();
+        user::create(&db, &events, "foo2").await.unwrap();
+        user::create(&db, &events, "foo3").await.unwrap();
+        user::create_if_not(&db, &events, "foo3").await.unwrap();
 
         category::create_if_not(&db, "category1", 1).await.unwrap();
         category::create_if_not(&db, "category2", 10).await.unwrap();
         category::create_if_not(&db, "category3", 100).await.unwrap();
 
-        points::create(&db, 1, 3, 10).await.unwrap();
-        points::create(&db, 1, 2, 20).await.unwrap();
-        points::create(&db, 2, 2, 100).await.unwrap();
-        points::create(&db, 3, 1, 1000).await.unwrap();
+        points::create(&db, &events, 1, 3, 10).await.unwrap();
+        points::create(&db, &events, 1, 2, 20).await.unwrap();
+        points::create(&db, &events, 2, 2, 100).await.unwrap();
+        points::create(&db, &events, 3, 1, 1000).await.unwrap();
 
         // Create
         rewards::create(&db, 1, 10).await.unwrap();
@@ -102,7 +186,7 @@ mod tests {
         assert!(users.iter().any(|x| x.id == 3));
 
         // Update
-        user::update(&db, 1, "foobar").await.unwrap();
+        user::update(&db, &events, 1, "foobar").await.unwrap();
         let users = user::get(&db).await.unwrap();
         assert!(users.len() == 3);
         let user1 = users.iter().find(|x| x.id == 1).unwrap();
@@ -116,8 +200,14 @@ mod tests {
         assert!(user3.name == "foo3");
 
         // Delete
-        let result = user::delete(&db, 1).await.unwrap();
+        let mut rx = events.subscribe();
+        let result = user::delete(&db, &events, 1).await.unwrap();
         assert!(result.rows_affected == 1);
+        assert!(rx.try_recv().unwrap() == events::Event::RewardDeleted { id: 1 });
+        assert!(rx.try_recv().unwrap() == events::Event::PointsDeleted { id: 1 });
+        assert!(rx.try_recv().unwrap() == events::Event::PointsDeleted { id: 2 });
+        assert!(rx.try_recv().unwrap() == events::Event::UserDeleted { id: 1 });
+        assert!(rx.try_recv().is_err());
 
         let result = rewards::get(&db).await.unwrap();
         assert!(result.len() == 3);
@@ -130,4 +220,27 @@ mod tests {
         assert!(result.iter().find(|x| x.value == 100).is_some());
         assert!(result.iter().find(|x| x.value == 1000).is_some());
     }
+
+    #[tokio::test]
+    async fn test_password_and_role() {
+        let db = test_db().await;
+        let events = Events::default();
+        let user = user::create(&db, &events, "foo").await.unwrap();
+        assert!(user.role == user::Role::User);
+        assert!(user.password_hash.is_none());
+        assert!(!user::verify_password(&user, ""));
+
+        let user = user::set_password(&db, user.id, "secret").await.unwrap();
+        assert!(user.password_hash.as_deref().is_some_and(|x| x.starts_with("$argon2") && !x.contains("secret")));
+        assert!(user::verify_password(&user, "secret"));
+        assert!(!user::verify_password(&user, "Secret"));
+
+        let user = user::create_admin_if_not(&db, &events, "foo", "other").await.unwrap();
+        assert!(user.id == 1);
+        assert!(user.role == user::Role::Admin);
+        assert!(user::verify_password(&user, "other"));
+
+        // Never serialized
+        assert!(serde_json::to_value(&user).unwrap().get("password_hash").is_none());
+    }
 }
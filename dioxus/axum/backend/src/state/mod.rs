@@ -1,6 +1,15 @@
+use crate::model::events::Events;
 use sea_orm::DatabaseConnection;
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: DatabaseConnection,
+    pub events: Events, // Changes made to the data to push to the clients
+}
+
+impl AppState {
+    /// Create the state with a new channel for the changes made to the data
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db, events: Events::default() }
+    }
 }
@@ -0,0 +1,120 @@
+// Translation of miniquad's input types to egui's and back for the cursor
+use miniquad as mq;
+
+pub fn modifiers(keymods: mq::KeyMods) -> egui::Modifiers {
+    egui::Modifiers {
+        alt: keymods.alt,
+        ctrl: keymods.ctrl,
+        shift: keymods.shift,
+        mac_cmd: cfg!(target_os = "macos") && keymods.logo,
+        command: if cfg!(target_os = "macos") { keymods.logo } else { keymods.ctrl },
+    }
+}
+
+pub fn button(button: mq::MouseButton) -> Option<egui::PointerButton> {
+    match button {
+        mq::MouseButton::Left => Some(egui::PointerButton::Primary),
+        mq::MouseButton::Right => Some(egui::PointerButton::Secondary),
+        mq::MouseButton::Middle => Some(egui::PointerButton::Middle),
+        mq::MouseButton::Unknown => None,
+    }
+}
+
+pub fn key(keycode: mq::KeyCode) -> Option<egui::Key> {
+    use egui::Key;
+    use mq::KeyCode as K;
+
+    Some(match keycode {
+        K::Down => Key::ArrowDown,
+        K::Left => Key::ArrowLeft,
+        K::Right => Key::ArrowRight,
+        K::Up => Key::ArrowUp,
+        K::Escape => Key::Escape,
+        K::Tab => Key::Tab,
+        K::Backspace => Key::Backspace,
+        K::Enter | K::KpEnter => Key::Enter,
+        K::Space => Key::Space,
+        K::Insert => Key::Insert,
+        K::Delete => Key::Delete,
+        K::Home => Key::Home,
+        K::End => Key::End,
+        K::PageUp => Key::PageUp,
+        K::PageDown => Key::PageDown,
+        K::Key0 | K::Kp0 => Key::Num0,
+        K::Key1 | K::Kp1 => Key::Num1,
+        K::Key2 | K::Kp2 => Key::Num2,
+        K::Key3 | K::Kp3 => Key::Num3,
+        K::Key4 | K::Kp4 => Key::Num4,
+        K::Key5 | K::Kp5 => Key::Num5,
+        K::Key6 | K::Kp6 => Key::Num6,
+        K::Key7 | K::Kp7 => Key::Num7,
+        K::Key8 | K::Kp8 => Key::Num8,
+        K::Key9 | K::Kp9 => Key::Num9,
+        K::A => Key::A,
+        K::B => Key::B,
+        K::C => Key::C,
+        K::D => Key::D,
+        K::E => Key::E,
+        K::F => Key::F,
+        K::G => Key::G,
+        K::H => Key::H,
+        K::I => Key::I,
+        K::J => Key::J,
+        K::K => Key::K,
+        K::L => Key::L,
+        K::M => Key::M,
+        K::N => Key::N,
+        K::O => Key::O,
+        K::P => Key::P,
+        K::Q => Key::Q,
+        K::R => Key::R,
+        K::S => Key::S,
+        K::T => Key::T,
+        K::U => Key::U,
+        K::V => Key::V,
+        K::W => Key::W,
+        K::X => Key::X,
+        K::Y => Key::Y,
+        K::Z => Key::Z,
+        K::F1 => Key::F1,
+        K::F2 => Key::F2,
+        K::F3 => Key::F3,
+        K::F4 => Key::F4,
+        K::F5 => Key::F5,
+        K::F6 => Key::F6,
+        K::F7 => Key::F7,
+        K::F8 => Key::F8,
+        K::F9 => Key::F9,
+        K::F10 => Key::F10,
+        K::F11 => Key::F11,
+        K::F12 => Key::F12,
+        K::F13 => Key::F13,
+        K::F14 => Key::F14,
+        K::F15 => Key::F15,
+        K::F16 => Key::F16,
+        K::F17 => Key::F17,
+        K::F18 => Key::F18,
+        K::F19 => Key::F19,
+        K::F20 => Key::F20,
+        _ => return None,
+    })
+}
+
+pub fn cursor(icon: egui::CursorIcon) -> mq::CursorIcon {
+    use egui::CursorIcon as C;
+
+    match icon {
+        C::Help => mq::CursorIcon::Help,
+        C::PointingHand => mq::CursorIcon::Pointer,
+        C::Progress | C::Wait => mq::CursorIcon::Wait,
+        C::Crosshair | C::Cell => mq::CursorIcon::Crosshair,
+        C::Text | C::VerticalText => mq::CursorIcon::Text,
+        C::Move | C::AllScroll | C::Grab | C::Grabbing => mq::CursorIcon::Move,
+        C::NoDrop | C::NotAllowed => mq::CursorIcon::NotAllowed,
+        C::ResizeHorizontal | C::ResizeEast | C::ResizeWest | C::ResizeColumn => mq::CursorIcon::EWResize,
+        C::ResizeVertical | C::ResizeNorth | C::ResizeSouth | C::ResizeRow => mq::CursorIcon::NSResize,
+        C::ResizeNeSw | C::ResizeNorthEast | C::ResizeSouthWest => mq::CursorIcon::NESWResize,
+        C::ResizeNwSe | C::ResizeNorthWest | C::ResizeSouthEast => mq::CursorIcon::NWSEResize,
+        _ => mq::CursorIcon::Default,
+    }
+}
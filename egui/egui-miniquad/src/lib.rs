@@ -0,0 +1,154 @@
+//! egui integration for miniquad doing gamma correct blending so that egui's color test passes
+//!
+//! ### Example
+//! ```ignore
+//! let mut egui_mq = egui_miniquad::EguiMq::new(ctx);
+//! // Every frame
+//! egui_mq.run(ctx, |_ctx, egui_ctx| {
+//!     egui::Window::new("Hello").show(egui_ctx, |ui| ui.label("world"));
+//! });
+//! egui_mq.draw(ctx);
+//! ctx.commit_frame();
+//! ```
+mod input;
+mod painter;
+
+use miniquad as mq;
+pub use painter::Painter;
+
+/// egui context fed with miniquad's events and drawn with a [`Painter`]
+pub struct EguiMq {
+    egui_ctx: egui::Context,
+    egui_input: egui::RawInput,                      // Input gathered since the last frame
+    painter: Painter,                                // Draws the shapes of the last frame
+    shapes: Option<Vec<egui::epaint::ClippedShape>>, // Output of the last frame waiting to be drawn
+    textures_delta: egui::TexturesDelta,             // Texture changes waiting to be applied when drawing
+    start_time: f64,                                 // Time egui's clock starts at
+}
+
+impl EguiMq {
+    /// Create a context matching egui's scale to the display's
+    pub fn new(mq_ctx: &mut mq::Context) -> Self {
+        Self {
+            egui_ctx: egui::Context::default(),
+            egui_input: egui::RawInput { pixels_per_point: Some(mq_ctx.dpi_scale()), ..Default::default() },
+            painter: Painter::new(mq_ctx),
+            shapes: None,
+            textures_delta: Default::default(),
+            start_time: mq::date::now(),
+        }
+    }
+
+    /// Get the egui context e.g. to change its style
+    pub fn egui_ctx(&self) -> &egui::Context {
+        &self.egui_ctx
+    }
+
+    /// Whether colors are blended in linear space so that egui's color test passes. False on GL2 and
+    /// WebGL1 contexts which lack sRGB framebuffers.
+    pub fn is_linear(&self) -> bool {
+        self.painter.is_linear()
+    }
+
+    /// Offscreen render target the last frame was drawn into, see [`Painter::target`]
+    pub fn target(&self, mq_ctx: &mut mq::Context) -> Option<mq::Texture> {
+        self.painter.target(mq_ctx)
+    }
+
+    /// Run the ui code for a frame with the input gathered since the last one. Call [`Self::draw`] afterwards
+    /// to draw the result.
+    pub fn run(&mut self, mq_ctx: &mut mq::Context, run_ui: impl FnOnce(&mut mq::Context, &egui::Context)) {
+        // Scale changes made through egui take effect by leaving the scale unset
+        let (width, height) = mq_ctx.screen_size();
+        let native = self.egui_input.pixels_per_point;
+        let pixels_per_point = native.unwrap_or_else(|| self.egui_ctx.pixels_per_point());
+        self.egui_input.screen_rect =
+            Some(egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(width, height) / pixels_per_point));
+        self.egui_input.time = Some(mq::date::now() - self.start_time);
+
+        let output = self.egui_ctx.run(self.egui_input.take(), |egui_ctx| run_ui(mq_ctx, egui_ctx));
+        self.shapes = Some(output.shapes);
+        self.textures_delta.append(output.textures_delta);
+
+        let platform = output.platform_output;
+        mq_ctx.set_mouse_cursor(input::cursor(platform.cursor_icon));
+        if !platform.copied_text.is_empty() {
+            mq_ctx.clipboard_set(&platform.copied_text);
+        }
+    }
+
+    /// Draw the output of the last call to [`Self::run`]
+    pub fn draw(&mut self, mq_ctx: &mut mq::Context) {
+        if let Some(shapes) = self.shapes.take() {
+            let primitives = self.egui_ctx.tessellate(shapes);
+            let textures_delta = std::mem::take(&mut self.textures_delta);
+            self.painter.paint(mq_ctx, primitives, &textures_delta, self.egui_ctx.pixels_per_point());
+        }
+    }
+
+    /// Call from [`mq::EventHandler::mouse_motion_event`]
+    pub fn mouse_motion_event(&mut self, x: f32, y: f32) {
+        let position = (egui::vec2(x, y) / self.egui_ctx.pixels_per_point()).to_pos2();
+        self.egui_input.events.push(egui::Event::PointerMoved(position));
+    }
+
+    /// Call from [`mq::EventHandler::mouse_wheel_event`]
+    pub fn mouse_wheel_event(&mut self, dx: f32, dy: f32) {
+        self.egui_input.events.push(egui::Event::Scroll(egui::vec2(dx, dy)));
+    }
+
+    /// Call from [`mq::EventHandler::mouse_button_down_event`]
+    pub fn mouse_button_down_event(&mut self, _mq_ctx: &mut mq::Context, button: mq::MouseButton, x: f32, y: f32) {
+        self.pointer_button(button, x, y, true);
+    }
+
+    /// Call from [`mq::EventHandler::mouse_button_up_event`]
+    pub fn mouse_button_up_event(&mut self, _mq_ctx: &mut mq::Context, button: mq::MouseButton, x: f32, y: f32) {
+        self.pointer_button(button, x, y, false);
+    }
+
+    /// Call from [`mq::EventHandler::char_event`]
+    pub fn char_event(&mut self, character: char) {
+        // Control characters come through as key events and function keys use the private use area
+        let private = ('\u{e000}'..='\u{f8ff}').contains(&character);
+        if !character.is_control() && !private {
+            self.egui_input.events.push(egui::Event::Text(character.to_string()));
+        }
+    }
+
+    /// Call from [`mq::EventHandler::key_down_event`]
+    pub fn key_down_event(&mut self, mq_ctx: &mut mq::Context, keycode: mq::KeyCode, keymods: mq::KeyMods) {
+        let modifiers = input::modifiers(keymods);
+        self.egui_input.modifiers = modifiers;
+        let event = match keycode {
+            mq::KeyCode::C if modifiers.command => egui::Event::Copy,
+            mq::KeyCode::X if modifiers.command => egui::Event::Cut,
+            mq::KeyCode::V if modifiers.command => match mq_ctx.clipboard_get() {
+                Some(text) => egui::Event::Paste(text),
+                None => return,
+            },
+            _ => match input::key(keycode) {
+                Some(key) => egui::Event::Key { key, pressed: true, modifiers },
+                None => return,
+            },
+        };
+        self.egui_input.events.push(event);
+    }
+
+    /// Call from [`mq::EventHandler::key_up_event`]
+    pub fn key_up_event(&mut self, keycode: mq::KeyCode, keymods: mq::KeyMods) {
+        let modifiers = input::modifiers(keymods);
+        self.egui_input.modifiers = modifiers;
+        if let Some(key) = input::key(keycode) {
+            self.egui_input.events.push(egui::Event::Key { key, pressed: false, modifiers });
+        }
+    }
+
+    fn pointer_button(&mut self, button: mq::MouseButton, x: f32, y: f32, pressed: bool) {
+        if let Some(button) = input::button(button) {
+            let pos = (egui::vec2(x, y) / self.egui_ctx.pixels_per_point()).to_pos2();
+            let modifiers = self.egui_input.modifiers;
+            self.egui_input.events.push(egui::Event::PointerButton { pos, button, pressed, modifiers });
+        }
+    }
+}
@@ -0,0 +1,465 @@
+// Draws egui's meshes with miniquad blending in linear space the way egui expects
+use std::collections::HashMap;
+
+use egui::epaint::textures::TextureFilter;
+use egui::epaint::{ImageData, ImageDelta, Mesh16, Primitive, Vertex};
+use miniquad as mq;
+
+/// Uploads egui's textures and draws its tessellated shapes.
+///
+/// egui's colors are sRGB encoded premultiplied alpha which must be decoded to linear before they are
+/// interpolated, multiplied and blended, then encoded back to sRGB for the screen. When the GPU supports sRGB
+/// textures and framebuffers egui is drawn into an sRGB render target so that the hardware decodes texture
+/// samples before filtering and blends in linear space, and the result is composited onto the screen. On
+/// GL 2 and WebGL 1 the conversions are done in the shader instead, which gets everything but the blending
+/// itself right.
+pub struct Painter {
+    pipeline: mq::Pipeline,                          // Draws egui's meshes
+    bindings: mq::Bindings,                          // Vertex and index buffers grown to fit the largest mesh
+    composite: mq::Pipeline,                         // Copies the render target onto the screen
+    quad: mq::Bindings,                              // Screen filling quad showing the render target
+    target: Option<mq::RenderPass>,                  // Linear render target egui is drawn into
+    textures: HashMap<egui::TextureId, mq::Texture>, // Textures managed by egui
+    srgb: Option<bool>,                              // sRGB support and whether the context is OpenGL ES
+}
+
+impl Painter {
+    /// Create the pipelines and buffers checking which way to convert colors
+    pub fn new(ctx: &mut mq::Context) -> Self {
+        let shader = mq::Shader::new(ctx, mesh_shader::VERTEX, mesh_shader::FRAGMENT, mesh_shader::meta())
+            .expect("couldn't compile the egui shader");
+        let pipeline = mq::Pipeline::with_params(
+            ctx,
+            &[mq::BufferLayout::default()],
+            &[
+                mq::VertexAttribute::new("a_pos", mq::VertexFormat::Float2),
+                mq::VertexAttribute::new("a_tc", mq::VertexFormat::Float2),
+                mq::VertexAttribute::new("a_srgba", mq::VertexFormat::Byte4),
+            ],
+            shader,
+            Self::premultiplied(),
+        );
+        let bindings = mq::Bindings {
+            vertex_buffers: vec![mq::Buffer::stream(ctx, mq::BufferType::VertexBuffer, 0)],
+            index_buffer: mq::Buffer::stream(ctx, mq::BufferType::IndexBuffer, 0),
+            images: vec![mq::Texture::empty()],
+        };
+
+        let shader =
+            mq::Shader::new(ctx, composite_shader::VERTEX, composite_shader::FRAGMENT, composite_shader::meta())
+                .expect("couldn't compile the egui composite shader");
+        let composite = mq::Pipeline::with_params(
+            ctx,
+            &[mq::BufferLayout::default()],
+            &[mq::VertexAttribute::new("a_pos", mq::VertexFormat::Float2)],
+            shader,
+            Self::premultiplied(),
+        );
+        let corners: [[f32; 2]; 4] = [[-1., -1.], [1., -1.], [1., 1.], [-1., 1.]];
+        let quad = mq::Bindings {
+            vertex_buffers: vec![mq::Buffer::immutable(ctx, mq::BufferType::VertexBuffer, &corners)],
+            index_buffer: mq::Buffer::immutable(ctx, mq::BufferType::IndexBuffer, &[0u16, 1, 2, 0, 2, 3]),
+            images: vec![mq::Texture::empty()],
+        };
+
+        Self { pipeline, bindings, composite, quad, target: None, textures: HashMap::new(), srgb: gl::srgb() }
+    }
+
+    /// Whether colors are blended in linear space by the GPU rather than only converted in the shader
+    pub fn is_linear(&self) -> bool {
+        self.srgb.is_some()
+    }
+
+    /// Render target the last frame was drawn into before being composited onto the screen. Its pixels are
+    /// sRGB encoded premultiplied alpha with the bottom row first. None when the shader does the conversions
+    /// and draws onto the screen directly.
+    pub fn target(&self, ctx: &mut mq::Context) -> Option<mq::Texture> {
+        self.target.map(|pass| pass.texture(ctx))
+    }
+
+    /// Apply the texture changes and draw the primitives onto the screen. Textures egui is done with are
+    /// freed afterwards.
+    pub fn paint(
+        &mut self, ctx: &mut mq::Context, primitives: Vec<egui::ClippedPrimitive>,
+        textures_delta: &egui::TexturesDelta, pixels_per_point: f32,
+    ) {
+        for (id, delta) in &textures_delta.set {
+            self.set_texture(ctx, *id, delta);
+        }
+
+        match self.srgb {
+            Some(gles) => {
+                let target = self.resize_target(ctx);
+                ctx.begin_pass(target, mq::PassAction::clear_color(0., 0., 0., 0.));
+                gl::framebuffer_srgb(gles, true);
+                self.paint_primitives(ctx, primitives, pixels_per_point);
+                ctx.end_render_pass();
+                gl::framebuffer_srgb(gles, false);
+
+                self.quad.images[0] = target.texture(ctx);
+                ctx.begin_default_pass(mq::PassAction::Nothing);
+                ctx.apply_pipeline(&self.composite);
+                ctx.apply_bindings(&self.quad);
+                ctx.draw(0, 6, 1);
+                ctx.end_render_pass();
+            },
+            None => {
+                ctx.begin_default_pass(mq::PassAction::Nothing);
+                self.paint_primitives(ctx, primitives, pixels_per_point);
+                ctx.end_render_pass();
+            },
+        }
+
+        for id in &textures_delta.free {
+            if let Some(texture) = self.textures.remove(id) {
+                texture.delete();
+            }
+        }
+    }
+
+    // Colors are premultiplied by their alpha
+    fn premultiplied() -> mq::PipelineParams {
+        let blend = mq::BlendState::new(
+            mq::Equation::Add,
+            mq::BlendFactor::One,
+            mq::BlendFactor::OneMinusValue(mq::BlendValue::SourceAlpha),
+        );
+        mq::PipelineParams { color_blend: Some(blend), alpha_blend: Some(blend), ..Default::default() }
+    }
+
+    // Get the render target recreating it if the screen has changed size
+    fn resize_target(&mut self, ctx: &mut mq::Context) -> mq::RenderPass {
+        let (width, height) = ctx.screen_size();
+        let size = (width as u32, height as u32);
+        match self.target {
+            Some(pass) if (pass.texture(ctx).width, pass.texture(ctx).height) == size => return pass,
+            Some(pass) => pass.delete(ctx),
+            None => (),
+        }
+        let texture = self.new_texture(ctx, size, mq::FilterMode::Nearest, None);
+        let pass = mq::RenderPass::new(ctx, texture, None);
+        self.target = Some(pass);
+        pass
+    }
+
+    // Create an RGBA texture which stores sRGB colors when the GPU can decode them
+    fn new_texture(
+        &self, ctx: &mut mq::Context, size: (u32, u32), filter: mq::FilterMode, pixels: Option<&[u8]>,
+    ) -> mq::Texture {
+        let params = mq::TextureParams {
+            width: size.0,
+            height: size.1,
+            format: mq::TextureFormat::RGBA8,
+            wrap: mq::TextureWrap::Clamp,
+            filter,
+        };
+        match self.srgb {
+            Some(_) => {
+                let texture = mq::Texture::new(ctx, mq::TextureAccess::Static, None, params);
+                gl::store_srgb(&texture, pixels);
+                texture
+            },
+            None => mq::Texture::new(ctx, mq::TextureAccess::Static, pixels, params),
+        }
+    }
+
+    // Create, replace or patch one of egui's textures
+    fn set_texture(&mut self, ctx: &mut mq::Context, id: egui::TextureId, delta: &ImageDelta) {
+        let (size, pixels): ([usize; 2], Vec<u8>) = match &delta.image {
+            ImageData::Color(image) => (image.size, image.pixels.iter().flat_map(|x| x.to_array()).collect()),
+            ImageData::Font(image) => (image.size, image.srgba_pixels(1.).flat_map(|x| x.to_array()).collect()),
+        };
+
+        match (delta.pos, self.textures.get(&id)) {
+            (Some([x, y]), Some(texture)) => {
+                texture.update_texture_part(ctx, x as i32, y as i32, size[0] as i32, size[1] as i32, &pixels);
+            },
+            // Patch of a texture we never got the whole of
+            (Some(_), None) => (),
+            (None, _) => {
+                let filter = match delta.filter {
+                    TextureFilter::Nearest => mq::FilterMode::Nearest,
+                    TextureFilter::Linear => mq::FilterMode::Linear,
+                };
+                let texture = self.new_texture(ctx, (size[0] as u32, size[1] as u32), filter, Some(&pixels));
+                if let Some(old) = self.textures.insert(id, texture) {
+                    old.delete();
+                }
+            },
+        }
+    }
+
+    // Draw the meshes into the current pass clipped to their rectangles
+    fn paint_primitives(
+        &mut self, ctx: &mut mq::Context, primitives: Vec<egui::ClippedPrimitive>, pixels_per_point: f32,
+    ) {
+        let (width, height) = ctx.screen_size();
+        ctx.apply_pipeline(&self.pipeline);
+        for egui::ClippedPrimitive { clip_rect, primitive } in primitives {
+            // Paint callbacks are for other backends
+            let Primitive::Mesh(mesh) = primitive else { continue };
+
+            // Round the clip rectangle to pixels and flip it to OpenGL's bottom up coordinates
+            let min = clip_rect.min.to_vec2() * pixels_per_point;
+            let max = clip_rect.max.to_vec2() * pixels_per_point;
+            let (x0, y0) = (min.x.round().clamp(0., width), min.y.round().clamp(0., height));
+            let (x1, y1) = (max.x.round().clamp(x0, width), max.y.round().clamp(y0, height));
+            if x1 <= x0 || y1 <= y0 {
+                continue;
+            }
+            ctx.apply_scissor_rect(x0 as i32, (height - y1) as i32, (x1 - x0) as i32, (y1 - y0) as i32);
+
+            for mesh in mesh.split_to_u16() {
+                self.draw_mesh(ctx, &mesh, egui::vec2(width, height) / pixels_per_point);
+            }
+        }
+    }
+
+    // Upload the mesh growing the buffers if needed and draw it
+    fn draw_mesh(&mut self, ctx: &mut mq::Context, mesh: &Mesh16, screen_size: egui::Vec2) {
+        let (texture, managed) = match mesh.texture_id {
+            egui::TextureId::Managed(_) => match self.textures.get(&mesh.texture_id) {
+                Some(texture) => (*texture, true),
+                None => return,
+            },
+            // Textures like render targets drawn by the application are RGBA8 storing sRGB colors
+            egui::TextureId::User(id) => (unsafe { mq::Texture::from_raw_id(id as u32) }, false),
+        };
+
+        let vertices = std::mem::size_of_val(mesh.vertices.as_slice());
+        if self.bindings.vertex_buffers[0].size() < vertices {
+            self.bindings.vertex_buffers[0].delete();
+            self.bindings.vertex_buffers[0] = mq::Buffer::stream(ctx, mq::BufferType::VertexBuffer, vertices);
+        }
+        let indices = std::mem::size_of_val(mesh.indices.as_slice());
+        if self.bindings.index_buffer.size() < indices {
+            self.bindings.index_buffer.delete();
+            self.bindings.index_buffer = mq::Buffer::stream(ctx, mq::BufferType::IndexBuffer, indices);
+        }
+        self.bindings.vertex_buffers[0].update(ctx, &mesh.vertices);
+        self.bindings.index_buffer.update(ctx, &mesh.indices);
+        self.bindings.images[0] = texture;
+
+        ctx.apply_bindings(&self.bindings);
+        ctx.apply_uniforms(&mesh_shader::Uniforms {
+            screen_size: (screen_size.x, screen_size.y),
+            decode_texture: (self.srgb.is_none() || !managed) as u8 as f32,
+            encode_output: self.srgb.is_none() as u8 as f32,
+        });
+        ctx.draw(0, mesh.indices.len() as i32, 1);
+    }
+}
+
+// Keep the vertex attributes in sync with egui's vertex layout
+const _: () = assert!(std::mem::size_of::<Vertex>() == 20);
+
+// GLSL conversions between sRGB encoded and linear colors in the range 0 to 1
+macro_rules! srgb_functions {
+    () => {
+        r#"
+    vec3 linear_from_srgb(vec3 srgb) {
+        bvec3 cutoff = lessThan(srgb, vec3(0.04045));
+        vec3 lower = srgb / vec3(12.92);
+        vec3 higher = pow((srgb + vec3(0.055)) / vec3(1.055), vec3(2.4));
+        return mix(higher, lower, vec3(cutoff));
+    }
+
+    vec3 srgb_from_linear(vec3 rgb) {
+        bvec3 cutoff = lessThan(rgb, vec3(0.0031308));
+        vec3 lower = rgb * vec3(12.92);
+        vec3 higher = vec3(1.055) * pow(rgb, vec3(1.0 / 2.4)) - vec3(0.055);
+        return mix(higher, lower, vec3(cutoff));
+    }
+
+    // Encode without the alpha so that the result can be blended in sRGB space
+    vec4 srgba_from_linear(vec4 rgba) {
+        if (rgba.a <= 0.0) {
+            return vec4(0.0);
+        }
+        return vec4(srgb_from_linear(rgba.rgb / rgba.a) * rgba.a, rgba.a);
+    }
+"#
+    };
+}
+
+mod mesh_shader {
+    use miniquad as mq;
+
+    pub const VERTEX: &str = concat!(
+        r#"#version 100
+    precision highp float;
+
+    uniform vec2 u_screen_size;
+
+    attribute vec2 a_pos;
+    attribute vec2 a_tc;
+    attribute vec4 a_srgba;
+
+    varying vec2 v_tc;
+    varying vec4 v_rgba;
+"#,
+        srgb_functions!(),
+        r#"
+    void main() {
+        gl_Position = vec4(2.0 * a_pos.x / u_screen_size.x - 1.0, 1.0 - 2.0 * a_pos.y / u_screen_size.y, 0.0, 1.0);
+        v_tc = a_tc;
+        v_rgba = vec4(linear_from_srgb(a_srgba.rgb / 255.0), a_srgba.a / 255.0);
+    }
+"#
+    );
+
+    pub const FRAGMENT: &str = concat!(
+        r#"#version 100
+    precision mediump float;
+
+    uniform sampler2D u_sampler;
+    uniform float u_decode_texture;
+    uniform float u_encode_output;
+
+    varying vec2 v_tc;
+    varying vec4 v_rgba;
+"#,
+        srgb_functions!(),
+        r#"
+    void main() {
+        vec4 texture_rgba = texture2D(u_sampler, v_tc);
+        if (u_decode_texture > 0.5) {
+            texture_rgba.rgb = linear_from_srgb(texture_rgba.rgb);
+        }
+        vec4 rgba = v_rgba * texture_rgba;
+        if (u_encode_output > 0.5) {
+            rgba = srgba_from_linear(rgba);
+        }
+        gl_FragColor = rgba;
+    }
+"#
+    );
+
+    pub fn meta() -> mq::ShaderMeta {
+        mq::ShaderMeta {
+            images: vec!["u_sampler".to_string()],
+            uniforms: mq::UniformBlockLayout {
+                uniforms: vec![
+                    mq::UniformDesc::new("u_screen_size", mq::UniformType::Float2),
+                    mq::UniformDesc::new("u_decode_texture", mq::UniformType::Float1),
+                    mq::UniformDesc::new("u_encode_output", mq::UniformType::Float1),
+                ],
+            },
+        }
+    }
+
+    #[repr(C)]
+    pub struct Uniforms {
+        pub screen_size: (f32, f32), // Screen size in points
+        pub decode_texture: f32,     // 1 if the texture's sRGB colors aren't decoded by the GPU
+        pub encode_output: f32,      // 1 if the framebuffer doesn't encode linear colors to sRGB
+    }
+}
+
+mod composite_shader {
+    use miniquad as mq;
+
+    pub const VERTEX: &str = r#"#version 100
+    attribute vec2 a_pos;
+
+    varying vec2 v_tc;
+
+    void main() {
+        gl_Position = vec4(a_pos, 0.0, 1.0);
+        v_tc = a_pos * 0.5 + 0.5;
+    }
+"#;
+
+    pub const FRAGMENT: &str = concat!(
+        r#"#version 100
+    precision mediump float;
+
+    uniform sampler2D u_sampler;
+
+    varying vec2 v_tc;
+"#,
+        srgb_functions!(),
+        r#"
+    void main() {
+        gl_FragColor = srgba_from_linear(texture2D(u_sampler, v_tc));
+    }
+"#
+    );
+
+    pub fn meta() -> mq::ShaderMeta {
+        mq::ShaderMeta {
+            images: vec!["u_sampler".to_string()],
+            uniforms: mq::UniformBlockLayout { uniforms: vec![] },
+        }
+    }
+}
+
+// Raw OpenGL calls for the sRGB formats miniquad doesn't know about
+#[cfg(not(any(target_arch = "wasm32", target_os = "android", target_os = "ios")))]
+mod gl {
+    use miniquad::gl::*;
+
+    const GL_SRGB8_ALPHA8: u32 = 0x8C43;
+    const GL_FRAMEBUFFER_SRGB: u32 = 0x8DB9;
+    const GL_TEXTURE_BINDING_2D: u32 = 0x8069;
+
+    // Check for OpenGL 3 or OpenGL ES 3 which have sRGB textures and render targets, returning whether it's
+    // OpenGL ES
+    pub fn srgb() -> Option<bool> {
+        let version = unsafe { glGetString(GL_VERSION) };
+        if version.is_null() {
+            return None;
+        }
+        let version = unsafe { std::ffi::CStr::from_ptr(version as _) }.to_str().ok()?;
+        let number = version.trim_start_matches(|x: char| !x.is_ascii_digit());
+        let major: u32 = number.split('.').next()?.parse().ok()?;
+        (major >= 3).then(|| version.starts_with("OpenGL ES"))
+    }
+
+    // Reallocate the texture storing sRGB colors with the given initial contents. The binding of the first
+    // texture unit is restored so that miniquad's cache of it stays valid.
+    pub fn store_srgb(texture: &miniquad::Texture, pixels: Option<&[u8]>) {
+        let data = pixels.map_or(std::ptr::null(), |x| x.as_ptr() as *const _);
+        unsafe {
+            let mut bound = 0;
+            glActiveTexture(GL_TEXTURE0);
+            glGetIntegerv(GL_TEXTURE_BINDING_2D, &mut bound);
+            glBindTexture(GL_TEXTURE_2D, texture.gl_internal_id());
+            glTexImage2D(
+                GL_TEXTURE_2D,
+                0,
+                GL_SRGB8_ALPHA8 as i32,
+                texture.width as i32,
+                texture.height as i32,
+                0,
+                GL_RGBA,
+                GL_UNSIGNED_BYTE,
+                data,
+            );
+            glBindTexture(GL_TEXTURE_2D, bound as u32);
+        }
+    }
+
+    // Turn the encoding of linear colors written to sRGB render targets on or off. OpenGL ES always encodes.
+    pub fn framebuffer_srgb(gles: bool, enable: bool) {
+        match (gles, enable) {
+            (true, _) => (),
+            (false, true) => unsafe { glEnable(GL_FRAMEBUFFER_SRGB) },
+            (false, false) => unsafe { glDisable(GL_FRAMEBUFFER_SRGB) },
+        }
+    }
+}
+
+// Platforms where miniquad creates an OpenGL ES 2 or WebGL 1 context always convert in the shader
+#[cfg(any(target_arch = "wasm32", target_os = "android", target_os = "ios"))]
+mod gl {
+    pub fn srgb() -> Option<bool> {
+        None
+    }
+
+    pub fn store_srgb(_texture: &miniquad::Texture, _pixels: Option<&[u8]>) {
+        unreachable!("sRGB textures are never used on this platform")
+    }
+
+    pub fn framebuffer_srgb(_gles: bool, _enable: bool) {}
+}
@@ -0,0 +1,127 @@
+// Draws egui's color test patterns into the painter's offscreen render target and compares its pixels with the
+// gamma correct result. miniquad only creates a context along with a window so the test is skipped when there
+// is no display to open one on or the context is too old to blend in linear space.
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+use egui::epaint::color::gamma_u8_from_linear_f32;
+use egui::{pos2, Color32, Rect};
+use miniquad as mq;
+
+const ORANGE: Color32 = Color32::from_rgb(255, 165, 0);
+
+// RGBA pixels of the render target with the bottom row first, empty if there was none
+#[derive(Default)]
+struct Frame {
+    size: (usize, usize),
+    pixels: Vec<u8>,
+}
+
+impl Frame {
+    fn rgb(&self, x: usize, y: usize) -> [u8; 3] {
+        let i = ((self.size.1 - 1 - y) * self.size.0 + x) * 4;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2]]
+    }
+}
+
+struct Snapshot {
+    egui_mq: egui_miniquad::EguiMq,
+    texture: Option<egui::TextureHandle>,
+    frame: Arc<Mutex<Frame>>,
+}
+
+impl mq::EventHandler for Snapshot {
+    fn update(&mut self, _ctx: &mut mq::Context) {}
+
+    fn draw(&mut self, ctx: &mut mq::Context) {
+        let texture = &mut self.texture;
+        self.egui_mq.run(ctx, |_ctx, egui_ctx| {
+            let painter = egui_ctx.layer_painter(egui::LayerId::background());
+
+            // Black to white vertex gradient
+            let mut mesh = egui::Mesh::default();
+            for (x, color) in [(0., Color32::BLACK), (64., Color32::WHITE)] {
+                mesh.colored_vertex(pos2(x, 0.), color);
+                mesh.colored_vertex(pos2(x, 16.), color);
+            }
+            mesh.add_triangle(0, 1, 2);
+            mesh.add_triangle(1, 2, 3);
+            painter.add(egui::Shape::mesh(mesh));
+
+            // Half transparent black over white
+            painter.rect_filled(Rect::from_min_max(pos2(0., 16.), pos2(64., 32.)), 0., Color32::WHITE);
+            let half = Color32::from_black_alpha(128);
+            painter.rect_filled(Rect::from_min_max(pos2(0., 16.), pos2(64., 32.)), 0., half);
+
+            // Texture which must come out exactly as it went in
+            let texture = texture.get_or_insert_with(|| {
+                let image = egui::ColorImage::new([4, 4], ORANGE);
+                egui_ctx.load_texture("orange", image, egui::TextureFilter::Linear)
+            });
+            let uv = Rect::from_min_max(pos2(0., 0.), pos2(1., 1.));
+            let rect = Rect::from_min_max(pos2(0., 32.), pos2(64., 48.));
+            painter.add(egui::Shape::image(texture.id(), rect, uv, Color32::WHITE));
+        });
+        self.egui_mq.draw(ctx);
+
+        if let Some(target) = self.egui_mq.target(ctx) {
+            let mut frame = self.frame.lock().unwrap();
+            frame.size = (target.width as usize, target.height as usize);
+            frame.pixels = vec![0; frame.size.0 * frame.size.1 * 4];
+            target.read_pixels(&mut frame.pixels);
+        }
+        ctx.quit();
+    }
+}
+
+// Draw a single frame returning the render target's pixels, none if no context could be created or the
+// painter doesn't use a render target
+fn snapshot() -> Option<Frame> {
+    let frame = Arc::new(Mutex::new(Frame::default()));
+    let created = Arc::new(AtomicBool::new(false));
+    let (shared, started) = (frame.clone(), created.clone());
+    let conf = mq::conf::Conf { window_width: 64, window_height: 48, ..Default::default() };
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        mq::start(conf, move |ctx| {
+            started.store(true, Ordering::SeqCst);
+            Box::new(Snapshot { egui_mq: egui_miniquad::EguiMq::new(ctx), texture: None, frame: shared })
+        })
+    }));
+    match result {
+        Err(_) if !created.load(Ordering::SeqCst) => return None, // No display to create the context on
+        Err(err) => panic::resume_unwind(err),
+        Ok(()) => (),
+    }
+
+    let frame = std::mem::take(&mut *frame.lock().unwrap());
+    (!frame.pixels.is_empty()).then_some(frame)
+}
+
+fn assert_near(actual: [u8; 3], expected: [u8; 3], what: &str) {
+    let near = actual.iter().zip(expected).all(|(&a, e)| (a as i32 - e as i32).abs() <= 2);
+    assert!(near, "{}: got {:?} expected {:?}", what, actual, expected);
+}
+
+#[test]
+fn color_test_snapshot() {
+    let Some(frame) = snapshot() else {
+        eprintln!("skipping the color test snapshot, no OpenGL 3 context could be created");
+        return;
+    };
+    assert!(frame.size.0 >= 64 && frame.size.1 >= 48, "render target too small: {:?}", frame.size);
+
+    // Vertex colors are interpolated in linear space so the middle is half as bright, not 128
+    for x in [8, 32, 56] {
+        let gray = gamma_u8_from_linear_f32((x as f32 + 0.5) / 64.);
+        assert_near(frame.rgb(x, 8), [gray; 3], &format!("gradient at {}", x));
+    }
+
+    // Blending happens in linear space so half of white is 188 rather than 128
+    let gray = gamma_u8_from_linear_f32(0.5);
+    assert_near(frame.rgb(32, 24), [gray; 3], "blending");
+
+    assert_near(frame.rgb(32, 40), [ORANGE.r(), ORANGE.g(), ORANGE.b()], "texture");
+}
@@ -0,0 +1,113 @@
+// egui widget showing a miniquad scene rendered into an offscreen render target
+use miniquad as mq;
+
+/// Pointer input over the canvas in pixels of the render target with the origin at the top left
+#[derive(Clone, Debug, Default)]
+pub struct CanvasInput {
+    pub size: (u32, u32),            // Width and height of the render target
+    pub pointer: Option<egui::Pos2>, // Position of the pointer while hovering over the canvas
+    pub drag: egui::Vec2,            // Pointer movement this frame while dragging on the canvas
+    pub scroll: egui::Vec2,          // Scroll wheel movement this frame while hovering
+    pub primary: bool,               // Primary button held down after pressing it on the canvas
+    pub secondary: bool,             // Secondary button held down after pressing it on the canvas
+}
+
+impl CanvasInput {
+    /// Get the width divided by the height of the canvas e.g. for a projection
+    pub fn aspect(&self) -> f32 {
+        self.size.0 as f32 / self.size.1 as f32
+    }
+}
+
+/// Widget filling the space it's given with whatever the draw callback renders.
+///
+/// The render target is recreated whenever the space allotted to the canvas changes size so that
+/// the scene is always drawn at the canvas' resolution.
+///
+/// ```ignore
+/// let mut canvas = MqCanvas::new().clear_color(1., 1., 1., 1.);
+/// egui::Window::new("Scene").show(egui_ctx, |ui| {
+///     canvas.show(ui, mq_ctx, |ctx, input| {
+///         ctx.apply_pipeline(&pipeline);
+///         ...
+///     });
+/// });
+/// ```
+pub struct MqCanvas {
+    pass: Option<mq::RenderPass>, // Offscreen color and depth target
+    size: (u32, u32),             // Size of the render target in pixels
+    clear: (f32, f32, f32, f32),  // Color to clear the target with before drawing
+    depth: bool,                  // Attach a depth buffer for 3D scenes
+}
+
+impl Default for MqCanvas {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MqCanvas {
+    /// Create a new canvas clearing to black with a depth buffer
+    pub fn new() -> Self {
+        Self { pass: None, size: (0, 0), clear: (0., 0., 0., 1.), depth: true }
+    }
+
+    /// Set the color the canvas is cleared with before drawing
+    pub fn clear_color(mut self, r: f32, g: f32, b: f32, a: f32) -> Self {
+        self.clear = (r, g, b, a);
+        self
+    }
+
+    /// Set whether the render target has a depth buffer
+    pub fn depth(mut self, value: bool) -> Self {
+        self.depth = value;
+        self
+    }
+
+    /// Fill the available space of the ui with the scene drawn by the given callback into the
+    /// canvas' render pass, passing it the pointer input over the canvas
+    pub fn show(
+        &mut self, ui: &mut egui::Ui, ctx: &mut mq::Context, draw: impl FnOnce(&mut mq::Context, &CanvasInput),
+    ) -> egui::Response {
+        let (rect, response) = ui.allocate_exact_size(ui.available_size(), egui::Sense::click_and_drag());
+        let scale = ui.ctx().pixels_per_point();
+        let size = ((rect.width() * scale).round().max(1.) as u32, (rect.height() * scale).round().max(1.) as u32);
+        let pass = self.resize(ctx, size);
+
+        let input = CanvasInput {
+            size,
+            pointer: response.hover_pos().map(|x| ((x - rect.min) * scale).to_pos2()),
+            drag: response.drag_delta() * scale,
+            scroll: if response.hovered() { ui.input().scroll_delta * scale } else { egui::Vec2::ZERO },
+            primary: response.dragged_by(egui::PointerButton::Primary),
+            secondary: response.dragged_by(egui::PointerButton::Secondary),
+        };
+
+        let (r, g, b, a) = self.clear;
+        ctx.begin_pass(pass, mq::PassAction::clear_color(r, g, b, a));
+        draw(ctx, &input);
+        ctx.end_render_pass();
+
+        // Render targets are stored bottom up so flip them to show them the right way up
+        let texture = egui::TextureId::User(pass.texture(ctx).gl_internal_id() as u64);
+        let uv = egui::Rect::from_min_max(egui::pos2(0., 1.), egui::pos2(1., 0.));
+        ui.painter().add(egui::Shape::image(texture, rect, uv, egui::Color32::WHITE));
+        response
+    }
+
+    // Get the render pass recreating it if the size has changed
+    fn resize(&mut self, ctx: &mut mq::Context, size: (u32, u32)) -> mq::RenderPass {
+        match self.pass {
+            Some(pass) if self.size == size => return pass,
+            Some(pass) => pass.delete(ctx),
+            None => (),
+        }
+        let params = |format| mq::TextureParams { width: size.0, height: size.1, format, ..Default::default() };
+        let color = mq::Texture::new_render_texture(ctx, params(mq::TextureFormat::RGBA8));
+        let depth = self.depth.then(|| mq::Texture::new_render_texture(ctx, params(mq::TextureFormat::Depth)));
+        let pass = mq::RenderPass::new(ctx, color, depth);
+        self.pass = Some(pass);
+        self.size = size;
+        pass
+    }
+}
@@ -0,0 +1,11 @@
+//! egui widget rendering a miniquad scene into an offscreen target shown inside the ui
+//!
+//! ### Example
+//! ```ignore
+//! use egui_miniquad_render_in_egui_win::canvas::MqCanvas;
+//! let mut canvas = MqCanvas::new().clear_color(1., 1., 1., 1.);
+//! egui::Window::new("Scene").show(egui_ctx, |ui| {
+//!     canvas.show(ui, mq_ctx, |ctx, input| scene.draw(ctx, input.aspect()));
+//! });
+//! ```
+pub mod canvas;
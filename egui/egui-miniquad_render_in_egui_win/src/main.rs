@@ -1,38 +1,19 @@
 use glam::{vec3, EulerRot, Mat4};
 use {egui_miniquad as egui_mq, miniquad as mq};
 
-struct Stage {
-    egui_mq: egui_mq::EguiMq,
-    offscreen_pipeline: mq::Pipeline,
-    offscreen_bind: mq::Bindings,
-    offscreen_pass: mq::RenderPass,
-    rx: f32,
-    ry: f32,
-}
+use egui_miniquad_render_in_egui_win::canvas::{CanvasInput, MqCanvas};
 
-impl Stage {
-    pub fn new(ctx: &mut mq::Context) -> Stage {
-        let color_img = mq::Texture::new_render_texture(
-            ctx,
-            mq::TextureParams {
-                width: 256,
-                height: 256,
-                format: mq::TextureFormat::RGBA8,
-                ..Default::default()
-            },
-        );
-        let depth_img = mq::Texture::new_render_texture(
-            ctx,
-            mq::TextureParams {
-                width: 256,
-                height: 256,
-                format: mq::TextureFormat::Depth,
-                ..Default::default()
-            },
-        );
+const DRAG_SPEED: f32 = 0.01; // Radians the cube turns per pixel dragged
+const ZOOM_SPEED: f32 = 0.005; // Change in camera distance per pixel scrolled
 
-        let offscreen_pass = mq::RenderPass::new(ctx, color_img, depth_img);
+// Untextured cube with a differently colored face on each side
+struct Cube {
+    pipeline: mq::Pipeline,
+    bind: mq::Bindings,
+}
 
+impl Cube {
+    fn new(ctx: &mut mq::Context) -> Cube {
         #[rustfmt::skip]
         let vertices: &[f32] = &[
             /* pos               color                   uvs */
@@ -81,7 +62,7 @@ impl Stage {
 
         let index_buffer = mq::Buffer::immutable(ctx, mq::BufferType::IndexBuffer, indices);
 
-        let offscreen_bind = mq::Bindings {
+        let bind = mq::Bindings {
             vertex_buffers: vec![vertex_buffer],
             index_buffer,
             images: vec![],
@@ -95,7 +76,7 @@ impl Stage {
         )
         .unwrap();
 
-        let offscreen_pipeline = mq::Pipeline::with_params(
+        let pipeline = mq::Pipeline::with_params(
             ctx,
             &[mq::BufferLayout {
                 stride: 36,
@@ -113,71 +94,89 @@ impl Stage {
             },
         );
 
-        Stage {
-            egui_mq: egui_mq::EguiMq::new(ctx),
-            offscreen_pipeline,
-            offscreen_bind,
-            offscreen_pass,
-            rx: 0.,
-            ry: 0.,
-        }
+        Cube { pipeline, bind }
     }
-}
-
-impl mq::EventHandler for Stage {
-    fn update(&mut self, _ctx: &mut mq::Context) {}
 
-    fn draw(&mut self, ctx: &mut mq::Context) {
-        let (width, height) = ctx.screen_size();
-        let proj = Mat4::perspective_rh_gl(60.0f32.to_radians(), width / height, 0.01, 10.0);
+    // Draw the cube with the given rotation seen from the given distance onto the current pass
+    fn draw(&self, ctx: &mut mq::Context, aspect: f32, rx: f32, ry: f32, distance: f32) {
+        let proj = Mat4::perspective_rh_gl(60.0f32.to_radians(), aspect, 0.01, 10.0);
         let view = Mat4::look_at_rh(
-            vec3(0.0, 1.5, 3.0),
+            vec3(0.0, 1.5, 3.0).normalize() * distance,
             vec3(0.0, 0.0, 0.0),
             vec3(0.0, 1.0, 0.0),
         );
-        let view_proj = proj * view;
-
-        self.rx += 0.01;
-        self.ry += 0.03;
-        let model = Mat4::from_euler(EulerRot::YXZ, self.rx, self.ry, 0.);
+        let model = Mat4::from_euler(EulerRot::YXZ, rx, ry, 0.);
+        let vs_params = offscreen_shader::Uniforms { mvp: proj * view * model };
 
-        let vs_params = offscreen_shader::Uniforms {
-            mvp: view_proj * model,
-        };
-
-        // the offscreen pass, rendering an rotating, untextured cube into a render target image
-        ctx.begin_pass(
-            self.offscreen_pass,
-            mq::PassAction::clear_color(1.0, 1.0, 1.0, 1.),
-        );
-        ctx.apply_pipeline(&self.offscreen_pipeline);
-        ctx.apply_bindings(&self.offscreen_bind);
+        ctx.apply_pipeline(&self.pipeline);
+        ctx.apply_bindings(&self.bind);
         ctx.apply_uniforms(&vs_params);
         ctx.draw(0, 36, 1);
-        ctx.end_render_pass();
+    }
+}
 
-        // Extract texture from offscreen render pass
-        let mq_texture = self.offscreen_pass.texture(ctx);
+struct Stage {
+    egui_mq: egui_mq::EguiMq,
+    canvas: MqCanvas,
+    cube: Cube,
+    rx: f32,
+    ry: f32,
+    distance: f32, // Distance of the camera from the cube
+}
 
-        // create egui TextureId from Miniquad GL texture Id
-        let egui_texture_id = egui::TextureId::User(mq_texture.gl_internal_id() as u64);
+impl Stage {
+    pub fn new(ctx: &mut mq::Context) -> Stage {
+        Stage {
+            egui_mq: egui_mq::EguiMq::new(ctx),
+            canvas: MqCanvas::new().clear_color(1.0, 1.0, 1.0, 1.),
+            cube: Cube::new(ctx),
+            rx: 0.,
+            ry: 0.,
+            distance: 3.35,
+        }
+    }
 
+    // Spin the cube by itself unless it's being dragged around and zoom with the scroll wheel
+    fn handle(&mut self, input: &CanvasInput) {
+        if input.primary {
+            self.rx += input.drag.x * DRAG_SPEED;
+            self.ry += input.drag.y * DRAG_SPEED;
+        } else {
+            self.rx += 0.01;
+            self.ry += 0.03;
+        }
+        self.distance = (self.distance - input.scroll.y * ZOOM_SPEED).clamp(2., 8.);
+    }
+}
+
+impl mq::EventHandler for Stage {
+    fn update(&mut self, _ctx: &mut mq::Context) {}
+
+    fn draw(&mut self, ctx: &mut mq::Context) {
         ctx.clear(Some((1., 1., 1., 1.)), None, None);
         ctx.begin_default_pass(mq::PassAction::clear_color(0.0, 0.0, 0.0, 1.0));
         ctx.end_render_pass();
 
         // Run the UI code:
-        self.egui_mq.run(ctx, |_mq_ctx, egui_ctx| {
-            egui::Window::new("egui ❤ miniquad").show(egui_ctx, |ui| {
-                ui.image(egui_texture_id, egui::Vec2::new(140.0, 140.0));
+        let mut input = CanvasInput::default();
+        let (canvas, cube, (rx, ry, distance)) = (&mut self.canvas, &self.cube, (self.rx, self.ry, self.distance));
+        self.egui_mq.run(ctx, |mq_ctx, egui_ctx| {
+            egui::Window::new("egui ❤ miniquad").default_size([256.0, 256.0]).show(egui_ctx, |ui| {
                 #[cfg(not(target_arch = "wasm32"))]
                 {
                     if ui.button("Quit").clicked() {
                         std::process::exit(0);
                     }
                 }
+
+                // The canvas resizes with the window
+                canvas.show(ui, mq_ctx, |ctx, canvas_input| {
+                    cube.draw(ctx, canvas_input.aspect(), rx, ry, distance);
+                    input = canvas_input.clone();
+                });
             });
         });
+        self.handle(&input);
 
         // Draw things behind egui here
 
@@ -246,7 +245,7 @@ fn main() {
         high_dpi: true,
         ..Default::default()
     };
-    mq::start(conf, |mut ctx| Box::new(Stage::new(&mut ctx)));
+    mq::start(conf, |ctx| Box::new(Stage::new(ctx)));
 }
 
 mod offscreen_shader {
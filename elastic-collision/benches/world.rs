@@ -0,0 +1,27 @@
+// Time steps of worlds with thousands of particles for each broadphase
+//
+// cargo bench
+use std::time::Instant;
+
+use elastic_collision::world::{Broadphase, World, TIMESTEP};
+use macroquad::color::WHITE;
+
+const STEPS: usize = 200;
+
+fn main() {
+    for count in [1000, 5000, 10000] {
+        for broadphase in [Broadphase::Grid, Broadphase::SweepAndPrune] {
+            // Keep the density the same as the particle count grows
+            let side = (count as f32 * 400.).sqrt();
+            let mut world = World::new(side, side).broadphase(broadphase).seed(42);
+            world.spawn(count, (3., 5.), &[WHITE]);
+
+            let start = Instant::now();
+            for _ in 0..STEPS {
+                world.step(TIMESTEP);
+            }
+            let elapsed = start.elapsed().as_secs_f64() * 1000. / STEPS as f64;
+            println!("{:>6} particles  {:<13}  {:>8.3} ms/step", count, format!("{:?}", broadphase), elapsed);
+        }
+    }
+}
@@ -0,0 +1,10 @@
+//! Elastic collisions between many particles in a box
+//!
+//! ### Example
+//! ```ignore
+//! use elastic_collision::world::*;
+//! let mut world = World::new(800., 600.);
+//! world.spawn(1000, (3., 5.), &[WHITE]);
+//! world.update(get_frame_time());
+//! ```
+pub mod world;
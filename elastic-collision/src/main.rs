@@ -1,151 +1,122 @@
+use elastic_collision::world::{Broadphase, Obstacle, Particle, World};
 use macroquad::prelude::*;
-use macroquad::rand::{gen_range, ChooseRandom};
+use macroquad::rand::gen_range;
 
-#[derive(Copy, Clone, PartialEq, Debug)]
-struct Particle {
-    position: Vec2,
-    velocity: Vec2,
-    color: Color,
-    radius: f32,
-    id: usize,
-}
+const SPAWN_COUNT: usize = 100; // Particles added or removed at a time
+const GRAVITY: f32 = 400.; // Downwards acceleration in pixels per second squared when enabled
+const DRAG: f32 = 0.3; // Fraction of the velocity lost per second when enabled
+const HEAVY: f32 = 10.; // Mass multiplier of particles spawned with the right mouse button
+const RESTITUTIONS: [f32; 4] = [1., 0.9, 0.7, 0.4]; // Choices of bounciness for spawned particles
 
-impl Particle {
-    fn new(radius: f32, color: Color, id: usize) -> Self {
-        Particle {
-            position: vec2(
-                gen_range(0., screen_width()),
-                gen_range(0., screen_height()),
-            ),
-            velocity: vec2(
-                gen_range(2.0, 4.0) * vec![-1., 1.].choose().unwrap(),
-                gen_range(2.0, 4.0) * vec![-1., 1.].choose().unwrap(),
-            ),
-            color,
-            radius,
-            id,
-        }
-    }
-    fn update(&mut self) {
-        self.velocity = self.velocity.clamp_length_max(5.6);
-        self.position += self.velocity;
-        if self.position.x + self.radius >= screen_width() {
-            self.position.x = screen_width() - self.radius;
-        } else if self.position.x - self.radius <= 0. {
-            self.position.x = 0. + self.radius;
-        }
-        if self.position.y + self.radius >= screen_height() {
-            self.position.y = screen_height() - self.radius;
-        } else if self.position.y - self.radius <= 0. {
-            self.position.y = 0. + self.radius;
-        }
-        if self.position.x + self.radius == screen_width() || self.position.x - self.radius == 0. {
-            self.velocity.x *= -1.;
-        }
-        if self.position.y + self.radius == screen_height() || self.position.y - self.radius == 0. {
-            self.velocity.y *= -1.;
-        }
-    }
+// Lines and boxes placed relative to the given window size
+fn obstacles(width: f32, height: f32) -> Vec<Obstacle> {
+    vec![
+        Obstacle::Line(vec2(width * 0.1, height * 0.35), vec2(width * 0.4, height * 0.5)),
+        Obstacle::Line(vec2(width * 0.9, height * 0.35), vec2(width * 0.6, height * 0.5)),
+        Obstacle::Box(Rect::new(width * 0.45, height * 0.7, width * 0.1, height * 0.05)),
+    ]
 }
 
 #[macroquad::main("Collision")]
 async fn main() {
-    let mut particles: Vec<Particle> = Vec::new();
-    let colors = vec![
+    let colors = [
         Color::from_rgba(30, 174, 66, 125),
         Color::from_rgba(153, 53, 46, 125),
         Color::from_rgba(64, 25, 159, 125),
         Color::from_rgba(41, 90, 204, 125),
         Color::from_rgba(113, 32, 193, 125),
     ];
-    for id in 0..15 {
-        particles.push(Particle::new(
-            gen_range(13.0, 20.0),
-            *colors.choose().unwrap(),
-            id,
-        ));
-    }
-    loop {
-        clear_background(Color::from_rgba(14, 5, 34, 255));
-        particles.sort_by(|a, b| {
-            vec2(a.position.x + a.radius, a.position.y + a.radius)
-                .x
-                .partial_cmp(&vec2(b.position.x + b.radius, b.position.y + b.radius).x)
-                .unwrap()
-        });
-        let mut active_list: Vec<Particle> = vec![particles[0]];
-        let mut possible_collisions: Vec<(usize, usize)> = Vec::new();
-        for (pind, particle) in particles.iter().enumerate() {
-            let mut particles_to_add: Vec<Particle> = Vec::new();
-            for active_particle in active_list.iter() {
-                if active_particle.id != particle.id {
-                    if particle.position.x - particle.radius
-                        <= active_particle.position.x + active_particle.radius
-                    {
-                        possible_collisions.push((pind, active_particle.id));
-                    }
-                    particles_to_add.push(*particle);
-                }
-            }
-            active_list.retain(|active_particle| {
-                particle.position.x - particle.radius
-                    <= active_particle.position.x + active_particle.radius
-            });
 
-            for pta in particles_to_add {
-                active_list.push(pta);
-            }
-        }
+    // Pass the number of particles to start with
+    let count = std::env::args().nth(1).and_then(|x| x.parse().ok()).unwrap_or(15);
+    let mut world = World::new(screen_width(), screen_height()).seed(miniquad::date::now() as u64);
+    world.spawn(count, (13., 20.), &colors);
 
-        for (p_pos, ap_id) in possible_collisions {
-            let ap_pos = particles.iter().position(|&p| p.id == ap_id).unwrap();
-            let part = particles[p_pos];
-            let actpart = particles[ap_pos];
+    let mut restitution = 0; // Index of the restitution of particles spawned with the mouse
+    let mut grabbed: Option<usize> = None; // Particle being dragged with the mouse
+    let mut fling = Vec2::ZERO; // Smoothed mouse velocity given to the grabbed particle on release
+    let mut last_mouse = Vec2::from(mouse_position());
 
-            let dist = part.position.distance(actpart.position);
-            if dist <= part.radius + actpart.radius {
-                let ndist = (part.position - actpart.position).normalize();
-                let touch_distance = dist * (actpart.radius / (actpart.radius + part.radius));
-                let contact = actpart.position + (ndist * touch_distance);
+    loop {
+        // Press B to switch broadphase and +/- to add or remove particles
+        if is_key_pressed(KeyCode::B) {
+            world.set_broadphase(match world.get_broadphase() {
+                Broadphase::Grid => Broadphase::SweepAndPrune,
+                Broadphase::SweepAndPrune => Broadphase::Grid,
+            });
+        }
+        if is_key_pressed(KeyCode::Equal) || is_key_pressed(KeyCode::KpAdd) {
+            world.spawn(SPAWN_COUNT, (4., 8.), &colors);
+        }
+        if is_key_pressed(KeyCode::Minus) || is_key_pressed(KeyCode::KpSubtract) {
+            let len = world.particles.len().saturating_sub(SPAWN_COUNT);
+            world.particles.truncate(len);
+            grabbed = grabbed.filter(|x| *x < len);
+        }
 
-                particles[ap_pos].position = contact - (ndist * actpart.radius);
-                particles[p_pos].position = contact + (ndist * part.radius);
+        // Press G for gravity, D for drag, O for obstacles and E to change bounciness
+        if is_key_pressed(KeyCode::G) {
+            world.set_gravity(if world.get_gravity() == Vec2::ZERO { vec2(0., GRAVITY) } else { Vec2::ZERO });
+        }
+        if is_key_pressed(KeyCode::D) {
+            world.set_drag(if world.get_drag() == 0. { DRAG } else { 0. });
+        }
+        if is_key_pressed(KeyCode::O) {
+            world.obstacles =
+                if world.obstacles.is_empty() { obstacles(screen_width(), screen_height()) } else { Vec::new() };
+        }
+        if is_key_pressed(KeyCode::E) {
+            restitution = (restitution + 1) % RESTITUTIONS.len();
+        }
 
-                let p_vel = part.velocity.length();
-                let ap_vel = actpart.velocity.length();
-                let p_dir = part.velocity.y.atan2(part.velocity.x);
-                let ap_dir = actpart.velocity.y.atan2(actpart.velocity.x);
+        // Click a particle to grab it and release to fling it or click elsewhere to spawn one with
+        // the right button spawning heavy particles
+        let mouse = Vec2::from(mouse_position());
+        fling = fling.lerp((mouse - last_mouse) / get_frame_time().max(f32::EPSILON), 0.5);
+        last_mouse = mouse;
+        for button in [MouseButton::Left, MouseButton::Right] {
+            if !is_mouse_button_pressed(button) {
+                continue;
+            }
+            match world.particle_at(mouse) {
+                Some(i) if button == MouseButton::Left => {
+                    world.particles[i].pinned = true;
+                    grabbed = Some(i);
+                },
+                Some(_) => (),
+                None => {
+                    let radius = gen_range(13., 20.);
+                    let mut particle =
+                        Particle::new(mouse, Vec2::ZERO, radius, colors[gen_range(0, colors.len())]);
+                    particle.restitution = RESTITUTIONS[restitution];
+                    if button == MouseButton::Right {
+                        particle.mass = Some(particle.mass() * HEAVY);
+                    }
+                    world.particles.push(particle);
+                },
+            }
+        }
+        if let Some(i) = grabbed {
+            let particle = &mut world.particles[i];
+            particle.position = mouse;
+            particle.velocity = fling;
+            if is_mouse_button_released(MouseButton::Left) {
+                particle.pinned = false;
+                grabbed = None;
+            }
+        }
 
-                let contact_dir = ndist.y.atan2(ndist.x);
-                let p_mass = part.radius.powf(3.);
-                let ap_mass = actpart.radius.powf(3.);
+        world.bounds = vec2(screen_width(), screen_height());
+        world.update(get_frame_time());
 
-                let t = (ap_vel * (ap_dir - contact_dir).cos() * (ap_mass - p_mass)
-                    + 2. * p_mass * p_vel * (p_dir - contact_dir).cos())
-                    / (ap_mass + p_mass);
-                particles[ap_pos].velocity.x = t * contact_dir.cos()
-                    + ap_vel
-                        * (ap_dir - contact_dir).sin()
-                        * (contact_dir + std::f32::consts::PI / 2.).cos();
-                particles[ap_pos].velocity.y = t * contact_dir.sin()
-                    + ap_vel
-                        * (ap_dir - contact_dir).sin()
-                        * (contact_dir + std::f32::consts::PI / 2.).sin();
-                let t = (p_vel * (p_dir - contact_dir).cos() * (p_mass - ap_mass)
-                    + 2. * ap_mass * ap_vel * (ap_dir - contact_dir).cos())
-                    / (ap_mass + p_mass);
-                particles[p_pos].velocity.x = t * -(contact_dir.cos())
-                    + p_vel
-                        * (p_dir - contact_dir).sin()
-                        * -(contact_dir + std::f32::consts::PI / 2.).cos();
-                particles[p_pos].velocity.y = t * -(contact_dir.sin())
-                    + p_vel
-                        * (p_dir - contact_dir).sin()
-                        * -(contact_dir + std::f32::consts::PI / 2.).sin();
+        clear_background(Color::from_rgba(14, 5, 34, 255));
+        for obstacle in world.obstacles.iter() {
+            match *obstacle {
+                Obstacle::Line(a, b) => draw_line(a.x, a.y, b.x, b.y, 4., GRAY),
+                Obstacle::Box(rect) => draw_rectangle(rect.x, rect.y, rect.w, rect.h, GRAY),
             }
         }
-        for particle in particles.iter_mut() {
-            particle.update();
+        for particle in world.particles.iter() {
             for i in 0..5 {
                 draw_circle_lines(
                     particle.position.x,
@@ -166,7 +137,32 @@ async fn main() {
                 particle.radius - 8.,
                 Color::from_rgba(255, 255, 255, 190),
             );
+            if particle.mass.is_some() || particle.pinned {
+                draw_circle_lines(particle.position.x, particle.position.y, particle.radius, 2., WHITE);
+            }
         }
+
+        let status = format!(
+            "{} particles  {:?}  {} candidate pairs  {} fps  (B: broadphase, +/-: particles)",
+            world.particles.len(),
+            world.get_broadphase(),
+            world.candidates(),
+            get_fps(),
+        );
+        let momentum = world.momentum();
+        let hud = format!(
+            "energy {:.3e}  momentum {:.3e} ({:.0} deg)  gravity {}  drag {}  obstacles {}  restitution {}  \
+             (G, D, O, E, click: spawn/grab, right click: heavy)",
+            world.energy(),
+            momentum.length(),
+            momentum.y.atan2(momentum.x).to_degrees(),
+            if world.get_gravity() == Vec2::ZERO { "off" } else { "on" },
+            if world.get_drag() == 0. { "off" } else { "on" },
+            if world.obstacles.is_empty() { "off" } else { "on" },
+            RESTITUTIONS[restitution],
+        );
+        draw_text(&status, 10., 20., 20., WHITE);
+        draw_text(&hud, 10., 40., 20., WHITE);
         next_frame().await;
     }
 }
@@ -0,0 +1,648 @@
+// Particles bouncing off each other, obstacles and the walls of a box
+//
+// The simulation doesn't touch the window or any global state so that it can run headless and
+// reproduce the same run given the same seed e.g. in tests and benchmarks.
+use macroquad::color::Color;
+use macroquad::math::{vec2, Rect, Vec2};
+use macroquad::rand::RandGenerator;
+
+/// Default simulation step in seconds independent of the frame rate
+pub const TIMESTEP: f32 = 1. / 120.;
+
+/// Maximum particle speed in pixels per second
+pub const MAX_SPEED: f32 = 336.;
+
+// Steps per update before dropping time so that a slow frame can't snowball into slower frames
+const MAX_STEPS: usize = 8;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Particle {
+    pub position: Vec2,
+    pub velocity: Vec2, // Pixels per second
+    pub color: Color,
+    pub radius: f32,
+    pub restitution: f32, // Fraction of the approach speed kept when bouncing with 1 being elastic
+    pub mass: Option<f32>, // Mass overriding the one derived from the radius
+    pub pinned: bool,     // Moved from outside the simulation e.g. dragged with the mouse
+}
+
+impl Particle {
+    /// Create a new perfectly elastic particle
+    pub fn new(position: Vec2, velocity: Vec2, radius: f32, color: Color) -> Self {
+        Particle { position, velocity, color, radius, restitution: 1., mass: None, pinned: false }
+    }
+
+    /// Get the mass of the particle which grows with its volume unless overridden
+    pub fn mass(&self) -> f32 {
+        self.mass.unwrap_or_else(|| self.radius.powf(3.))
+    }
+
+    // Pinned particles push others around without being pushed back as if infinitely heavy
+    fn inverse_mass(&self) -> f32 {
+        if self.pinned {
+            0.
+        } else {
+            1. / self.mass()
+        }
+    }
+}
+
+/// Static shape the particles bounce off
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Obstacle {
+    Line(Vec2, Vec2), // Segment between two points
+    Box(Rect),        // Solid axis aligned rectangle
+}
+
+impl Obstacle {
+    // Get the direction and distance to push a circle out of the obstacle if they overlap
+    fn contact(&self, center: Vec2, radius: f32) -> Option<(Vec2, f32)> {
+        let closest = match *self {
+            Obstacle::Line(a, b) => {
+                let t = ((center - a).dot(b - a) / (b - a).length_squared().max(f32::EPSILON)).clamp(0., 1.);
+                a + (b - a) * t
+            },
+            Obstacle::Box(rect) => {
+                let closest = center.clamp(rect.point(), rect.point() + rect.size());
+
+                // Push out through the nearest side when the center is inside
+                if closest == center {
+                    let sides = [
+                        (center.x - rect.left(), -Vec2::X),
+                        (rect.right() - center.x, Vec2::X),
+                        (center.y - rect.top(), -Vec2::Y),
+                        (rect.bottom() - center.y, Vec2::Y),
+                    ];
+                    let (depth, normal) = sides.into_iter().fold(sides[0], |a, b| if b.0 < a.0 { b } else { a });
+                    return Some((normal, depth + radius));
+                }
+                closest
+            },
+        };
+        let delta = center - closest;
+        let dist = delta.length();
+        if dist >= radius {
+            return None;
+        }
+        let normal = if dist > 0. {
+            delta / dist
+        } else {
+            match *self {
+                Obstacle::Line(a, b) => (b - a).perp().normalize_or_zero(),
+                Obstacle::Box(_) => -Vec2::Y,
+            }
+        };
+        Some((normal, radius - dist))
+    }
+}
+
+/// Algorithm used to find the pairs of particles that might be colliding
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Broadphase {
+    Grid,          // Bucket particles into a uniform grid of cells the size of the largest particle
+    SweepAndPrune, // Sort particles along the x axis and sweep for overlapping extents
+}
+
+/// Box of particles advanced with a fixed timestep.
+///
+/// The world is deterministic: the same seed, bounds and sequence of steps give the same result.
+///
+/// The broadphase state is kept between steps so that the per step work is close to linear in
+/// the number of particles: the grid reuses its buffers and sweep and prune keeps the particle
+/// order from the previous step which is nearly sorted already making the sort close to linear.
+pub struct World {
+    pub particles: Vec<Particle>,
+    pub bounds: Vec2,             // Width and height of the box with the origin at the top left
+    pub obstacles: Vec<Obstacle>, // Static shapes inside the box
+    gravity: Vec2,                // Acceleration in pixels per second squared
+    drag: f32,                    // Fraction of the velocity lost per second
+    broadphase: Broadphase,       // Algorithm to find candidate pairs
+    timestep: f32,                // Seconds per step
+    accumulator: f32,             // Time left over from the last update not yet stepped
+    pairs: Vec<(usize, usize)>,   // Candidate pairs found by the broadphase
+    order: Vec<usize>,            // Particle indices sorted by left edge for sweep and prune
+    active: Vec<usize>,           // Particles whose extent overlaps the sweep position
+    cell_start: Vec<usize>,       // Offset of each grid cell's particles in `cell_items`
+    cell_items: Vec<usize>,       // Particle indices grouped by grid cell
+    cell_next: Vec<usize>,        // Next free slot of each grid cell while filling `cell_items`
+    cell_of: Vec<usize>,          // Grid cell of each particle
+    rng: RandGenerator,           // Random numbers for spawning particles
+}
+
+impl World {
+    /// Create an empty world with the given bounds
+    ///
+    /// ```ignore
+    /// let mut world = World::new(800., 600.).broadphase(Broadphase::SweepAndPrune).seed(42);
+    /// ```
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            particles: Vec::new(),
+            bounds: vec2(width, height),
+            obstacles: Vec::new(),
+            gravity: Vec2::ZERO,
+            drag: 0.,
+            broadphase: Broadphase::Grid,
+            timestep: TIMESTEP,
+            accumulator: 0.,
+            pairs: Vec::new(),
+            order: Vec::new(),
+            active: Vec::new(),
+            cell_start: Vec::new(),
+            cell_items: Vec::new(),
+            cell_next: Vec::new(),
+            cell_of: Vec::new(),
+            rng: RandGenerator::new(),
+        }
+        .seed(0)
+    }
+
+    /// Set the algorithm used to find candidate pairs
+    pub fn broadphase(mut self, value: Broadphase) -> Self {
+        self.broadphase = value;
+        self
+    }
+
+    /// Set the simulation step in seconds
+    pub fn timestep(mut self, value: f32) -> Self {
+        self.timestep = value;
+        self
+    }
+
+    /// Set the acceleration applied to every particle in pixels per second squared
+    pub fn gravity(mut self, value: Vec2) -> Self {
+        self.gravity = value;
+        self
+    }
+
+    /// Set the fraction of their velocity particles lose per second
+    pub fn drag(mut self, value: f32) -> Self {
+        self.drag = value;
+        self
+    }
+
+    /// Seed the random numbers used to spawn particles
+    pub fn seed(self, value: u64) -> Self {
+        self.rng.srand(value);
+        self
+    }
+
+    /// Change the algorithm used to find candidate pairs
+    pub fn set_broadphase(&mut self, value: Broadphase) {
+        self.broadphase = value;
+    }
+
+    /// Get the algorithm used to find candidate pairs
+    pub fn get_broadphase(&self) -> Broadphase {
+        self.broadphase
+    }
+
+    /// Change the acceleration applied to every particle
+    pub fn set_gravity(&mut self, value: Vec2) {
+        self.gravity = value;
+    }
+
+    /// Get the acceleration applied to every particle
+    pub fn get_gravity(&self) -> Vec2 {
+        self.gravity
+    }
+
+    /// Change the fraction of their velocity particles lose per second
+    pub fn set_drag(&mut self, value: f32) {
+        self.drag = value;
+    }
+
+    /// Get the fraction of their velocity particles lose per second
+    pub fn get_drag(&self) -> f32 {
+        self.drag
+    }
+
+    /// Get the topmost particle under the given position if any
+    pub fn particle_at(&self, position: Vec2) -> Option<usize> {
+        self.particles.iter().rposition(|x| x.position.distance(position) <= x.radius)
+    }
+
+    /// Get the number of candidate pairs the broadphase found in the last step
+    pub fn candidates(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Get the total momentum of the particles
+    pub fn momentum(&self) -> Vec2 {
+        self.particles.iter().fold(Vec2::ZERO, |acc, x| acc + x.velocity * x.mass())
+    }
+
+    /// Get the total kinetic energy of the particles
+    pub fn kinetic_energy(&self) -> f32 {
+        self.particles.iter().map(|x| 0.5 * x.mass() * x.velocity.length_squared()).sum()
+    }
+
+    /// Get the total potential energy of the particles due to gravity relative to the top left
+    pub fn potential_energy(&self) -> f32 {
+        self.particles.iter().map(|x| -x.mass() * self.gravity.dot(x.position)).sum()
+    }
+
+    /// Get the total kinetic and potential energy of the particles
+    pub fn energy(&self) -> f32 {
+        self.kinetic_energy() + self.potential_energy()
+    }
+
+    /// Add the given number of particles at random positions with random velocities, radii in
+    /// the given range and one of the given colors
+    pub fn spawn(&mut self, count: usize, radius: (f32, f32), colors: &[Color]) {
+        let rng = &self.rng;
+        let sign = || if rng.gen_range(0, 2) == 0 { -1. } else { 1. };
+        for _ in 0..count {
+            let radius = rng.gen_range(radius.0, radius.1);
+            let position = vec2(
+                rng.gen_range(radius, (self.bounds.x - radius).max(radius)),
+                rng.gen_range(radius, (self.bounds.y - radius).max(radius)),
+            );
+            let velocity = vec2(rng.gen_range(120., 240.) * sign(), rng.gen_range(120., 240.) * sign());
+            let color = colors[rng.gen_range(0, colors.len())];
+            self.particles.push(Particle::new(position, velocity, radius, color));
+        }
+    }
+
+    /// Advance the world by the given frame time in as many fixed steps as fit carrying the rest
+    /// over to the next update
+    pub fn update(&mut self, frame_time: f32) {
+        self.accumulator += frame_time;
+        let mut steps = 0;
+        while self.accumulator >= self.timestep {
+            self.step(self.timestep);
+            self.accumulator -= self.timestep;
+            steps += 1;
+            if steps >= MAX_STEPS {
+                self.accumulator = 0.;
+                break;
+            }
+        }
+    }
+
+    /// Advance the world by a single step of the given number of seconds
+    pub fn step(&mut self, dt: f32) {
+        match self.broadphase {
+            Broadphase::Grid => self.grid_pairs(),
+            Broadphase::SweepAndPrune => self.sweep_and_prune_pairs(),
+        }
+        for k in 0..self.pairs.len() {
+            let (i, j) = self.pairs[k];
+            self.collide(i, j);
+        }
+        let damping = (-self.drag * dt).exp();
+        for particle in self.particles.iter_mut() {
+            if !particle.pinned {
+                particle.velocity =
+                    ((particle.velocity + self.gravity * dt) * damping).clamp_length_max(MAX_SPEED);
+                particle.position += particle.velocity * dt;
+            }
+            for obstacle in self.obstacles.iter() {
+                if let Some((normal, depth)) = obstacle.contact(particle.position, particle.radius) {
+                    particle.position += normal * depth;
+                    Self::reflect(particle, normal);
+                }
+            }
+            Self::bounce(particle, self.bounds);
+        }
+    }
+
+    // Keep the particle inside the bounds reflecting its velocity off the walls
+    fn bounce(particle: &mut Particle, bounds: Vec2) {
+        let (min, max) = (Vec2::splat(particle.radius), bounds - particle.radius);
+        let clamped = particle.position.clamp(min, max.max(min));
+        for axis in [Vec2::X, Vec2::Y] {
+            let push = (clamped - particle.position).dot(axis);
+            if push != 0. {
+                Self::reflect(particle, axis * push.signum());
+            }
+        }
+        particle.position = clamped;
+    }
+
+    // Bounce the particle off a static surface with the given normal if moving into it
+    fn reflect(particle: &mut Particle, normal: Vec2) {
+        let speed = particle.velocity.dot(normal);
+        if speed < 0. {
+            particle.velocity -= normal * (1. + particle.restitution) * speed;
+        }
+    }
+
+    // Separate the pair if overlapping and exchange momentum along the line between their
+    // centers if they're moving towards each other. The pair bounces as much as the less bouncy
+    // of the two.
+    fn collide(&mut self, i: usize, j: usize) {
+        let (a, b) = (self.particles[i], self.particles[j]);
+        let delta = a.position - b.position;
+        let dist = delta.length();
+        if dist > a.radius + b.radius {
+            return;
+        }
+        let (ia, ib) = (a.inverse_mass(), b.inverse_mass());
+        if ia + ib == 0. {
+            return;
+        }
+        let normal = if dist > 0. { delta / dist } else { Vec2::X };
+
+        // Push them apart in proportion to the other's mass so heavy particles move less
+        let overlap = a.radius + b.radius - dist;
+        self.particles[i].position += normal * overlap * ia / (ia + ib);
+        self.particles[j].position -= normal * overlap * ib / (ia + ib);
+
+        let approach = (a.velocity - b.velocity).dot(normal);
+        if approach < 0. {
+            let impulse = -(1. + a.restitution.min(b.restitution)) * approach / (ia + ib);
+            self.particles[i].velocity += normal * impulse * ia;
+            self.particles[j].velocity -= normal * impulse * ib;
+        }
+    }
+
+    // Bucket the particles into cells at least as large as the largest particle so that only
+    // particles in the same or neighbouring cells can overlap
+    fn grid_pairs(&mut self) {
+        self.pairs.clear();
+        let max_radius = self.particles.iter().map(|x| x.radius).fold(0., f32::max);
+        let size = (max_radius * 2.).max(1.);
+        let cols = ((self.bounds.x / size).ceil() as usize).max(1);
+        let rows = ((self.bounds.y / size).ceil() as usize).max(1);
+        let cell = |p: Vec2| {
+            let col = ((p.x / size).max(0.) as usize).min(cols - 1);
+            let row = ((p.y / size).max(0.) as usize).min(rows - 1);
+            (col, row)
+        };
+
+        // Counting sort of the particles by cell
+        self.cell_start.clear();
+        self.cell_start.resize(cols * rows + 1, 0);
+        self.cell_of.clear();
+        for particle in self.particles.iter() {
+            let (col, row) = cell(particle.position);
+            self.cell_of.push(row * cols + col);
+            self.cell_start[row * cols + col + 1] += 1;
+        }
+        for i in 1..self.cell_start.len() {
+            self.cell_start[i] += self.cell_start[i - 1];
+        }
+        self.cell_items.clear();
+        self.cell_items.resize(self.particles.len(), 0);
+        self.cell_next.resize(self.cell_start.len(), 0);
+        self.cell_next.copy_from_slice(&self.cell_start);
+        for (i, c) in self.cell_of.iter().enumerate() {
+            self.cell_items[self.cell_next[*c]] = i;
+            self.cell_next[*c] += 1;
+        }
+
+        // Check each particle against the particles in its own and the neighbouring cells
+        for (i, particle) in self.particles.iter().enumerate() {
+            let (col, row) = cell(particle.position);
+            for r in row.saturating_sub(1)..(row + 2).min(rows) {
+                for c in col.saturating_sub(1)..(col + 2).min(cols) {
+                    let index = r * cols + c;
+                    for &j in self.cell_items[self.cell_start[index]..self.cell_start[index + 1]].iter() {
+                        if j > i {
+                            self.pairs.push((i, j));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Sweep along the x axis keeping a list of particles whose extent overlaps the current
+    // particle's left edge; only those can be colliding with it
+    fn sweep_and_prune_pairs(&mut self) {
+        self.pairs.clear();
+        let particles = &self.particles;
+        if self.order.len() != particles.len() {
+            self.order = (0..particles.len()).collect();
+        }
+
+        // Insertion sort is close to linear as the order barely changes between steps
+        let left = |i: usize| particles[i].position.x - particles[i].radius;
+        for i in 1..self.order.len() {
+            let mut j = i;
+            while j > 0 && left(self.order[j - 1]) > left(self.order[j]) {
+                self.order.swap(j - 1, j);
+                j -= 1;
+            }
+        }
+
+        self.active.clear();
+        for &i in self.order.iter() {
+            let edge = left(i);
+            self.active.retain(|&j| particles[j].position.x + particles[j].radius >= edge);
+            for &j in self.active.iter() {
+                self.pairs.push((i.min(j), i.max(j)));
+            }
+            self.active.push(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use macroquad::color::WHITE;
+
+    const BROADPHASES: [Broadphase; 2] = [Broadphase::Grid, Broadphase::SweepAndPrune];
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() <= b.abs().max(1.) * 1e-3, "{} != {}", a, b);
+    }
+
+    // Particles of different sizes packed in the middle of a box too large for them to reach the
+    // walls within the given number of steps
+    fn cluster(broadphase: Broadphase, steps: usize) -> World {
+        let mut world = World::new(10000., 10000.).broadphase(broadphase).seed(7);
+        let rng = RandGenerator::new();
+        rng.srand(7);
+        for _ in 0..200 {
+            let position = vec2(rng.gen_range(4900., 5100.), rng.gen_range(4900., 5100.));
+            let velocity = vec2(rng.gen_range(-20., 20.), rng.gen_range(-20., 20.));
+            world.particles.push(Particle::new(position, velocity, rng.gen_range(4., 8.), WHITE));
+        }
+        let (momentum, energy) = (world.momentum(), world.kinetic_energy());
+        for _ in 0..steps {
+            world.step(TIMESTEP);
+        }
+        assert_close(world.momentum().x, momentum.x);
+        assert_close(world.momentum().y, momentum.y);
+        assert_close(world.kinetic_energy(), energy);
+        world
+    }
+
+    #[test]
+    fn head_on_collision() {
+        for broadphase in BROADPHASES {
+            let mut world = World::new(400., 400.).broadphase(broadphase);
+            world.particles.push(Particle::new(vec2(150., 200.), vec2(100., 0.), 10., WHITE));
+            world.particles.push(Particle::new(vec2(250., 200.), vec2(-100., 0.), 20., WHITE));
+            let (momentum, energy) = (world.momentum(), world.kinetic_energy());
+            for _ in 0..60 {
+                world.step(TIMESTEP);
+            }
+
+            // Velocities after a one dimensional elastic collision of masses 1000 and 8000
+            assert_close(world.particles[0].velocity.x, -2300. / 9.);
+            assert_close(world.particles[1].velocity.x, -500. / 9.);
+            assert_close(world.momentum().x, momentum.x);
+            assert_close(world.momentum().y, momentum.y);
+            assert_close(world.kinetic_energy(), energy);
+        }
+    }
+
+    #[test]
+    fn collisions_conserve_momentum_and_energy() {
+        for broadphase in BROADPHASES {
+            cluster(broadphase, 600);
+        }
+    }
+
+    #[test]
+    fn walls_conserve_energy() {
+        for broadphase in BROADPHASES {
+            let mut world = World::new(600., 400.).broadphase(broadphase).seed(3);
+            world.spawn(300, (5., 5.), &[WHITE]);
+
+            // Slow enough that no particle is ever sped up past the limit which would lose energy
+            for particle in world.particles.iter_mut() {
+                particle.velocity = particle.velocity.normalize() * 60.;
+            }
+            let energy = world.kinetic_energy();
+            for _ in 0..1200 {
+                world.step(TIMESTEP);
+            }
+            assert_close(world.kinetic_energy(), energy);
+        }
+    }
+
+    #[test]
+    fn particles_never_leave_bounds() {
+        for broadphase in BROADPHASES {
+            let mut world = World::new(300., 200.).broadphase(broadphase).seed(11);
+            world.spawn(200, (3., 8.), &[WHITE]);
+            for particle in world.particles.iter_mut() {
+                particle.velocity = particle.velocity.normalize() * MAX_SPEED;
+            }
+
+            // Steps long enough to move several radii at once
+            for _ in 0..500 {
+                world.step(0.05);
+                for p in world.particles.iter() {
+                    assert!(p.position.x >= p.radius && p.position.x <= world.bounds.x - p.radius, "{:?}", p);
+                    assert!(p.position.y >= p.radius && p.position.y <= world.bounds.y - p.radius, "{:?}", p);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn inelastic_collision_conserves_momentum() {
+        let mut world = World::new(400., 400.);
+        world.particles.push(Particle::new(vec2(150., 200.), vec2(100., 0.), 10., WHITE));
+        world
+            .particles
+            .push(Particle { restitution: 0., ..Particle::new(vec2(250., 200.), vec2(-100., 0.), 10., WHITE) });
+        let momentum = world.momentum();
+        for _ in 0..60 {
+            world.step(TIMESTEP);
+        }
+
+        // The less bouncy particle decides so they stick together
+        assert_close(world.particles[0].velocity.x, 0.);
+        assert_close(world.particles[1].velocity.x, 0.);
+        assert_close(world.momentum().x, momentum.x);
+    }
+
+    #[test]
+    fn mass_override() {
+        let mut world = World::new(400., 400.);
+        world.particles.push(Particle::new(vec2(150., 200.), vec2(100., 0.), 10., WHITE));
+        world
+            .particles
+            .push(Particle { mass: Some(1000.), ..Particle::new(vec2(250., 200.), vec2(-100., 0.), 20., WHITE) });
+        for _ in 0..60 {
+            world.step(TIMESTEP);
+        }
+
+        // Equal masses swap velocities even though the radii differ
+        assert_close(world.particles[0].velocity.x, -100.);
+        assert_close(world.particles[1].velocity.x, 100.);
+    }
+
+    #[test]
+    fn pinned_particles_push_without_moving() {
+        let mut world = World::new(400., 400.);
+        world.particles.push(Particle { pinned: true, ..Particle::new(vec2(200., 200.), Vec2::ZERO, 20., WHITE) });
+        world.particles.push(Particle::new(vec2(140., 200.), vec2(100., 0.), 10., WHITE));
+        for _ in 0..60 {
+            world.step(TIMESTEP);
+        }
+        assert_eq!(world.particles[0].position, vec2(200., 200.));
+        assert_close(world.particles[1].velocity.x, -100.);
+    }
+
+    #[test]
+    fn gravity_conserves_energy() {
+        let mut world = World::new(400., 400.).gravity(vec2(0., 200.));
+        world.particles.push(Particle::new(vec2(200., 200.), Vec2::ZERO, 10., WHITE));
+        let energy = world.energy();
+        for _ in 0..240 {
+            world.step(TIMESTEP);
+            assert!(world.particles[0].position.y > 200. - 1.);
+        }
+
+        // Semi-implicit Euler and snapping to the floor drift a little so allow for a few pixels of height
+        let drift = world.particles[0].mass() * 200. * 3.;
+        assert!((world.energy() - energy).abs() < drift, "{} != {}", world.energy(), energy);
+    }
+
+    #[test]
+    fn drag_slows_particles() {
+        let mut world = World::new(400., 400.).drag(0.5);
+        world.particles.push(Particle::new(vec2(200., 200.), vec2(100., 0.), 10., WHITE));
+        for _ in 0..120 {
+            world.step(TIMESTEP);
+        }
+        assert_close(world.particles[0].velocity.x, 100. * (-0.5f32).exp());
+    }
+
+    #[test]
+    fn obstacles_stop_particles() {
+        let obstacles = [
+            Obstacle::Line(vec2(0., 250.), vec2(400., 250.)),
+            Obstacle::Box(Rect::new(0., 250., 400., 30.)),
+        ];
+        for obstacle in obstacles {
+            let mut world = World::new(400., 400.).gravity(vec2(0., 500.));
+            world.obstacles.push(obstacle);
+            world.particles.push(Particle::new(vec2(200., 50.), Vec2::ZERO, 5., WHITE));
+            for _ in 0..600 {
+                world.step(TIMESTEP);
+                assert!(world.particles[0].position.y < 250., "{:?}", world.particles[0]);
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_same_run() {
+        let run = |seed| {
+            let mut world = World::new(400., 300.).seed(seed);
+            world.spawn(100, (4., 8.), &[WHITE]);
+            world.update(1.);
+            world.particles
+        };
+        assert_eq!(run(5), run(5));
+        assert_ne!(run(5), run(6));
+    }
+
+    #[test]
+    fn update_steps_fixed_timestep() {
+        let mut world = World::new(400., 300.).timestep(0.01);
+        world.particles.push(Particle::new(vec2(100., 100.), vec2(100., 0.), 5., WHITE));
+        world.update(0.025);
+        assert_close(world.particles[0].position.x, 102.);
+        world.update(0.005);
+        assert_close(world.particles[0].position.x, 103.);
+    }
+}
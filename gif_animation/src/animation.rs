@@ -0,0 +1,71 @@
+// Format independent access to the frames of an animated image
+use image::RgbaImage;
+
+use crate::error::AnimationError;
+use crate::formats::{ApngAnimation, GifAnimation, WebpAnimation};
+
+/// How many times the animation should play before stopping
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopCount {
+    Infinite,    // Loop forever
+    Finite(u32), // Total number of times to play the animation
+}
+
+/// Decoder for the frames of an animated image.
+///
+/// Frames are composited onto a canvas the size of the whole animation and produced in order as
+/// later frames build on the earlier ones. Going back means rewinding and decoding again from the
+/// first frame. Still images are treated as an animation with a single frame.
+pub trait Animation {
+    /// Get the width and height of the canvas in pixels
+    fn dimensions(&self) -> (u32, u32);
+
+    /// Get the delay of every frame in seconds
+    fn delays(&self) -> &[f32];
+
+    /// Get how many times the animation should play according to the file
+    fn loop_count(&self) -> LoopCount;
+
+    /// Decode the next frame returning None after the last one
+    fn next_frame(&mut self) -> Result<Option<RgbaImage>, AnimationError>;
+
+    /// Start decoding again from the first frame
+    fn rewind(&mut self) -> Result<(), AnimationError>;
+
+    /// Get the number of frames
+    fn len(&self) -> usize {
+        self.delays().len()
+    }
+
+    /// Check if there are no frames
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Decode all remaining frames
+    fn frames(&mut self) -> Result<Vec<RgbaImage>, AnimationError> {
+        let mut frames = Vec::with_capacity(self.len());
+        while let Some(frame) = self.next_frame()? {
+            frames.push(frame);
+        }
+        Ok(frames)
+    }
+}
+
+/// Open an animation choosing the backend from the file signature
+///
+/// ```ignore
+/// let bytes = load_file("ferris.webp").await?;
+/// let mut animation = gif_animation::open(bytes)?;
+/// ```
+pub fn open(bytes: Vec<u8>) -> Result<Box<dyn Animation>, AnimationError> {
+    if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Ok(Box::new(GifAnimation::from_bytes(bytes)?))
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Ok(Box::new(ApngAnimation::from_bytes(bytes)?))
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        Ok(Box::new(WebpAnimation::from_bytes(bytes)?))
+    } else {
+        Err(AnimationError::Unsupported)
+    }
+}
@@ -0,0 +1,53 @@
+// Errors that can occur while loading and decoding an animation
+use std::fmt;
+
+use macroquad::file::FileError;
+
+#[derive(Debug)]
+pub enum AnimationError {
+    File(FileError),             // The file couldn't be read
+    Gif(gif::DecodingError),     // The file isn't a valid GIF
+    Dispose(gif_dispose::Error), // A GIF frame couldn't be composited onto the previous ones
+    Image(image::ImageError),    // The file isn't a valid APNG or WebP
+    Unsupported,                 // The file isn't in a supported format
+    NoFrames,                    // The animation doesn't contain any frames
+}
+
+impl fmt::Display for AnimationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnimationError::File(e) => write!(f, "couldn't load file: {}", e),
+            AnimationError::Gif(e) => write!(f, "couldn't decode gif: {}", e),
+            AnimationError::Dispose(e) => write!(f, "couldn't blit frame: {}", e),
+            AnimationError::Image(e) => write!(f, "couldn't decode image: {}", e),
+            AnimationError::Unsupported => write!(f, "unsupported animation format"),
+            AnimationError::NoFrames => write!(f, "animation doesn't contain any frames"),
+        }
+    }
+}
+
+impl std::error::Error for AnimationError {}
+
+impl From<FileError> for AnimationError {
+    fn from(e: FileError) -> Self {
+        AnimationError::File(e)
+    }
+}
+
+impl From<gif::DecodingError> for AnimationError {
+    fn from(e: gif::DecodingError) -> Self {
+        AnimationError::Gif(e)
+    }
+}
+
+impl From<gif_dispose::Error> for AnimationError {
+    fn from(e: gif_dispose::Error) -> Self {
+        AnimationError::Dispose(e)
+    }
+}
+
+impl From<image::ImageError> for AnimationError {
+    fn from(e: image::ImageError) -> Self {
+        AnimationError::Image(e)
+    }
+}
@@ -0,0 +1,94 @@
+// APNG backend using the image crate
+use std::rc::Rc;
+
+use image::codecs::png::PngDecoder;
+use image::{ImageFormat, RgbaImage};
+
+use super::{into_frames, ImageFrames};
+use crate::animation::{Animation, LoopCount};
+use crate::error::AnimationError;
+
+/// Animated PNG. Plain PNG files are a single frame.
+pub struct ApngAnimation {
+    frames: ImageFrames,    // Frame decoder
+    dimensions: (u32, u32), // Width and height of the canvas
+    delays: Vec<f32>,       // Delay of every frame in seconds
+    loop_count: LoopCount,  // How many times to play
+}
+
+impl ApngAnimation {
+    /// Read the frame delays and loop count of the given PNG ready to decode the first frame
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, AnimationError> {
+        let bytes: Rc<[u8]> = bytes.into();
+        let (delays, loop_count) = Self::scan(&bytes);
+        let animated = !delays.is_empty();
+        let frames = ImageFrames::new(bytes, ImageFormat::Png, animated, |reader| {
+            into_frames(PngDecoder::new(reader).map(|x| x.apng()))
+        });
+        Ok(Self {
+            dimensions: frames.dimensions()?,
+            frames,
+            delays: if animated { delays } else { vec![0.] },
+            loop_count,
+        })
+    }
+
+    // The image crate doesn't expose the animation control chunks until each frame is decoded so
+    // walk the chunks directly. acTL holds the number of plays with 0 looping forever and every
+    // fcTL the delay of a frame as a fraction of a second with a denominator of 0 meaning 100.
+    fn scan(bytes: &[u8]) -> (Vec<f32>, LoopCount) {
+        let mut delays = Vec::new();
+        let mut loop_count = LoopCount::Finite(1);
+        let mut pos = 8;
+        while let Some(header) = bytes.get(pos..pos + 8) {
+            let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+            let data = match bytes.get(pos + 8..pos + 8 + len) {
+                Some(data) => data,
+                None => break,
+            };
+            match &header[4..8] {
+                b"acTL" if len >= 8 => {
+                    loop_count = match u32::from_be_bytes([data[4], data[5], data[6], data[7]]) {
+                        0 => LoopCount::Infinite,
+                        n => LoopCount::Finite(n),
+                    };
+                },
+                b"fcTL" if len >= 24 => {
+                    let num = u16::from_be_bytes([data[20], data[21]]) as f32;
+                    let den = match u16::from_be_bytes([data[22], data[23]]) {
+                        0 => 100.,
+                        n => n as f32,
+                    };
+                    delays.push(num / den);
+                },
+                b"IEND" => break,
+                _ => {},
+            }
+            pos += len + 12;
+        }
+        (delays, loop_count)
+    }
+}
+
+impl Animation for ApngAnimation {
+    fn dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+
+    fn delays(&self) -> &[f32] {
+        &self.delays
+    }
+
+    fn loop_count(&self) -> LoopCount {
+        self.loop_count
+    }
+
+    fn next_frame(&mut self) -> Result<Option<RgbaImage>, AnimationError> {
+        self.frames.next_frame()
+    }
+
+    fn rewind(&mut self) -> Result<(), AnimationError> {
+        self.frames.rewind();
+        Ok(())
+    }
+}
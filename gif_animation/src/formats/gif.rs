@@ -0,0 +1,145 @@
+// GIF backend using the gif and gif-dispose crates
+use std::io::Cursor;
+use std::rc::Rc;
+
+use image::RgbaImage;
+use rgb::ComponentBytes;
+
+use crate::animation::{Animation, LoopCount};
+use crate::error::AnimationError;
+
+type Decoder = gif::Decoder<Cursor<Rc<[u8]>>>;
+
+/// Animated GIF
+pub struct GifAnimation {
+    bytes: Rc<[u8]>,             // File to restart decoding from
+    decoder: Decoder,            // Decoder positioned at the next frame
+    screen: gif_dispose::Screen, // Canvas with all frames so far composited
+    delays: Vec<f32>,            // Delay of every frame in seconds
+    loop_count: LoopCount,       // How many times to play
+}
+
+impl GifAnimation {
+    /// Read the frame delays and loop count of the given GIF ready to decode the first frame
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, AnimationError> {
+        let bytes: Rc<[u8]> = bytes.into();
+        let (decoder, screen) = Self::decoder(&bytes)?;
+        let (delays, loop_count) = Self::scan(&bytes);
+        Ok(Self { bytes, decoder, screen, delays, loop_count })
+    }
+
+    // Create a decoder and canvas positioned at the first frame
+    fn decoder(bytes: &Rc<[u8]>) -> Result<(Decoder, gif_dispose::Screen), AnimationError> {
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let decoder = options.read_info(Cursor::new(bytes.clone()))?;
+        let screen = gif_dispose::Screen::new_decoder(&decoder);
+        Ok((decoder, screen))
+    }
+
+    // The gif crate doesn't expose the NETSCAPE2.0 application extension and only gives the delays while
+    // decoding each frame so walk the blocks directly skipping over the image data. The loop count comes from
+    // an application extension before the first image: without it the animation plays once, a value of 0 loops
+    // forever and any other value is the number of times to repeat after the first play like browsers do. Each
+    // image takes the delay of the graphic control extension before it in hundredths of a second.
+    fn scan(bytes: &[u8]) -> (Vec<f32>, LoopCount) {
+        let mut delays = Vec::new();
+        let mut loop_count = LoopCount::Finite(1);
+        let mut delay = 0;
+
+        // Header and logical screen descriptor followed by the global color table
+        let flags = bytes.get(10).copied().unwrap_or(0);
+        let mut pos = 13 + color_table_len(flags);
+        loop {
+            match bytes.get(pos..pos + 2) {
+                // Extension with its label followed by data sub-blocks
+                Some(&[0x21, label]) => {
+                    let data = bytes.get(pos + 3..pos + 3 + bytes.get(pos + 2).copied().unwrap_or(0) as usize);
+                    match (label, data) {
+                        (0xF9, Some(data)) if data.len() >= 3 => delay = u16::from_le_bytes([data[1], data[2]]),
+                        (0xFF, Some(b"NETSCAPE2.0" | b"ANIMEXTS1.0")) if delays.is_empty() => {
+                            if let Some(&[0x03, 0x01, low, high]) = bytes.get(pos + 14..pos + 18) {
+                                loop_count = match u16::from_le_bytes([low, high]) {
+                                    0 => LoopCount::Infinite,
+                                    n => LoopCount::Finite(n as u32 + 1),
+                                };
+                            }
+                        },
+                        _ => (),
+                    }
+                    pos = match skip_sub_blocks(bytes, pos + 2) {
+                        Some(pos) => pos,
+                        None => break,
+                    };
+                },
+
+                // Image descriptor, local color table and LZW code size followed by the image data sub-blocks
+                Some(&[0x2C, _]) => {
+                    let flags = match bytes.get(pos + 9) {
+                        Some(flags) => *flags,
+                        None => break,
+                    };
+                    pos = match skip_sub_blocks(bytes, pos + 11 + color_table_len(flags)) {
+                        Some(pos) => pos,
+                        None => break,
+                    };
+                    delays.push(delay as f32 / 100.);
+                    delay = 0;
+                },
+
+                // Trailer, truncated file or garbage the decoder will report
+                _ => break,
+            }
+        }
+        (delays, loop_count)
+    }
+}
+
+// Size in bytes of the color table following a descriptor with the given flags
+fn color_table_len(flags: u8) -> usize {
+    match flags & 0x80 {
+        0 => 0,
+        _ => 3 << ((flags & 0x07) + 1),
+    }
+}
+
+// Get the position after the data sub-blocks starting at the given position
+fn skip_sub_blocks(bytes: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        match *bytes.get(pos)? {
+            0 => return Some(pos + 1),
+            len => pos += len as usize + 1,
+        }
+    }
+}
+
+impl Animation for GifAnimation {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.decoder.width() as u32, self.decoder.height() as u32)
+    }
+
+    fn delays(&self) -> &[f32] {
+        &self.delays
+    }
+
+    fn loop_count(&self) -> LoopCount {
+        self.loop_count
+    }
+
+    fn next_frame(&mut self) -> Result<Option<RgbaImage>, AnimationError> {
+        let frame = match self.decoder.read_next_frame()? {
+            Some(frame) => frame,
+            None => return Ok(None),
+        };
+        self.screen.blit_frame(frame)?;
+        let (pixels, width, height) = self.screen.pixels.as_contiguous_buf();
+        Ok(RgbaImage::from_raw(width as u32, height as u32, pixels.as_bytes().to_vec()))
+    }
+
+    fn rewind(&mut self) -> Result<(), AnimationError> {
+        let (decoder, screen) = Self::decoder(&self.bytes)?;
+        self.decoder = decoder;
+        self.screen = screen;
+        Ok(())
+    }
+}
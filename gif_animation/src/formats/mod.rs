@@ -0,0 +1,67 @@
+// Animation backends for each supported file format
+use std::io::Cursor;
+use std::rc::Rc;
+
+use image::{AnimationDecoder, Frames, ImageFormat, ImageResult, RgbaImage};
+
+use crate::error::AnimationError;
+
+mod apng;
+mod gif;
+mod webp;
+pub use self::apng::ApngAnimation;
+pub use self::gif::GifAnimation;
+pub use self::webp::WebpAnimation;
+
+type Reader = Cursor<Rc<[u8]>>;
+type FrameDecoder = fn(Reader) -> ImageResult<Frames<'static>>;
+
+/// Frames decoded by the image crate for formats it can animate. Files without animation yield
+/// their still image as the only frame as the image crate's frame iterators are empty for them.
+struct ImageFrames {
+    bytes: Rc<[u8]>,                 // File to restart decoding from
+    format: ImageFormat,             // Format of the file
+    animated: bool,                  // Whether the file contains an animation
+    decoder: FrameDecoder,           // Creates the frame iterator
+    frames: Option<Frames<'static>>, // Frame iterator positioned at the next frame
+    done: bool,                      // Whether the still image has been produced
+}
+
+impl ImageFrames {
+    fn new(bytes: Rc<[u8]>, format: ImageFormat, animated: bool, decoder: FrameDecoder) -> Self {
+        Self { bytes, format, animated, decoder, frames: None, done: false }
+    }
+
+    // Width and height of the canvas
+    fn dimensions(&self) -> Result<(u32, u32), AnimationError> {
+        let reader = image::io::Reader::with_format(Cursor::new(&self.bytes[..]), self.format);
+        Ok(reader.into_dimensions()?)
+    }
+
+    fn next_frame(&mut self) -> Result<Option<RgbaImage>, AnimationError> {
+        if !self.animated {
+            if self.done {
+                return Ok(None);
+            }
+            self.done = true;
+            return Ok(Some(image::load_from_memory_with_format(&self.bytes, self.format)?.into_rgba8()));
+        }
+        if self.frames.is_none() {
+            self.frames = Some((self.decoder)(Cursor::new(self.bytes.clone()))?);
+        }
+        match self.frames.as_mut().and_then(|x| x.next()) {
+            Some(frame) => Ok(Some(frame?.into_buffer())),
+            None => Ok(None),
+        }
+    }
+
+    fn rewind(&mut self) {
+        self.frames = None;
+        self.done = false;
+    }
+}
+
+// Wrap a decoder of the image crate into a frame iterator
+fn into_frames<'a, D: AnimationDecoder<'a>>(decoder: ImageResult<D>) -> ImageResult<Frames<'a>> {
+    Ok(decoder?.into_frames())
+}
@@ -0,0 +1,91 @@
+// Animated WebP backend using the image crate
+use std::rc::Rc;
+
+use image::codecs::webp::WebPDecoder;
+use image::{ImageFormat, RgbaImage};
+
+use super::{into_frames, ImageFrames};
+use crate::animation::{Animation, LoopCount};
+use crate::error::AnimationError;
+
+/// Animated WebP. Still WebP files are a single frame.
+pub struct WebpAnimation {
+    frames: ImageFrames,    // Frame decoder
+    dimensions: (u32, u32), // Width and height of the canvas
+    delays: Vec<f32>,       // Delay of every frame in seconds
+    loop_count: LoopCount,  // How many times to play
+}
+
+impl WebpAnimation {
+    /// Read the frame delays and loop count of the given WebP ready to decode the first frame
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, AnimationError> {
+        let bytes: Rc<[u8]> = bytes.into();
+        let (delays, loop_count) = Self::scan(&bytes);
+        let animated = !delays.is_empty();
+        let frames = ImageFrames::new(bytes, ImageFormat::WebP, animated, |reader| {
+            into_frames(WebPDecoder::new(reader))
+        });
+        Ok(Self {
+            dimensions: frames.dimensions()?,
+            frames,
+            delays: if animated { delays } else { vec![0.] },
+            loop_count,
+        })
+    }
+
+    // The image crate doesn't expose the animation chunks so walk the RIFF chunks directly. ANIM
+    // holds the loop count with 0 looping forever and every ANMF the duration of a frame in
+    // milliseconds as a 24 bit value after the frame's offset and size.
+    fn scan(bytes: &[u8]) -> (Vec<f32>, LoopCount) {
+        let mut delays = Vec::new();
+        let mut loop_count = LoopCount::Finite(1);
+        let mut pos = 12;
+        while let Some(header) = bytes.get(pos..pos + 8) {
+            let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+            let data = match bytes.get(pos + 8..pos + 8 + len) {
+                Some(data) => data,
+                None => break,
+            };
+            match &header[0..4] {
+                b"ANIM" if len >= 6 => {
+                    loop_count = match u16::from_le_bytes([data[4], data[5]]) {
+                        0 => LoopCount::Infinite,
+                        n => LoopCount::Finite(n as u32),
+                    };
+                },
+                b"ANMF" if len >= 15 => {
+                    let duration = u32::from_le_bytes([data[12], data[13], data[14], 0]);
+                    delays.push(duration as f32 / 1000.);
+                },
+                _ => {},
+            }
+
+            // Chunks are padded to an even size
+            pos += 8 + len + (len & 1);
+        }
+        (delays, loop_count)
+    }
+}
+
+impl Animation for WebpAnimation {
+    fn dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+
+    fn delays(&self) -> &[f32] {
+        &self.delays
+    }
+
+    fn loop_count(&self) -> LoopCount {
+        self.loop_count
+    }
+
+    fn next_frame(&mut self) -> Result<Option<RgbaImage>, AnimationError> {
+        self.frames.next_frame()
+    }
+
+    fn rewind(&mut self) -> Result<(), AnimationError> {
+        self.frames.rewind();
+        Ok(())
+    }
+}
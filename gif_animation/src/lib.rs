@@ -0,0 +1,16 @@
+//! Decode animated GIF, APNG and WebP images frame by frame through the common `Animation` trait
+//!
+//! ### Example
+//! ```ignore
+//! let mut animation = gif_animation::open(bytes)?;
+//! while let Some(frame) = animation.next_frame()? {
+//!     ...
+//! }
+//! ```
+mod animation;
+mod error;
+mod formats;
+
+pub use animation::{open, Animation, LoopCount};
+pub use error::AnimationError;
+pub use formats::{ApngAnimation, GifAnimation, WebpAnimation};
@@ -1,6 +1,14 @@
-// Display an animated GIF with macroquad
+// Display an animated GIF, APNG or WebP with macroquad
+use gif_animation::{Animation, AnimationError, LoopCount};
 use macroquad::prelude::*;
-use rgb::ComponentBytes;
+
+mod stream;
+use stream::FrameStream;
+
+// Browsers show frames with a delay of 10ms or less for 100ms instead as many GIFs in the wild were
+// authored with a zero delay assuming that behavior.
+const MIN_DELAY: f32 = 0.02;
+const CLAMPED_DELAY: f32 = 0.1;
 
 /// A single frame in the animation
 #[derive(Debug)]
@@ -9,24 +17,84 @@ pub struct Frame {
     delay: f32,         // How many seconds the frame should show before advancing
 }
 
-// All data for displaying an animated gif using Macroquad
+impl Frame {
+    /// Decode the remaining frames of the given animation into textures
+    fn decode_all(animation: &mut dyn Animation) -> Result<Vec<Frame>, AnimationError> {
+        let mut frames: Vec<Frame> = Vec::new();
+        for (img, delay) in animation.frames()?.into_iter().zip(animation.delays()) {
+            frames.push(Frame {
+                texture: Texture2D::from_rgba8(img.width() as u16, img.height() as u16, img.as_raw()),
+                delay: *delay,
+            });
+        }
+        Ok(frames)
+    }
+}
+
+/// Frames either all decoded up front or streamed on demand
+enum Frames {
+    Decoded(Vec<Frame>),
+    Streamed(Box<FrameStream>),
+}
+
+impl Frames {
+    fn len(&self) -> usize {
+        match self {
+            Frames::Decoded(frames) => frames.len(),
+            Frames::Streamed(stream) => stream.len(),
+        }
+    }
+
+    fn delay(&self, frame: usize) -> f32 {
+        match self {
+            Frames::Decoded(frames) => frames[frame].delay,
+            Frames::Streamed(stream) => stream.delay(frame),
+        }
+    }
+
+    fn texture(&self, frame: usize) -> Option<Texture2D> {
+        match self {
+            Frames::Decoded(frames) => frames.get(frame).map(|x| x.texture),
+            Frames::Streamed(stream) => stream.texture(frame),
+        }
+    }
+}
+
+/// Order in which the frames are played
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayMode {
+    Forward,  // First to last frame
+    Reverse,  // Last to first frame
+    PingPong, // First to last frame then back again
+}
+
+// All data for displaying an animated gif, apng or webp using Macroquad
 pub struct Gif {
-    frames: Vec<Frame>, // Frames to show
+    frames: Frames, // Frames to show
     pub width: u16,
     pub height: u16,
     current_frame: usize,
     elapsed_time: f32,
     paused: bool,
+    loop_count: LoopCount,                 // How many times to play before finishing
+    loops: u32,                            // Number of times the animation has played so far
+    mode: PlayMode,                        // Order to play the frames in
+    backwards: bool,                       // Whether playback is currently moving backwards
+    speed: f32,                            // Playback speed multiplier
+    min_delay: f32,                        // Delays below this are clamped like browsers do
+    finished: bool,                        // Whether a finite animation has played to completion
+    on_complete: Option<Box<dyn FnMut()>>, // Called once a finite animation finishes
+    error: Option<AnimationError>,               // Error that stopped a streamed animation
 }
 
 impl Gif {
-    /// Load and decode a GIF file using Macroquad
+    /// Load and decode a GIF, APNG or WebP file using Macroquad
     ///
     /// ```rust
-    /// let mut gif = Gif::load("filename.gif").await;
+    /// let mut gif = Gif::load("filename.gif").await?;
     /// ```
-    pub async fn load(filename: String) -> Self {
-        let file_bytes = load_file(&filename).await.expect("Couldn't load file");
+    pub async fn load(filename: String) -> Result<Self, AnimationError> {
+        let file_bytes = load_file(&filename).await?;
         Self::from_bytes(&file_bytes)
     }
 
@@ -34,41 +102,124 @@ impl Gif {
     ///
     /// ```rust
     /// let bytes: [u8] = ...
-    /// let mut gif = Gif::from_bytes(&bytes);
+    /// let mut gif = Gif::from_bytes(&bytes)?;
+    /// ```
+    pub fn from_bytes(file_bytes: &[u8]) -> Result<Gif, AnimationError> {
+        Self::from_animation(gif_animation::open(file_bytes.to_vec())?)
+    }
+
+    /// Instantiate a new `Gif` decoding all frames of the given animation up front
+    ///
+    /// ```rust
+    /// let animation = gif_animation::open(bytes)?;
+    /// let mut gif = Gif::from_animation(animation)?;
+    /// ```
+    pub fn from_animation(mut animation: Box<dyn Animation>) -> Result<Gif, AnimationError> {
+        let frames = Frame::decode_all(animation.as_mut())?;
+        if frames.is_empty() {
+            return Err(AnimationError::NoFrames);
+        }
+        let (width, height) = animation.dimensions();
+        Ok(Self::new(Frames::Decoded(frames), width, height, animation.loop_count()))
+    }
+
+    /// Load a GIF, APNG or WebP file using Macroquad decoding frames on demand during playback rather than up
+    /// front, keeping at most `capacity` frames in GPU memory at a time. Frames can only be decoded in order so
+    /// all of them are decoded once a play mode going backwards is chosen.
+    ///
+    /// ```rust
+    /// let mut gif = Gif::load_streaming("filename.gif", 16).await?;
+    /// ```
+    pub async fn load_streaming(filename: String, capacity: usize) -> Result<Self, AnimationError> {
+        let file_bytes = load_file(&filename).await?;
+        Self::from_bytes_streaming(file_bytes, capacity)
+    }
+
+    /// Instantiate a new streaming `Gif` from bytes keeping at most `capacity` frames in GPU
+    /// memory at a time.
+    ///
+    /// ```rust
+    /// let bytes: Vec<u8> = ...
+    /// let mut gif = Gif::from_bytes_streaming(bytes, 16)?;
     /// ```
-    pub fn from_bytes(file_bytes: &[u8]) -> Gif {
-        let (frames, width, height) = Self::decode_gif(&file_bytes);
+    pub fn from_bytes_streaming(file_bytes: Vec<u8>, capacity: usize) -> Result<Gif, AnimationError> {
+        let animation = gif_animation::open(file_bytes)?;
+        let (width, height) = animation.dimensions();
+        let loop_count = animation.loop_count();
+        let stream = FrameStream::new(animation, capacity)?;
+        Ok(Self::new(Frames::Streamed(Box::new(stream)), width, height, loop_count))
+    }
+
+    // Create a new animation with default playback settings
+    fn new(frames: Frames, width: u32, height: u32, loop_count: LoopCount) -> Self {
         Self {
             frames,
-            width,
-            height,
+            width: width as u16,
+            height: height as u16,
             current_frame: 0,
             elapsed_time: 0.,
             paused: false,
+            loop_count,
+            loops: 0,
+            mode: PlayMode::Forward,
+            backwards: false,
+            speed: 1.,
+            min_delay: MIN_DELAY,
+            finished: false,
+            on_complete: None,
+            error: None,
         }
     }
 
-    // Use the gif and gif-dispose crates to decode the gif
-    fn decode_gif(file: &[u8]) -> (Vec<Frame>, u16, u16) {
-        let mut options = gif::DecodeOptions::new();
-        options.set_color_output(gif::ColorOutput::Indexed);
-        let mut decoder = options.read_info(&*file).unwrap();
-        let mut screen = gif_dispose::Screen::new_decoder(&decoder);
+    /// Override the loop count read from the file
+    ///
+    /// ```rust
+    /// let mut gif = Gif::load("filename.gif").await.loop_count(LoopCount::Finite(3));
+    /// ```
+    pub fn loop_count(mut self, value: LoopCount) -> Self {
+        self.loop_count = value;
+        self
+    }
 
-        let mut frames: Vec<Frame> = Vec::new();
-        while let Some(frame) = decoder.read_next_frame().unwrap() {
-            screen.blit_frame(&frame).expect("Couldn't blit frame");
-            let (pixels, frame_width, frame_height) = screen.pixels.as_contiguous_buf();
-            frames.push(Frame {
-                texture: Texture2D::from_rgba8(
-                    frame_width as u16,
-                    frame_height as u16,
-                    pixels.as_bytes(),
-                ),
-                delay: frame.delay as f32 / 100.,
-            });
-        }
-        (frames, decoder.width(), decoder.height())
+    /// Set the order to play the frames in
+    ///
+    /// ```rust
+    /// let mut gif = Gif::load("filename.gif").await.mode(PlayMode::PingPong);
+    /// ```
+    pub fn mode(mut self, value: PlayMode) -> Self {
+        self.set_mode(value);
+        self
+    }
+
+    /// Set the playback speed multiplier e.g. 2.0 for double speed
+    ///
+    /// ```rust
+    /// let mut gif = Gif::load("filename.gif").await.speed(2.0);
+    /// ```
+    pub fn speed(mut self, value: f32) -> Self {
+        self.set_speed(value);
+        self
+    }
+
+    /// Set the delay in seconds below which frames are shown for 100ms instead. Use 0.0 to
+    /// disable the clamping and honor the delays as written.
+    ///
+    /// ```rust
+    /// let mut gif = Gif::load("filename.gif").await.min_delay(0.0);
+    /// ```
+    pub fn min_delay(mut self, value: f32) -> Self {
+        self.min_delay = value;
+        self
+    }
+
+    /// Set a callback to be called once a finite animation has played to completion
+    ///
+    /// ```rust
+    /// let mut gif = Gif::load("filename.gif").await.on_complete(|| println!("done"));
+    /// ```
+    pub fn on_complete(mut self, callback: impl FnMut() + 'static) -> Self {
+        self.on_complete = Some(Box::new(callback));
+        self
     }
 
     fn pos_x(&self) -> f32 {
@@ -94,7 +245,9 @@ impl Gif {
     /// gif_animation.draw_at(42.0, 47.0);
     /// ```
     pub fn draw_at(&self, pos_x: f32, pos_y: f32) {
-        draw_texture(self.frame().texture, pos_x, pos_y, WHITE);
+        if let Some(texture) = self.frames.texture(self.current_frame) {
+            draw_texture(texture, pos_x, pos_y, WHITE);
+        }
     }
 
     /// Update method that needs to be called in the loop to advance to next frame
@@ -104,12 +257,32 @@ impl Gif {
     /// gif_animation.tick();
     /// ```
     pub fn tick(&mut self) {
-        if !self.paused {
-            self.elapsed_time += get_frame_time();
-            if self.elapsed_time > self.frame().delay {
-                self.advance_frame();
+        self.update(get_frame_time());
+    }
+
+    /// Advance the animation by the given number of seconds. Time left over after a frame's
+    /// delay is carried over to the next frame so that long animations don't drift.
+    ///
+    /// ```rust
+    /// gif_animation.update(1.0 / 60.0);
+    /// ```
+    pub fn update(&mut self, delta: f32) {
+        if self.paused || self.finished {
+            return;
+        }
+        self.elapsed_time += delta * self.speed;
+        let mut steps = 0;
+        while self.elapsed_time >= self.delay(self.current_frame) {
+            self.elapsed_time -= self.delay(self.current_frame);
+            steps += 1;
+
+            // Drop time left over from long stalls rather than spinning through whole loops
+            if !self.advance_frame() || steps > self.frames.len() {
+                self.elapsed_time = 0.;
+                break;
             }
         }
+        self.decode_ahead();
     }
 
     /// Toggle whether the animation should be playing or be paused.
@@ -121,33 +294,294 @@ impl Gif {
         self.paused ^= true;
     }
 
-    fn frame(&self) -> &Frame {
-        self.frames.get(self.current_frame).unwrap()
+    /// Change the order to play the frames in
+    ///
+    /// ```rust
+    /// gif_animation.set_mode(PlayMode::Reverse);
+    /// ```
+    pub fn set_mode(&mut self, value: PlayMode) {
+        self.mode = value;
+        self.backwards = value == PlayMode::Reverse;
+        if value != PlayMode::Forward {
+            self.decode_all();
+        }
+
+        // Reversing from the first frame would count a whole loop without showing any other frame
+        if self.backwards && self.current_frame == 0 {
+            self.seek(self.frames.len() - 1);
+        }
+    }
+
+    /// Change the playback speed multiplier
+    ///
+    /// ```rust
+    /// gif_animation.set_speed(0.5);
+    /// ```
+    pub fn set_speed(&mut self, value: f32) {
+        self.speed = value.max(0.);
+    }
+
+    /// Get the order the frames are played in
+    pub fn get_mode(&self) -> PlayMode {
+        self.mode
+    }
+
+    /// Get the playback speed multiplier
+    pub fn get_speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Get the index of the frame currently being shown
+    pub fn current_frame(&self) -> usize {
+        self.current_frame
+    }
+
+    /// Get the number of frames in the animation
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Get the duration of a single play through of the frames in seconds
+    pub fn duration(&self) -> f32 {
+        (0..self.frames.len()).map(|i| self.delay(i)).sum()
+    }
+
+    /// Check if a finite animation has played to completion
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Get the error that stopped a streamed animation if any
+    pub fn error(&self) -> Option<&AnimationError> {
+        self.error.as_ref()
     }
 
-    /// Advance the frame restarting when it hits the end
-    fn advance_frame(&mut self) {
-        self.current_frame = if self.current_frame == self.frames.len() - 1 {
-            0
+    /// Jump to the given frame resetting the time shown for it
+    ///
+    /// ```rust
+    /// gif_animation.seek(0);
+    /// ```
+    pub fn seek(&mut self, frame: usize) {
+        self.current_frame = frame.min(self.frames.len() - 1);
+        self.elapsed_time = 0.;
+        self.finished = self.error.is_some();
+        self.decode_ahead();
+    }
+
+    /// Jump to the frame that would be showing the given number of seconds into a play through
+    /// of the frames in order, wrapping around for times past the end.
+    ///
+    /// ```rust
+    /// gif_animation.seek_time(1.5);
+    /// ```
+    pub fn seek_time(&mut self, time: f32) {
+        let mut time = time.max(0.) % self.duration().max(f32::EPSILON);
+        for i in 0..self.frames.len() {
+            let delay = self.delay(i);
+            if time < delay || i == self.frames.len() - 1 {
+                self.seek(i);
+                self.elapsed_time = time.min(delay);
+                return;
+            }
+            time -= delay;
+        }
+    }
+
+    /// Restart the animation from the beginning including the loop count
+    ///
+    /// ```rust
+    /// gif_animation.restart();
+    /// ```
+    pub fn restart(&mut self) {
+        self.loops = 0;
+        self.backwards = self.mode == PlayMode::Reverse;
+        self.seek(if self.backwards { self.frames.len() - 1 } else { 0 });
+    }
+
+    // Make sure the current frame is decoded when streaming and decode ahead of it. Errors stop
+    // playback as the remaining frames can't be shown.
+    fn decode_ahead(&mut self) {
+        if let Frames::Streamed(stream) = &mut self.frames {
+            let result = stream.ensure(self.current_frame).and_then(|_| stream.prefetch(self.current_frame));
+            if let Err(e) = result {
+                self.error = Some(e);
+                self.finished = true;
+            }
+        }
+    }
+
+    // Switch from streaming to all frames decoded up front as every step backwards past the buffered frames
+    // would decode again from the first frame. Errors stop playback as they do while streaming.
+    fn decode_all(&mut self) {
+        if let Frames::Streamed(stream) = &mut self.frames {
+            match stream.decode_all() {
+                Ok(frames) => self.frames = Frames::Decoded(frames),
+                Err(e) => {
+                    self.error = Some(e);
+                    self.finished = true;
+                },
+            }
+        }
+    }
+
+    // Delay of the given frame in seconds clamping short delays like browsers do
+    fn delay(&self, frame: usize) -> f32 {
+        let delay = self.frames.delay(frame);
+        if delay < self.min_delay {
+            CLAMPED_DELAY
         } else {
-            self.current_frame + 1
+            delay
+        }
+    }
+
+    /// Advance the frame according to the play mode restarting when it hits the end. Returns
+    /// false once a finite animation has finished.
+    fn advance_frame(&mut self) -> bool {
+        let last = self.frames.len() - 1;
+        let at_end = if self.backwards { self.current_frame == 0 } else { self.current_frame == last };
+        if !at_end {
+            self.current_frame = if self.backwards { self.current_frame - 1 } else { self.current_frame + 1 };
+            return true;
+        }
+
+        // Ping pong turns around at the last frame and completes a loop back at the first
+        if self.mode == PlayMode::PingPong && !self.backwards && last > 0 {
+            self.backwards = true;
+            self.current_frame -= 1;
+            return true;
+        }
+
+        self.loops += 1;
+        if let LoopCount::Finite(count) = self.loop_count {
+            if self.loops >= count {
+                self.finished = true;
+                if let Some(callback) = self.on_complete.as_mut() {
+                    callback();
+                }
+                return false;
+            }
+        }
+        self.current_frame = match self.mode {
+            PlayMode::Forward => 0,
+            PlayMode::Reverse => last,
+            PlayMode::PingPong => {
+                self.backwards = false;
+                1.min(last)
+            },
         };
-        self.elapsed_time = 0.0;
+        true
     }
 }
 
 #[macroquad::main("GIF Animation example")]
 async fn main() {
-    let mut animation = Gif::load("assets/ferris.gif".to_string()).await;
+    // Pass a GIF, APNG or WebP file to play instead of the default and --stream to decode frames
+    // during playback instead of all up front
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let filename = args.iter().find(|x| !x.starts_with("--")).cloned();
+    let filename = filename.unwrap_or_else(|| "assets/ferris.gif".to_string());
+    let animation = match args.iter().any(|x| x == "--stream") {
+        true => Gif::load_streaming(filename, 16).await,
+        false => Gif::load(filename).await,
+    };
+    let mut animation = match animation {
+        Ok(animation) => animation.on_complete(|| println!("Animation finished")),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return;
+        },
+    };
     loop {
         if is_key_pressed(KeyCode::Space) {
             animation.toggle_paused();
         }
 
+        // Cycle through the play modes
+        if is_key_pressed(KeyCode::M) {
+            animation.set_mode(match animation.get_mode() {
+                PlayMode::Forward => PlayMode::Reverse,
+                PlayMode::Reverse => PlayMode::PingPong,
+                PlayMode::PingPong => PlayMode::Forward,
+            });
+        }
+
+        // Change the speed or step through the frames
+        if is_key_pressed(KeyCode::Up) {
+            animation.set_speed(animation.get_speed() * 2.);
+        }
+        if is_key_pressed(KeyCode::Down) {
+            animation.set_speed(animation.get_speed() / 2.);
+        }
+        if is_key_pressed(KeyCode::Right) {
+            animation.seek((animation.current_frame() + 1) % animation.frame_count());
+        }
+        if is_key_pressed(KeyCode::Left) {
+            let count = animation.frame_count();
+            animation.seek((animation.current_frame() + count - 1) % count);
+        }
+        if is_key_pressed(KeyCode::Home) {
+            animation.restart();
+        }
+
         clear_background(WHITE);
         animation.draw();
         animation.tick();
 
+        let status = format!(
+            "Frame {}/{}  {:?}  {}x  (Space: pause, M: mode, Up/Down: speed, Left/Right: step, Home: restart)",
+            animation.current_frame() + 1,
+            animation.frame_count(),
+            animation.get_mode(),
+            animation.get_speed(),
+        );
+        draw_text(&status, 10., 20., 20., DARKGRAY);
+        if let Some(e) = animation.error() {
+            draw_text(&format!("Error: {}", e), 10., 40., 20., RED);
+        }
+
         next_frame().await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Animation of the given number of frames showing each for 100ms without needing a window
+    fn animation(count: usize, loop_count: LoopCount) -> Gif {
+        let frames = (0..count).map(|_| Frame { texture: Texture2D::empty(), delay: 0.1 }).collect();
+        Gif::new(Frames::Decoded(frames), 1, 1, loop_count)
+    }
+
+    // Step through the animation until it finishes returning the frames shown in order
+    fn play(gif: &mut Gif) -> Vec<usize> {
+        let mut shown = vec![gif.current_frame()];
+        while !gif.is_finished() && shown.len() < 100 {
+            gif.update(0.1);
+            if !gif.is_finished() {
+                shown.push(gif.current_frame());
+            }
+        }
+        shown
+    }
+
+    #[test]
+    fn test_reverse_plays_every_frame() {
+        let mut gif = animation(4, LoopCount::Finite(1)).mode(PlayMode::Reverse);
+        assert_eq!(play(&mut gif), vec![3, 2, 1, 0]);
+
+        // Switching to reverse while showing the first frame
+        let mut gif = animation(4, LoopCount::Finite(1));
+        gif.set_mode(PlayMode::Reverse);
+        assert_eq!(play(&mut gif), vec![3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn test_forward_and_ping_pong() {
+        let mut gif = animation(4, LoopCount::Finite(2));
+        assert_eq!(play(&mut gif), vec![0, 1, 2, 3, 0, 1, 2, 3]);
+
+        let mut gif = animation(3, LoopCount::Finite(1)).mode(PlayMode::PingPong);
+        assert_eq!(play(&mut gif), vec![0, 1, 2, 1, 0]);
+    }
+}
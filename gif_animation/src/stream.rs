@@ -0,0 +1,108 @@
+// Decode animation frames on demand keeping only a bounded number of textures in memory
+use std::collections::VecDeque;
+
+use gif_animation::{Animation, AnimationError};
+use macroquad::prelude::*;
+
+use crate::Frame;
+
+/// Streaming frame source for long or large animations.
+///
+/// Frames are decoded as playback reaches them into a ring buffer of textures. Frames build on
+/// the previous ones so stepping back past the buffered frames means decoding again from the
+/// first frame; forward playback is cheap while reverse playback of long animations is not and
+/// should use [`FrameStream::decode_all`] instead.
+pub struct FrameStream {
+    animation: Box<dyn Animation>,        // Decoder positioned at frame `next`
+    next: usize,                          // Index of the next frame the decoder will produce
+    buffer: VecDeque<(usize, Texture2D)>, // Decoded frames oldest first
+    capacity: usize,                      // Maximum number of textures to keep
+}
+
+impl FrameStream {
+    /// Create a new stream keeping at most `capacity` textures, decoding the first frame
+    pub fn new(animation: Box<dyn Animation>, capacity: usize) -> Result<Self, AnimationError> {
+        if animation.is_empty() {
+            return Err(AnimationError::NoFrames);
+        }
+        let mut stream = Self {
+            animation,
+            next: 0,
+            buffer: VecDeque::new(),
+            capacity: capacity.max(2),
+        };
+        stream.ensure(0)?;
+        Ok(stream)
+    }
+
+    /// Get the number of frames in the animation
+    pub fn len(&self) -> usize {
+        self.animation.len()
+    }
+
+    /// Get the delay of the given frame in seconds
+    pub fn delay(&self, frame: usize) -> f32 {
+        self.animation.delays()[frame]
+    }
+
+    /// Get the texture for the given frame if it is currently buffered
+    pub fn texture(&self, frame: usize) -> Option<Texture2D> {
+        self.buffer.iter().find(|(i, _)| *i == frame).map(|(_, texture)| *texture)
+    }
+
+    /// Make sure the given frame is buffered decoding it if needed
+    pub fn ensure(&mut self, frame: usize) -> Result<(), AnimationError> {
+        if self.texture(frame).is_some() {
+            return Ok(());
+        }
+        if frame < self.next {
+            self.animation.rewind()?;
+            self.next = 0;
+        }
+        while self.next <= frame {
+            self.decode_next()?;
+        }
+        Ok(())
+    }
+
+    /// Decode at most one frame ahead of the given frame, filling up to half the buffer so that
+    /// recently shown frames are kept for stepping back.
+    pub fn prefetch(&mut self, frame: usize) -> Result<(), AnimationError> {
+        let ahead = self.buffer.iter().filter(|(i, _)| *i > frame).count();
+        if self.next < self.len() && self.next > frame && ahead < self.capacity / 2 {
+            self.decode_next()?;
+        }
+        Ok(())
+    }
+
+    /// Decode every frame from the first one up front for playing backwards
+    pub fn decode_all(&mut self) -> Result<Vec<Frame>, AnimationError> {
+        self.animation.rewind()?;
+        self.next = 0;
+        let frames = Frame::decode_all(self.animation.as_mut())?;
+        self.next = frames.len();
+        Ok(frames)
+    }
+
+    // Decode the next frame into the buffer evicting the oldest frame when full
+    fn decode_next(&mut self) -> Result<(), AnimationError> {
+        let img = self.animation.next_frame()?.ok_or(AnimationError::NoFrames)?;
+        let texture = Texture2D::from_rgba8(img.width() as u16, img.height() as u16, img.as_raw());
+        if self.buffer.len() >= self.capacity {
+            if let Some((_, old)) = self.buffer.pop_front() {
+                old.delete();
+            }
+        }
+        self.buffer.push_back((self.next, texture));
+        self.next += 1;
+        Ok(())
+    }
+}
+
+impl Drop for FrameStream {
+    fn drop(&mut self) {
+        for (_, texture) in self.buffer.drain(..) {
+            texture.delete();
+        }
+    }
+}
@@ -1,10 +1,13 @@
 use macroquad::prelude::*;
-use macroquad_tantan_toolbox::animation::*;
 
-#[derive(std::hash::Hash, Eq, PartialEq)]
-enum CatAnimationIdentifier {
-    Run,
-}
+mod player;
+mod scene;
+use scene::Scene;
+
+const WIDTH: f32 = 320.; // Width of the screen the scene is drawn on before scaling
+const HEIGHT: f32 = 240.; // Height of the screen the scene is drawn on before scaling
+const TITLE_SPEED: f32 = 30.; // Pixels per second the title drops in at
+const TITLE_STOP: f32 = 30.; // Position the title drops to
 
 fn window_conf() -> Conf {
     Conf {
@@ -17,67 +20,37 @@ fn window_conf() -> Conf {
 
 #[macroquad::main(window_conf)]
 async fn main() {
-    let texture_title: Texture2D = load_texture("assets/title.png").await.unwrap();
-    let texture_far: Texture2D = load_texture("assets/far-buildings.png").await.unwrap();
-    let texture_back: Texture2D = load_texture("assets/back-buildings.png").await.unwrap();
-    let texture_foreground: Texture2D = load_texture("assets/foreground.png").await.unwrap();
-    let texture_cat: Texture2D = load_texture("assets/cat.png").await.unwrap();
+    // Pass the path of a scene file to play a different level
+    let path = std::env::args().nth(1).unwrap_or_else(|| "assets/scene.toml".to_owned());
+    let mut scene = match Scene::load(&path).await {
+        Ok(scene) => scene,
+        Err(e) => {
+            eprintln!("Couldn't load scene {}: {}", path, e);
+            return;
+        },
+    };
 
+    let texture_title: Texture2D = load_texture("assets/title.png").await.unwrap();
+    texture_title.set_filter(FilterMode::Nearest);
     let mut position_title = 0. - texture_title.height();
-    let mut position_far = texture_far.width();
-    let mut position_back = texture_back.width();
-    let mut position_foreground = texture_foreground.width();
 
-    let mut animation = AnimationInstance::<CatAnimationIdentifier>::new(
-        6.,
-        12.,
-        texture_cat,
-        CatAnimationIdentifier::Run,
-    );
-    animation.add_animation(36, 39, None, 4., CatAnimationIdentifier::Run);
-    animation.play_animation(CatAnimationIdentifier::Run);
+    // Draw at the original resolution and scale up by whole multiples to keep the pixels square
+    let target = render_target(WIDTH as u32, HEIGHT as u32);
+    target.texture.set_filter(FilterMode::Nearest);
+    let mut camera = Camera2D::from_display_rect(Rect::new(0., 0., WIDTH, HEIGHT));
+    camera.render_target = Some(target);
 
     loop {
         let time = get_frame_time();
 
+        set_camera(&camera);
         clear_background(BLACK);
 
-        position_far -= 0.05;
-        if position_far < 0. {
-            position_far = texture_far.width();
-        }
-
-        draw_texture(texture_far, position_far, 0., WHITE);
-        draw_texture(texture_far, position_far - texture_far.width(), 0., WHITE);
-
-        position_back -= 0.1;
-        if position_back < 0. {
-            position_back = texture_back.width();
-        }
+        scene.update(time);
+        scene.draw(vec2(WIDTH, HEIGHT));
 
-        draw_texture(texture_back, position_back, -30., WHITE);
-        draw_texture(
-            texture_back,
-            position_back - texture_back.width(),
-            -30.,
-            WHITE,
-        );
-
-        position_foreground -= 0.5;
-        if position_foreground < 0. {
-            position_foreground = texture_foreground.width();
-        }
-
-        draw_texture(texture_foreground, position_foreground, 50., WHITE);
-        draw_texture(
-            texture_foreground,
-            position_foreground - texture_foreground.width(),
-            50.,
-            WHITE,
-        );
-
-        if position_title <= 30. {
-            position_title += 0.5;
+        if position_title <= TITLE_STOP {
+            position_title += TITLE_SPEED * time;
         } else {
             draw_text("PRESS START", 124.0, 120.0, 16.0, WHITE);
         }
@@ -89,8 +62,17 @@ async fn main() {
             WHITE,
         );
 
-        animation.update(time);
-        animation.draw(&vec2(160., 210.), false);
+        // Center the scaled screen in the window with black bars around it
+        set_default_camera();
+        clear_background(BLACK);
+        let scale = (screen_width() / WIDTH).min(screen_height() / HEIGHT).floor().max(1.);
+        let x = ((screen_width() - WIDTH * scale) / 2.).floor();
+        let y = ((screen_height() - HEIGHT * scale) / 2.).floor();
+        draw_texture_ex(target.texture, x, y, WHITE, DrawTextureParams {
+            dest_size: Some(vec2(WIDTH * scale, HEIGHT * scale)),
+            flip_y: true, // Render targets are upside down
+            ..Default::default()
+        });
 
         next_frame().await
     }
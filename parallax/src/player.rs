@@ -0,0 +1,92 @@
+// Character running left and right and jumping under keyboard control
+use macroquad::prelude::*;
+use serde::Deserialize;
+
+use crate::scene::{Result, Sprite, SpriteDesc};
+
+/// Player sprite with an `idle`, `run` and `jump` animation and how it moves
+#[derive(Debug, Deserialize)]
+pub struct PlayerDesc {
+    #[serde(flatten)]
+    sprite: SpriteDesc,
+    run_speed: f32,  // World units per second
+    jump_speed: f32, // Upwards speed in world units per second when jumping
+    gravity: f32,    // Downwards acceleration in world units per second squared
+    ground: f32,     // Height the player stands at
+}
+
+/// Character controlled with the arrow keys or `A` and `D` to run and `Space`, `W` or `Up` to jump
+pub struct Player {
+    pub position: Vec2, // World units
+    pub velocity: Vec2, // World units per second
+    grounded: bool,     // Standing on the ground rather than in the air
+    facing_left: bool,  // Last direction the player ran in
+    sprite: Sprite,
+    run_speed: f32,
+    jump_speed: f32,
+    gravity: f32,
+    ground: f32,
+}
+
+impl Player {
+    /// Load the player's sprite sheet placing the player at the sprite's position
+    pub async fn load(desc: PlayerDesc) -> Result<Self> {
+        let position = Vec2::from(desc.sprite.position);
+        let mut sprite = Sprite::load(desc.sprite).await?;
+
+        // Fail early rather than when the player first runs or jumps
+        for name in ["run", "jump", "idle"] {
+            sprite.play(name)?;
+        }
+        Ok(Self {
+            position,
+            velocity: Vec2::ZERO,
+            grounded: position.y >= desc.ground,
+            facing_left: false,
+            sprite,
+            run_speed: desc.run_speed,
+            jump_speed: desc.jump_speed,
+            gravity: desc.gravity,
+            ground: desc.ground,
+        })
+    }
+
+    /// Handle user input and move the player by the given number of seconds
+    pub fn update(&mut self, dt: f32) {
+        let left = is_key_down(KeyCode::Left) || is_key_down(KeyCode::A);
+        let right = is_key_down(KeyCode::Right) || is_key_down(KeyCode::D);
+        let jump = [KeyCode::Space, KeyCode::Up, KeyCode::W].into_iter().any(is_key_pressed);
+
+        let direction = right as i32 - left as i32;
+        self.velocity.x = direction as f32 * self.run_speed;
+        if direction != 0 {
+            self.facing_left = direction < 0;
+        }
+        if jump && self.grounded {
+            self.velocity.y = -self.jump_speed;
+            self.grounded = false;
+        }
+
+        self.velocity.y += self.gravity * dt;
+        self.position += self.velocity * dt;
+        if self.position.y >= self.ground {
+            self.position.y = self.ground;
+            self.velocity.y = 0.;
+            self.grounded = true;
+        }
+
+        // The animations were all checked to exist when loading
+        let animation = match (self.grounded, direction) {
+            (false, _) => "jump",
+            (true, 0) => "idle",
+            _ => "run",
+        };
+        self.sprite.play(animation).ok();
+        self.sprite.update(dt);
+    }
+
+    /// Draw the player as seen from the camera
+    pub fn draw(&mut self, camera: Vec2) {
+        self.sprite.draw(self.position - camera, self.facing_left);
+    }
+}
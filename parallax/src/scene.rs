@@ -0,0 +1,240 @@
+// Parallax scene loaded from a TOML description so that new levels need no code changes
+use std::error::Error;
+
+use macroquad::prelude::*;
+use macroquad_tantan_toolbox::animation::AnimationInstance;
+use serde::Deserialize;
+
+use crate::player::{Player, PlayerDesc};
+
+pub type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Scene file contents with the layers and sprites drawn back to front
+#[derive(Debug, Deserialize)]
+struct SceneDesc {
+    camera: CameraDesc,
+    player: PlayerDesc,
+    #[serde(default)]
+    layers: Vec<LayerDesc>,
+    #[serde(default)]
+    sprites: Vec<SpriteDesc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CameraDesc {
+    anchor: [f32; 2], // Position on the screen the camera keeps the player at
+    follow: [f32; 2], // How quickly the camera catches up with the player along each axis
+}
+
+/// Background image scrolling at a fraction of the camera's movement
+#[derive(Debug, Deserialize)]
+struct LayerDesc {
+    texture: String, // Path of the image
+    #[serde(default)]
+    speed: [f32; 2], // Movement relative to the camera along each axis with 0 fixed to the screen
+    #[serde(default)]
+    offset: [f32; 2], // Position when the camera is at the origin
+    #[serde(default)]
+    repeat_x: bool, // Tile the image horizontally to fill the screen
+    #[serde(default)]
+    repeat_y: bool, // Tile the image vertically to fill the screen
+    #[serde(default = "white")]
+    tint: [u8; 4], // RGBA color multiplied with the image
+}
+
+/// Animated sprite sheet
+#[derive(Debug, Deserialize)]
+pub struct SpriteDesc {
+    texture: String, // Path of the sprite sheet
+    columns: u32,    // Frames per row of the sprite sheet
+    rows: u32,       // Rows of the sprite sheet
+    #[serde(default)]
+    speed: [f32; 2], // Movement relative to the camera along each axis with 0 fixed to the screen
+    #[serde(default)]
+    pub position: [f32; 2], // Position when the camera is at the origin
+    #[serde(default)]
+    flip_x: bool, // Mirror the sprite horizontally
+    animations: Vec<AnimationDesc>,
+    play: Option<String>, // Animation to start with defaulting to the first
+}
+
+/// Range of frames of a sprite sheet played in a loop or followed by another animation
+#[derive(Debug, Deserialize)]
+struct AnimationDesc {
+    name: String,         // Name to refer to the animation by
+    from: u32,            // Index of the first frame counting rows left to right
+    to: u32,              // Index of the last frame
+    fps: f32,             // Frames per second
+    next: Option<String>, // Animation to play afterwards instead of looping
+}
+
+fn white() -> [u8; 4] {
+    [255, 255, 255, 255]
+}
+
+/// Camera smoothly following a target
+pub struct Camera {
+    pub position: Vec2, // Top left of the view in world units
+    anchor: Vec2,       // Position on the screen the target is kept at
+    follow: Vec2,       // Fraction of the distance to the target caught up per second along each axis
+}
+
+impl Camera {
+    /// Move the camera towards keeping the target at its anchor by the given number of seconds
+    pub fn follow(&mut self, target: Vec2, dt: f32) {
+        let goal = target - self.anchor;
+        let catch_up = vec2(1. - (-self.follow.x * dt).exp(), 1. - (-self.follow.y * dt).exp());
+        self.position += (goal - self.position) * catch_up;
+    }
+}
+
+/// Sprite sheet animations addressed by name
+pub struct Sprite {
+    animation: AnimationInstance<usize>, // Animations identified by their index in the description
+    current: usize,                      // Index of the animation playing
+    desc: SpriteDesc,
+}
+
+impl Sprite {
+    /// Load the sprite sheet and set up its animations
+    pub async fn load(desc: SpriteDesc) -> Result<Self> {
+        let texture = load_texture(&desc.texture).await?;
+        texture.set_filter(FilterMode::Nearest);
+        if desc.animations.is_empty() {
+            return Err(format!("{}: no animations", desc.texture).into());
+        }
+        let current = desc.play.as_deref().map(|x| Self::index(&desc, x)).transpose()?.unwrap_or(0);
+
+        let mut animation = AnimationInstance::new(desc.columns as f32, desc.rows as f32, texture, current);
+        for (i, x) in desc.animations.iter().enumerate() {
+            let next = x.next.as_deref().map(|x| Self::index(&desc, x)).transpose()?;
+            animation.add_animation(x.from, x.to, next, x.fps, i);
+        }
+        animation.play_animation(current);
+        Ok(Self { animation, current, desc })
+    }
+
+    /// Switch to the given animation unless it's already playing
+    pub fn play(&mut self, name: &str) -> Result<()> {
+        let index = Self::index(&self.desc, name)?;
+        if index != self.current {
+            self.current = index;
+            self.animation.play_animation(index);
+        }
+        Ok(())
+    }
+
+    /// Advance the animation by the given number of seconds
+    pub fn update(&mut self, dt: f32) {
+        self.animation.update(dt);
+    }
+
+    /// Draw the sprite at the given screen position optionally mirrored
+    pub fn draw(&mut self, position: Vec2, flip_x: bool) {
+        self.animation.draw(&position.round(), flip_x != self.desc.flip_x);
+    }
+
+    // Find the index of the named animation
+    fn index(desc: &SpriteDesc, name: &str) -> Result<usize> {
+        let found = desc.animations.iter().position(|x| x.name == name);
+        Ok(found.ok_or_else(|| format!("{}: unknown animation {}", desc.texture, name))?)
+    }
+}
+
+struct Layer {
+    texture: Texture2D,
+    desc: LayerDesc,
+}
+
+/// Layers, sprites and a player seen through a camera following the player
+///
+/// ```rust
+/// let mut scene = Scene::load("assets/scene.toml").await?;
+/// loop {
+///     scene.update(get_frame_time());
+///     scene.draw(vec2(320., 240.));
+///     next_frame().await
+/// }
+/// ```
+pub struct Scene {
+    pub camera: Camera,
+    pub player: Player,
+    layers: Vec<Layer>,
+    sprites: Vec<Sprite>,
+}
+
+impl Scene {
+    /// Load the scene description at the given path and the textures it refers to
+    pub async fn load(path: &str) -> Result<Self> {
+        let desc: SceneDesc = toml::from_str(&load_string(path).await?)?;
+
+        let mut layers = Vec::new();
+        for layer in desc.layers {
+            let texture = load_texture(&layer.texture).await?;
+            texture.set_filter(FilterMode::Nearest);
+            layers.push(Layer { texture, desc: layer });
+        }
+
+        let mut sprites = Vec::new();
+        for sprite in desc.sprites {
+            sprites.push(Sprite::load(sprite).await?);
+        }
+
+        // Start with the player at the anchor rather than catching up with it
+        let player = Player::load(desc.player).await?;
+        let anchor = Vec2::from(desc.camera.anchor);
+        Ok(Self {
+            camera: Camera { position: player.position - anchor, anchor, follow: Vec2::from(desc.camera.follow) },
+            player,
+            layers,
+            sprites,
+        })
+    }
+
+    /// Move the player and the camera and advance the sprite animations by the given number of
+    /// seconds
+    pub fn update(&mut self, dt: f32) {
+        self.player.update(dt);
+        self.camera.follow(self.player.position, dt);
+        for sprite in self.sprites.iter_mut() {
+            sprite.update(dt);
+        }
+    }
+
+    /// Draw the layers, sprites and player as seen from the camera onto a screen of the given size
+    pub fn draw(&mut self, screen: Vec2) {
+        for layer in self.layers.iter() {
+            Self::draw_layer(layer, self.camera.position, screen);
+        }
+        for sprite in self.sprites.iter_mut() {
+            let position = Vec2::from(sprite.desc.position) - self.camera.position * Vec2::from(sprite.desc.speed);
+            sprite.draw(position, false);
+        }
+        self.player.draw(self.camera.position);
+    }
+
+    // Draw the layer once or tiled to fill the screen along the repeated axes. Positions are
+    // rounded to whole pixels so that tiles line up without seams.
+    fn draw_layer(layer: &Layer, camera: Vec2, screen: Vec2) {
+        let desc = &layer.desc;
+        let size = vec2(layer.texture.width(), layer.texture.height());
+        let mut origin = (Vec2::from(desc.offset) - camera * Vec2::from(desc.speed)).round();
+        let mut tiles = (1, 1);
+        if desc.repeat_x {
+            origin.x = origin.x.rem_euclid(size.x) - size.x;
+            tiles.0 = ((screen.x - origin.x) / size.x).ceil() as usize;
+        }
+        if desc.repeat_y {
+            origin.y = origin.y.rem_euclid(size.y) - size.y;
+            tiles.1 = ((screen.y - origin.y) / size.y).ceil() as usize;
+        }
+
+        let [r, g, b, a] = desc.tint;
+        for col in 0..tiles.0 {
+            for row in 0..tiles.1 {
+                let position = origin + size * vec2(col as f32, row as f32);
+                draw_texture(layer.texture, position.x, position.y, Color::from_rgba(r, g, b, a));
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+// Embed TrueType fonts into a PDF as Type0/CIDFontType2 fonts
+use std::collections::BTreeMap;
+
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream, StringFormat};
+use rusttype::{Font, GlyphId, Scale};
+
+use crate::subset;
+
+/// A TrueType font to be embedded into a PDF document.
+///
+/// Text is encoded as 2 byte glyph ids using the `Identity-H` encoding, which allows for any
+/// character the font has a glyph for rather than only the latin characters of the base 14 fonts.
+/// Only the glyphs actually used are embedded and a ToUnicode CMap is included so that the text
+/// can still be searched and copied from the resulting document.
+pub struct TrueTypeFont {
+    name: String,               // PostScript name of the font
+    bytes: Vec<u8>,             // Original font file
+    font: Font<'static>,        // Parsed font used for glyph lookups and metrics
+    used: BTreeMap<u16, char>, // Glyph ids used so far mapped to the char they represent
+}
+
+impl TrueTypeFont {
+    /// Create a new font from the bytes of a TrueType file
+    ///
+    /// ```rust
+    /// let bytes = load_file("../text-image/assets/DejaVuSans.ttf").await.unwrap();
+    /// let mut font = TrueTypeFont::from_bytes("DejaVuSans", bytes).unwrap();
+    /// ```
+    pub fn from_bytes(name: &str, bytes: Vec<u8>) -> Option<Self> {
+        let font = Font::try_from_vec(bytes.clone())?;
+        Some(Self {
+            name: name.chars().filter(|x| x.is_ascii_alphanumeric() || *x == '-').collect(),
+            bytes,
+            font,
+            used: BTreeMap::new(),
+        })
+    }
+
+    /// Encode the given text as a string operand for the `Tj` operator tracking the glyphs used
+    pub fn encode(&mut self, text: &str) -> Object {
+        let mut bytes = Vec::with_capacity(text.len() * 2);
+        for char in text.chars() {
+            let id = self.font.glyph(char).id().0;
+            self.used.entry(id).or_insert(char);
+            bytes.extend_from_slice(&id.to_be_bytes());
+        }
+        Object::String(bytes, StringFormat::Hexadecimal)
+    }
+
+    /// Calculate the width of the given text in points for the given font size
+    pub fn text_width(&self, text: &str, size: f32) -> f32 {
+        let units = self.font.units_per_em() as f32;
+        text.chars().map(|x| self.advance(self.font.glyph(x).id()) * size / units).sum()
+    }
+
+    /// Embed the font subset with all glyphs encoded so far into the document. Call this only
+    /// after all text using the font has been encoded.
+    pub fn embed(&self, doc: &mut Document) -> ObjectId {
+        let units = self.font.units_per_em() as f32;
+        let to_pdf = |value: f32| (value * 1000. / units).round() as i64;
+
+        // Subset tag is derived from the glyphs used so the same subset gets the same name
+        let hash = self.used.keys().fold(self.name.len() as u32, |h, x| h.wrapping_mul(31).wrapping_add(*x as u32));
+        let tag: String = (0..6).map(|i| (b'A' + (hash >> (i * 5) & 0x1f) as u8 % 26) as char).collect();
+        let base_font = format!("{}+{}", tag, self.name);
+
+        let glyphs = self.used.keys().copied().collect();
+        let font_file = subset::subset(&self.bytes, &glyphs).unwrap_or_else(|| self.bytes.clone());
+        let font_file_id = doc.add_object(Stream::new(
+            dictionary! {
+                "Length1" => font_file.len() as i64,
+            },
+            font_file,
+        ));
+
+        let v_metrics = self.font.v_metrics_unscaled();
+        let bbox = self.bbox().unwrap_or([0, v_metrics.descent as i16, units as i16, v_metrics.ascent as i16]);
+        let descriptor_id = doc.add_object(dictionary! {
+            "Type" => "FontDescriptor",
+            "FontName" => Object::Name(base_font.clone().into_bytes()),
+            "Flags" => 32,
+            "FontBBox" => bbox.iter().map(|x| to_pdf(*x as f32).into()).collect::<Vec<Object>>(),
+            "ItalicAngle" => 0,
+            "Ascent" => to_pdf(v_metrics.ascent),
+            "Descent" => to_pdf(v_metrics.descent),
+            "CapHeight" => to_pdf(v_metrics.ascent),
+            "StemV" => 80,
+            "FontFile2" => font_file_id,
+        });
+
+        // Widths are given per glyph id as `id [width]`
+        let mut widths = Vec::with_capacity(self.used.len() * 2);
+        for id in self.used.keys() {
+            widths.push((*id as i64).into());
+            widths.push(Object::Array(vec![to_pdf(self.advance(GlyphId(*id))).into()]));
+        }
+
+        let cid_font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "CIDFontType2",
+            "BaseFont" => Object::Name(base_font.clone().into_bytes()),
+            "CIDSystemInfo" => dictionary! {
+                "Registry" => Object::string_literal("Adobe"),
+                "Ordering" => Object::string_literal("Identity"),
+                "Supplement" => 0,
+            },
+            "FontDescriptor" => descriptor_id,
+            "DW" => 1000,
+            "W" => widths,
+            "CIDToGIDMap" => "Identity",
+        });
+
+        let to_unicode_id = doc.add_object(Stream::new(Dictionary::new(), self.to_unicode()));
+        doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type0",
+            "BaseFont" => Object::Name(base_font.into_bytes()),
+            "Encoding" => "Identity-H",
+            "DescendantFonts" => vec![cid_font_id.into()],
+            "ToUnicode" => to_unicode_id,
+        })
+    }
+
+    // Advance width of the given glyph in font units. Rusttype scales relative to the font's
+    // ascent - descent height rather than its em square so the scale is set to that height.
+    fn advance(&self, id: GlyphId) -> f32 {
+        let height = self.font.scale_for_pixel_height(1.).recip();
+        self.font.glyph(id).scaled(Scale::uniform(height)).h_metrics().advance_width
+    }
+
+    // Read the font bounding box from the head table in font units
+    fn bbox(&self) -> Option<[i16; 4]> {
+        let num_tables = u16::from_be_bytes(self.bytes.get(4..6)?.try_into().ok()?) as usize;
+        let head = (0..num_tables).map(|i| 12 + i * 16).find(|x| self.bytes.get(*x..*x + 4) == Some(b"head"))?;
+        let offset = u32::from_be_bytes(self.bytes.get(head + 8..head + 12)?.try_into().ok()?) as usize;
+        let mut bbox = [0i16; 4];
+        for (i, value) in bbox.iter_mut().enumerate() {
+            let pos = offset + 36 + i * 2;
+            *value = i16::from_be_bytes(self.bytes.get(pos..pos + 2)?.try_into().ok()?);
+        }
+        Some(bbox)
+    }
+
+    // Build a ToUnicode CMap mapping the used glyph ids back to their unicode values
+    fn to_unicode(&self) -> Vec<u8> {
+        let mut cmap = String::from(
+            "/CIDInit /ProcSet findresource begin\n\
+             12 dict begin\n\
+             begincmap\n\
+             /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+             /CMapName /Adobe-Identity-UCS def\n\
+             /CMapType 2 def\n\
+             1 begincodespacerange\n\
+             <0000> <FFFF>\n\
+             endcodespacerange\n",
+        );
+
+        // Entries are limited to 100 per block
+        let entries: Vec<(&u16, &char)> = self.used.iter().collect();
+        for chunk in entries.chunks(100) {
+            cmap.push_str(&format!("{} beginbfchar\n", chunk.len()));
+            for (id, char) in chunk {
+                let mut buf = [0u16; 2];
+                let utf16: String = char.encode_utf16(&mut buf).iter().map(|x| format!("{:04X}", x)).collect();
+                cmap.push_str(&format!("<{:04X}> <{}>\n", id, utf16));
+            }
+            cmap.push_str("endbfchar\n");
+        }
+        cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+        cmap.into_bytes()
+    }
+}
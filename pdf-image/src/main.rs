@@ -4,14 +4,92 @@ use lopdf::content::{Content, Operation};
 use lopdf::{Document, Object, Stream};
 use macroquad::prelude::*;
 
-#[macroquad::main("Text Image Example")]
-async fn main() {
+mod font;
+mod render;
+mod subset;
+mod viewer;
+mod xobject;
+use font::TrueTypeFont;
+use viewer::Viewer;
+use xobject::ImageXObject;
+
+// Page size in points
+const PAGE_WIDTH: f32 = 595.;
+const PAGE_HEIGHT: f32 = 842.;
+const MARGIN: f32 = 50.;
+
+// Wrap the given text into lines that fit within the given width
+fn wrap(font: &TrueTypeFont, text: &str, size: f32, width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+            if !line.is_empty() && font.text_width(&candidate, size) > width {
+                lines.push(std::mem::replace(&mut line, word.to_string()));
+            } else {
+                line = candidate;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+// Generate an example PDF mixing fonts and images at the given path
+async fn write_example(path: &str) {
     let bytes = load_file("assets/example.txt")
         .await
         .expect("Couldn't load file");
     let data = std::str::from_utf8(&bytes).unwrap();
     println!("{}", data);
 
+    let font_bytes = load_file("../text-image/assets/DejaVuSans.ttf").await.expect("Couldn't load font file");
+    let mut dejavu = TrueTypeFont::from_bytes("DejaVuSans", font_bytes).expect("Couldn't parse font file");
+    let png = ImageXObject::from_bytes(load_file("assets/ferris.png").await.expect("Couldn't load file"))
+        .expect("Couldn't decode image");
+    let jpg = ImageXObject::from_bytes(load_file("assets/ferris.jpg").await.expect("Couldn't load file"))
+        .expect("Couldn't decode image");
+
+    // Heading using the embedded font with characters outside of latin-1
+    let mut operations = vec![
+        Operation::new("BT", vec![]),
+        Operation::new("Tf", vec!["F2".into(), 36.into()]),
+        Operation::new("Td", vec![MARGIN.into(), (PAGE_HEIGHT - MARGIN - 36.).into()]),
+        Operation::new("Tj", vec![dejavu.encode("Hello World!")]),
+        Operation::new("ET", vec![]),
+        Operation::new("BT", vec![]),
+        Operation::new("Tf", vec!["F2".into(), 18.into()]),
+        Operation::new("Td", vec![MARGIN.into(), (PAGE_HEIGHT - MARGIN - 64.).into()]),
+        Operation::new("Tj", vec![dejavu.encode("Γειά σου Κόσμε! Привет, мир! Grüß Gott!")]),
+        Operation::new("ET", vec![]),
+        Operation::new("BT", vec![]),
+        Operation::new("Tf", vec!["F1".into(), 14.into()]),
+        Operation::new("Td", vec![MARGIN.into(), (PAGE_HEIGHT - MARGIN - 88.).into()]),
+        Operation::new("Tj", vec![Object::string_literal("Base 14 Courier still works alongside it")]),
+        Operation::new("ET", vec![]),
+    ];
+
+    // Body text wrapped to the page width
+    let (size, leading) = (11., 15.);
+    operations.push(Operation::new("BT", vec![]));
+    operations.push(Operation::new("Tf", vec!["F2".into(), size.into()]));
+    operations.push(Operation::new("TL", vec![leading.into()]));
+    operations.push(Operation::new("Td", vec![MARGIN.into(), (PAGE_HEIGHT - MARGIN - 124.).into()]));
+    for line in wrap(&dejavu, data, size, PAGE_WIDTH - MARGIN * 2.) {
+        operations.push(Operation::new("Tj", vec![dejavu.encode(&line)]));
+        operations.push(Operation::new("T*", vec![]));
+    }
+    operations.push(Operation::new("ET", vec![]));
+
+    // Images side by side at the bottom of the page preserving their aspect ratio
+    let img_width = (PAGE_WIDTH - MARGIN * 3.) / 2.;
+    for (i, (name, img)) in [("Im1", &png), ("Im2", &jpg)].iter().enumerate() {
+        let img_height = img_width * img.height as f32 / img.width as f32;
+        let x = MARGIN + i as f32 * (img_width + MARGIN);
+        operations.extend(ImageXObject::draw(name, x, MARGIN, img_width, img_height));
+    }
+
     let mut doc = Document::with_version("1.5");
     let pages_id = doc.new_object_id();
     let font_id = doc.add_object(dictionary! {
@@ -19,20 +97,20 @@ async fn main() {
         "Subtype" => "Type1",
         "BaseFont" => "Courier",
     });
+    let dejavu_id = dejavu.embed(&mut doc);
+    let png_id = png.embed(&mut doc);
+    let jpg_id = jpg.embed(&mut doc);
     let resources_id = doc.add_object(dictionary! {
         "Font" => dictionary! {
             "F1" => font_id,
+            "F2" => dejavu_id,
+        },
+        "XObject" => dictionary! {
+            "Im1" => png_id,
+            "Im2" => jpg_id,
         },
     });
-    let content = Content {
-        operations: vec![
-            Operation::new("BT", vec![]),
-            Operation::new("Tf", vec!["F1".into(), 48.into()]),
-            Operation::new("Td", vec![100.into(), 600.into()]),
-            Operation::new("Tj", vec![Object::string_literal("Hello World!")]),
-            Operation::new("ET", vec![]),
-        ],
-    };
+    let content = Content { operations };
     let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
     let page_id = doc.add_object(dictionary! {
         "Type" => "Page",
@@ -44,7 +122,7 @@ async fn main() {
         "Kids" => vec![page_id.into()],
         "Count" => 1,
         "Resources" => resources_id,
-        "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+        "MediaBox" => vec![0.into(), 0.into(), PAGE_WIDTH.into(), PAGE_HEIGHT.into()],
     };
     doc.objects.insert(pages_id, Object::Dictionary(pages));
     let catalog_id = doc.add_object(dictionary! {
@@ -54,6 +132,38 @@ async fn main() {
     doc.trailer.set("Root", catalog_id);
     doc.compress();
 
-    // Store file in current working directory.
-    doc.save("example.pdf").unwrap();
+    doc.save(path).unwrap();
+}
+
+// Configure window
+fn window_conf() -> Conf {
+    Conf {
+        window_title: "PDF Image example".to_string(),
+        window_width: 1024,
+        window_height: 768,
+        high_dpi: true,
+        ..Default::default()
+    }
+}
+
+#[macroquad::main(window_conf)]
+async fn main() {
+    // View the given PDF or generate the example into the current working directory and view it
+    let path = match std::env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            write_example("example.pdf").await;
+            "example.pdf".to_string()
+        },
+    };
+    let mut viewer = Viewer::load(&path).unwrap_or_else(|e| panic!("Couldn't load {}: {}", path, e));
+
+    loop {
+        clear_background(DARKGRAY);
+
+        viewer.update();
+        viewer.draw();
+
+        next_frame().await
+    }
 }
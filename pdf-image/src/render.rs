@@ -0,0 +1,794 @@
+// Rasterize PDF pages into images by interpreting their content streams.
+//
+// Only a useful subset of PDF is supported: text using embedded TrueType or base 14 fonts, simple
+// filled and stroked paths in device RGB, gray or CMYK colors and 8 bit image XObjects.
+use std::collections::HashMap;
+
+use image::{imageops, imageops::FilterType, Rgba, RgbaImage};
+use imageproc::drawing::{draw_line_segment_mut, draw_polygon_mut};
+use imageproc::point::Point;
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use rusttype::{point, Font, GlyphId, Scale};
+
+// Transformation matrix [a b c d e f] as used by PDF
+type Matrix = [f32; 6];
+const IDENTITY: Matrix = [1., 0., 0., 1., 0., 0.];
+
+// Number of line segments used to approximate a bezier curve
+const CURVE_SEGMENTS: usize = 12;
+
+// Limit on nested form XObjects to guard against forms that draw themselves
+const MAX_FORM_DEPTH: usize = 16;
+
+/// A font loaded from a page's resources ready for drawing glyphs
+struct PdfFont {
+    font: Font<'static>,         // Embedded font or the fallback font when not embedded
+    embedded: bool,              // Whether the font is the one embedded in the PDF
+    two_byte: bool,              // Whether codes are 2 byte glyph ids i.e. the Identity-H encoding
+    widths: HashMap<u16, f32>,   // Code to width in glyph space i.e. 1/1000 of the font size
+    default_width: Option<f32>,  // Width to use for codes without an explicit width
+    unicode: HashMap<u16, char>, // Code to char mapping from the ToUnicode CMap
+}
+
+impl PdfFont {
+    // Split the string operand into character codes
+    fn codes(&self, bytes: &[u8]) -> Vec<u16> {
+        match self.two_byte {
+            true => bytes.chunks(2).map(|x| u16::from_be_bytes([x[0], *x.get(1).unwrap_or(&0)])).collect(),
+            false => bytes.iter().map(|x| *x as u16).collect(),
+        }
+    }
+
+    // Get the glyph to draw for the given character code
+    fn glyph_id(&self, code: u16) -> GlyphId {
+        match (self.two_byte, self.embedded) {
+            // Identity CIDToGIDMap so the code is the glyph id
+            (true, true) => GlyphId(code),
+
+            // Without the embedded font fall back on the unicode value of the code
+            (true, false) => self.font.glyph(*self.unicode.get(&code).unwrap_or(&'?')).id(),
+
+            // Single byte codes are treated as latin-1
+            (false, _) => self.font.glyph(code as u8 as char).id(),
+        }
+    }
+
+    // Width of the given code in glyph space
+    fn width(&self, code: u16) -> f32 {
+        if let Some(width) = self.widths.get(&code).or(self.default_width.as_ref()) {
+            return *width;
+        }
+        let height = self.font.scale_for_pixel_height(1.).recip();
+        let advance = self.font.glyph(self.glyph_id(code)).scaled(Scale::uniform(height)).h_metrics().advance_width;
+        advance * 1000. / self.font.units_per_em() as f32
+    }
+}
+
+/// Resources in scope for a content stream with a form's own resources shadowing its parent's
+struct Scope<'b> {
+    resources: Option<&'b Dictionary>, // Form resources or `None` for the page's inherited ones
+    fonts: HashMap<Vec<u8>, PdfFont>,  // Fonts loaded from the resources
+    parent: Option<&'b Scope<'b>>,     // Scope of the content stream that drew the form
+    depth: usize,                      // Number of forms nested within the page
+}
+
+impl Scope<'_> {
+    // Find the named font in this scope or any of its parents
+    fn font(&self, name: &[u8]) -> Option<&PdfFont> {
+        self.fonts.get(name).or_else(|| self.parent.and_then(|x| x.font(name)))
+    }
+}
+
+/// Graphics state saved and restored by the `q` and `Q` operators
+#[derive(Clone)]
+struct GraphicsState {
+    ctm: Matrix,             // Current transformation matrix including the device transform
+    fill: Rgba<u8>,          // Non-stroking color
+    stroke: Rgba<u8>,        // Stroking color
+    line_width: f32,         // Line width in user space
+    font: Option<Vec<u8>>,   // Resource name of the current font
+    font_size: f32,          // Current font size
+    char_spacing: f32,       // Extra space added after each character
+    word_spacing: f32,       // Extra space added after each single byte space character
+    horiz_scaling: f32,      // Horizontal scaling of text as a fraction
+    leading: f32,            // Distance between lines of text
+    rise: f32,               // Baseline offset for super and subscripts
+}
+
+impl GraphicsState {
+    // Initial state of a page drawn with the given device transform
+    fn new(ctm: Matrix) -> Self {
+        Self {
+            ctm,
+            fill: Rgba([0, 0, 0, 255]),
+            stroke: Rgba([0, 0, 0, 255]),
+            line_width: 1.,
+            font: None,
+            font_size: 0.,
+            char_spacing: 0.,
+            word_spacing: 0.,
+            horiz_scaling: 1.,
+            leading: 0.,
+            rise: 0.,
+        }
+    }
+}
+
+/// Renders pages of a PDF document into RGBA images
+pub struct PageRenderer<'a> {
+    doc: &'a Document,
+    fallback: Font<'static>, // Font to use for fonts that aren't embedded in the document
+}
+
+impl<'a> PageRenderer<'a> {
+    /// Create a new renderer for the given document
+    ///
+    /// ```rust
+    /// let doc = Document::load("example.pdf").unwrap();
+    /// let renderer = PageRenderer::new(&doc);
+    /// ```
+    pub fn new(doc: &'a Document) -> Self {
+        let fallback = Font::try_from_bytes(include_bytes!("../../text-image/assets/DejaVuSans.ttf")).unwrap();
+        Self { doc, fallback }
+    }
+
+    /// Get the page ids of the document in page order
+    pub fn pages(&self) -> Vec<ObjectId> {
+        self.doc.get_pages().into_values().collect()
+    }
+
+    /// Get the size of the given page in points from its media box
+    pub fn page_size(&self, page_id: ObjectId) -> (f32, f32) {
+        let [x0, y0, x1, y1] = self.media_box(page_id);
+        ((x1 - x0).abs(), (y1 - y0).abs())
+    }
+
+    /// Render the given page into an image with `zoom` pixels per point
+    pub fn render(&self, page_id: ObjectId, zoom: f32) -> RgbaImage {
+        let [x0, _, _, y1] = self.media_box(page_id);
+        let (width, height) = self.page_size(page_id);
+        let mut img = RgbaImage::from_pixel(
+            (width * zoom).ceil().max(1.) as u32,
+            (height * zoom).ceil().max(1.) as u32,
+            Rgba([255, 255, 255, 255]),
+        );
+
+        // Device space has the origin in the top left with y increasing downwards
+        let device = [zoom, 0., 0., -zoom, -x0 * zoom, y1 * zoom];
+        let fonts = self.load_fonts(self.doc.get_page_fonts(page_id));
+        let scope = Scope { resources: None, fonts, parent: None, depth: 0 };
+        let content = self.doc.get_page_content(page_id).ok().and_then(|x| Content::decode(&x).ok());
+        if let Some(content) = content {
+            self.execute(&mut img, page_id, &scope, &content.operations, GraphicsState::new(device));
+        }
+        img
+    }
+
+    // Interpret the content stream operations drawing onto the image starting from the given state
+    fn execute(
+        &self, img: &mut RgbaImage, page_id: ObjectId, scope: &Scope, operations: &[Operation],
+        mut state: GraphicsState,
+    ) {
+        let mut stack: Vec<GraphicsState> = Vec::new();
+        let mut path: Vec<Vec<(f32, f32)>> = Vec::new();
+        let mut tm = IDENTITY; // Text matrix
+        let mut tlm = IDENTITY; // Text line matrix
+
+        for op in operations {
+            let num = |i: usize| op.operands.get(i).and_then(number).unwrap_or(0.);
+            let nums = |n: usize| (0..n).map(num).collect::<Vec<f32>>();
+            match op.operator.as_str() {
+                // Graphics state
+                "q" => stack.push(state.clone()),
+                "Q" => state = stack.pop().unwrap_or(state),
+                "cm" => state.ctm = mul(&to_matrix(&nums(6)), &state.ctm),
+                "w" => state.line_width = num(0),
+
+                // Colors
+                "g" => state.fill = gray(num(0)),
+                "G" => state.stroke = gray(num(0)),
+                "rg" => state.fill = rgb(num(0), num(1), num(2)),
+                "RG" => state.stroke = rgb(num(0), num(1), num(2)),
+                "k" => state.fill = cmyk(num(0), num(1), num(2), num(3)),
+                "K" => state.stroke = cmyk(num(0), num(1), num(2), num(3)),
+
+                // Path construction in device space
+                "m" => path.push(vec![apply(&state.ctm, num(0), num(1))]),
+                "l" => {
+                    if let Some(subpath) = path.last_mut() {
+                        subpath.push(apply(&state.ctm, num(0), num(1)));
+                    }
+                },
+                "c" | "v" | "y" => {
+                    if let Some(subpath) = path.last_mut() {
+                        let current = *subpath.last().unwrap();
+                        let p = nums(6);
+                        let (c1, c2, end) = match op.operator.as_str() {
+                            "c" => (apply(&state.ctm, p[0], p[1]), apply(&state.ctm, p[2], p[3]), (p[4], p[5])),
+                            "v" => (current, apply(&state.ctm, p[0], p[1]), (p[2], p[3])),
+                            _ => (apply(&state.ctm, p[0], p[1]), apply(&state.ctm, p[2], p[3]), (p[2], p[3])),
+                        };
+                        let end = apply(&state.ctm, end.0, end.1);
+                        subpath.extend(bezier(current, c1, c2, end));
+                    }
+                },
+                "h" => {
+                    if let Some(subpath) = path.last_mut() {
+                        subpath.push(subpath[0]);
+                    }
+                },
+                "re" => {
+                    let [x, y, w, h] = [num(0), num(1), num(2), num(3)];
+                    let corners = [(x, y), (x + w, y), (x + w, y + h), (x, y + h), (x, y)];
+                    path.push(corners.iter().map(|(x, y)| apply(&state.ctm, *x, *y)).collect());
+                },
+
+                // Path painting
+                "f" | "F" | "f*" => fill(img, &path, state.fill),
+                "S" => stroke(img, &path, state.stroke, self.line_width(&state)),
+                "s" => {
+                    close(&mut path);
+                    stroke(img, &path, state.stroke, self.line_width(&state));
+                },
+                "B" | "B*" | "b" | "b*" => {
+                    if op.operator.starts_with('b') {
+                        close(&mut path);
+                    }
+                    fill(img, &path, state.fill);
+                    stroke(img, &path, state.stroke, self.line_width(&state));
+                },
+                "n" => (),
+
+                // Text objects and state
+                "BT" => {
+                    tm = IDENTITY;
+                    tlm = IDENTITY;
+                },
+                "ET" => (),
+                "Tf" => {
+                    state.font = op.operands.first().and_then(|x| x.as_name().ok()).map(|x| x.to_vec());
+                    state.font_size = num(1);
+                },
+                "Tc" => state.char_spacing = num(0),
+                "Tw" => state.word_spacing = num(0),
+                "Tz" => state.horiz_scaling = num(0) / 100.,
+                "TL" => state.leading = num(0),
+                "Ts" => state.rise = num(0),
+
+                // Text positioning
+                "Td" | "TD" => {
+                    if op.operator == "TD" {
+                        state.leading = -num(1);
+                    }
+                    tlm = mul(&[1., 0., 0., 1., num(0), num(1)], &tlm);
+                    tm = tlm;
+                },
+                "Tm" => {
+                    tlm = to_matrix(&nums(6));
+                    tm = tlm;
+                },
+                "T*" => {
+                    tlm = mul(&[1., 0., 0., 1., 0., -state.leading], &tlm);
+                    tm = tlm;
+                },
+
+                // Text showing
+                "Tj" | "'" | "\"" => {
+                    if op.operator == "\"" {
+                        state.word_spacing = num(0);
+                        state.char_spacing = num(1);
+                    }
+                    if op.operator != "Tj" {
+                        tlm = mul(&[1., 0., 0., 1., 0., -state.leading], &tlm);
+                        tm = tlm;
+                    }
+                    if let Some(Object::String(bytes, _)) = op.operands.last() {
+                        self.show_text(img, scope, &state, &mut tm, bytes);
+                    }
+                },
+                "TJ" => {
+                    let items = op.operands.first().and_then(|x| x.as_array().ok()).cloned().unwrap_or_default();
+                    for item in items {
+                        match item {
+                            Object::String(bytes, _) => self.show_text(img, scope, &state, &mut tm, &bytes),
+                            _ => {
+                                let tx = -number(&item).unwrap_or(0.) / 1000. * state.font_size * state.horiz_scaling;
+                                tm = mul(&[1., 0., 0., 1., tx, 0.], &tm);
+                            },
+                        }
+                    }
+                },
+
+                // External objects
+                "Do" => {
+                    if let Some(name) = op.operands.first().and_then(|x| x.as_name().ok()) {
+                        self.draw_xobject(img, page_id, scope, name, &state);
+                    }
+                },
+                _ => (),
+            }
+
+            // Painting operators end the current path
+            if matches!(op.operator.as_str(), "f" | "F" | "f*" | "S" | "s" | "B" | "B*" | "b" | "b*" | "n") {
+                path.clear();
+            }
+        }
+    }
+
+    // Draw the glyphs for the given string advancing the text matrix
+    fn show_text(
+        &self, img: &mut RgbaImage, scope: &Scope, state: &GraphicsState, tm: &mut Matrix, bytes: &[u8],
+    ) {
+        let font = match state.font.as_ref().and_then(|x| scope.font(x)) {
+            Some(font) => font,
+            None => return,
+        };
+        let units = font.font.units_per_em() as f32;
+        let em_to_height = font.font.scale_for_pixel_height(1.).recip() / units;
+        for code in font.codes(bytes) {
+            // Text rendering matrix maps glyph space into device space
+            let trm = mul(
+                &mul(&[state.font_size * state.horiz_scaling, 0., 0., state.font_size, 0., state.rise], tm),
+                &state.ctm,
+            );
+            let scale = Scale {
+                x: trm[0].hypot(trm[1]) * em_to_height,
+                y: trm[2].hypot(trm[3]) * em_to_height,
+            };
+            let glyph = font.font.glyph(font.glyph_id(code)).scaled(scale).positioned(point(trm[4], trm[5]));
+            if let Some(bb) = glyph.pixel_bounding_box() {
+                glyph.draw(|x, y, v| blend(img, x as i32 + bb.min.x, y as i32 + bb.min.y, state.fill, v));
+            }
+
+            // Advance by the glyph width plus any spacing
+            let spacing = state.char_spacing
+                + if !font.two_byte && code == 32 { state.word_spacing } else { 0. };
+            let tx = (font.width(code) / 1000. * state.font_size + spacing) * state.horiz_scaling;
+            *tm = mul(&[1., 0., 0., 1., tx, 0.], tm);
+        }
+    }
+
+    // Draw the named XObject which may be an image or a form
+    fn draw_xobject(
+        &self, img: &mut RgbaImage, page_id: ObjectId, scope: &Scope, name: &[u8], state: &GraphicsState,
+    ) {
+        let stream = match self.resource(page_id, scope, b"XObject", name).and_then(|x| x.as_stream().ok()) {
+            Some(stream) => stream,
+            None => return,
+        };
+        match stream.dict.get(b"Subtype").and_then(Object::as_name).unwrap_or(b"") {
+            b"Image" => {
+                if let Some(src) = self.decode_image(stream) {
+                    draw_image(img, &src, &state.ctm);
+                }
+            },
+            b"Form" if scope.depth < MAX_FORM_DEPTH => {
+                // Forms draw with their own resources falling back on those of the parent scope
+                let resources =
+                    stream.dict.get(b"Resources").ok().and_then(|x| self.deref(x)).and_then(|x| x.as_dict().ok());
+                let fonts = resources
+                    .and_then(|x| x.get(b"Font").ok())
+                    .and_then(|x| self.deref(x))
+                    .and_then(|x| x.as_dict().ok())
+                    .map(|x| {
+                        self.load_fonts(
+                            x.iter().filter_map(|(k, v)| Some((k.clone(), self.deref(v)?.as_dict().ok()?))),
+                        )
+                    })
+                    .unwrap_or_default();
+                let scope = Scope { resources, fonts, parent: Some(scope), depth: scope.depth + 1 };
+
+                let content = stream.decompressed_content().unwrap_or_else(|_| stream.content.clone());
+                if let Ok(content) = Content::decode(&content) {
+                    let matrix = stream.dict.get(b"Matrix").and_then(Object::as_array).ok();
+                    let matrix = matrix.map(|x| to_matrix(&x.iter().filter_map(number).collect::<Vec<_>>()));
+
+                    // Forms inherit the caller's state with their matrix applied and leave it untouched
+                    let mut inner = state.clone();
+                    inner.ctm = mul(&matrix.unwrap_or(IDENTITY), &state.ctm);
+                    self.execute(img, page_id, &scope, &content.operations, inner);
+                }
+            },
+            _ => (),
+        }
+    }
+
+    // Decode an 8 bit image XObject into an RGBA image
+    fn decode_image(&self, stream: &Stream) -> Option<RgbaImage> {
+        let dict = &stream.dict;
+        let filter = stream.filter().unwrap_or_default();
+        if filter == "DCTDecode" {
+            return image::load_from_memory(&stream.content).ok().map(|x| x.to_rgba8());
+        }
+
+        let width = self.deref(dict.get(b"Width").ok()?).and_then(number)? as u32;
+        let height = self.deref(dict.get(b"Height").ok()?).and_then(number)? as u32;
+        let data = match filter.as_str() {
+            "" => stream.content.clone(),
+
+            // lopdf only decompresses streams that aren't images
+            _ => {
+                let mut plain = stream.clone();
+                plain.dict.remove(b"Subtype");
+                plain.decompressed_content().ok()?
+            },
+        };
+        let color_space = self.deref(dict.get(b"ColorSpace").ok()?).and_then(|x| x.as_name().ok());
+        let components = match color_space {
+            Some(b"DeviceGray") => 1,
+            Some(b"DeviceCMYK") => 4,
+            _ => 3,
+        };
+        if data.len() < (width * height * components) as usize {
+            return None;
+        }
+
+        // Apply the soft mask as the alpha channel if there is one
+        let alpha = dict
+            .get(b"SMask")
+            .ok()
+            .and_then(|x| self.deref(x))
+            .and_then(|x| x.as_stream().ok())
+            .and_then(|x| self.decode_image(x))
+            .map(|x| match x.dimensions() == (width, height) {
+                true => x,
+
+                // Soft masks may have different dimensions than the image they apply to
+                false => imageops::resize(&x, width, height, FilterType::Triangle),
+            });
+        let mut src = RgbaImage::new(width, height);
+        for (i, pixel) in src.pixels_mut().enumerate() {
+            let c = &data[i * components as usize..(i + 1) * components as usize];
+            *pixel = match components {
+                1 => Rgba([c[0], c[0], c[0], 255]),
+                4 => cmyk(c[0] as f32 / 255., c[1] as f32 / 255., c[2] as f32 / 255., c[3] as f32 / 255.),
+                _ => Rgba([c[0], c[1], c[2], 255]),
+            };
+            if let Some(alpha) = alpha.as_ref() {
+                pixel.0[3] = alpha.get_pixel(i as u32 % width, i as u32 / width).0[0];
+            }
+        }
+        Some(src)
+    }
+
+    // Load all the given fonts from a page's or form's resources
+    fn load_fonts<'b>(
+        &self, dicts: impl IntoIterator<Item = (Vec<u8>, &'b Dictionary)>,
+    ) -> HashMap<Vec<u8>, PdfFont> {
+        let mut fonts = HashMap::new();
+        for (name, dict) in dicts {
+            let subtype = dict.get(b"Subtype").and_then(Object::as_name).unwrap_or(b"");
+            let two_byte = subtype == b"Type0";
+
+            // Type0 fonts keep their metrics and font file on the descendant font
+            let descendant = match two_byte {
+                true => dict
+                    .get(b"DescendantFonts")
+                    .ok()
+                    .and_then(|x| self.deref(x))
+                    .and_then(|x| x.as_array().ok())
+                    .and_then(|x| x.first())
+                    .and_then(|x| self.deref(x))
+                    .and_then(|x| x.as_dict().ok()),
+                false => Some(dict),
+            };
+            let descendant = match descendant {
+                Some(descendant) => descendant,
+                None => continue,
+            };
+
+            let embedded = self.font_file(descendant).and_then(Font::try_from_vec);
+            let (widths, default_width) = match two_byte {
+                true => (self.cid_widths(descendant), Some(self.number(descendant, b"DW").unwrap_or(1000.))),
+                false => (self.simple_widths(dict), None),
+            };
+            fonts.insert(name, PdfFont {
+                embedded: embedded.is_some(),
+                font: embedded.unwrap_or_else(|| self.fallback.clone()),
+                two_byte,
+                widths,
+                default_width,
+                unicode: self.to_unicode(dict),
+            });
+        }
+        fonts
+    }
+
+    // Get the decompressed TrueType font file from the font descriptor if embedded
+    fn font_file(&self, font: &Dictionary) -> Option<Vec<u8>> {
+        let descriptor = self.deref(font.get(b"FontDescriptor").ok()?)?.as_dict().ok()?;
+        let stream = self.deref(descriptor.get(b"FontFile2").ok()?)?.as_stream().ok()?;
+        Some(stream.decompressed_content().unwrap_or_else(|_| stream.content.clone()))
+    }
+
+    // Widths of a CID font given as `c [w1 w2 ...]` or `c_first c_last w`
+    fn cid_widths(&self, font: &Dictionary) -> HashMap<u16, f32> {
+        let mut widths = HashMap::new();
+        let array = match font.get(b"W").ok().and_then(|x| self.deref(x)).and_then(|x| x.as_array().ok()) {
+            Some(array) => array,
+            None => return widths,
+        };
+        let mut i = 0;
+        while i + 1 < array.len() {
+            let first = number(&array[i]).unwrap_or(0.) as u16;
+            match self.deref(&array[i + 1]) {
+                Some(Object::Array(list)) => {
+                    for (j, width) in list.iter().enumerate() {
+                        widths.insert(first + j as u16, number(width).unwrap_or(0.));
+                    }
+                    i += 2;
+                },
+                _ => {
+                    let last = number(&array[i + 1]).unwrap_or(0.) as u16;
+                    let width = array.get(i + 2).and_then(number).unwrap_or(0.);
+                    for code in first..=last {
+                        widths.insert(code, width);
+                    }
+                    i += 3;
+                },
+            }
+        }
+        widths
+    }
+
+    // Widths of a simple font given as an array starting at `FirstChar`
+    fn simple_widths(&self, font: &Dictionary) -> HashMap<u16, f32> {
+        let first = self.number(font, b"FirstChar").unwrap_or(0.) as u16;
+        let array = font.get(b"Widths").ok().and_then(|x| self.deref(x)).and_then(|x| x.as_array().ok());
+        array
+            .map(|x| x.iter().enumerate().map(|(i, w)| (first + i as u16, number(w).unwrap_or(0.))).collect())
+            .unwrap_or_default()
+    }
+
+    // Parse the `bfchar` entries of the font's ToUnicode CMap
+    fn to_unicode(&self, font: &Dictionary) -> HashMap<u16, char> {
+        let mut map = HashMap::new();
+        let stream = font.get(b"ToUnicode").ok().and_then(|x| self.deref(x)).and_then(|x| x.as_stream().ok());
+        let cmap = match stream {
+            Some(stream) => stream.decompressed_content().unwrap_or_else(|_| stream.content.clone()),
+            None => return map,
+        };
+        let cmap = String::from_utf8_lossy(&cmap);
+        for block in cmap.split("beginbfchar").skip(1) {
+            let block = block.split("endbfchar").next().unwrap_or("");
+            let hex: Vec<&str> = block.split(['<', '>']).map(str::trim).filter(|x| !x.is_empty()).collect();
+            for pair in hex.chunks(2) {
+                if let [code, value] = pair {
+                    let code = u16::from_str_radix(code, 16).ok();
+                    let utf16: Vec<u16> = (0..value.len() / 4)
+                        .filter_map(|i| u16::from_str_radix(&value[i * 4..i * 4 + 4], 16).ok())
+                        .collect();
+                    let char = char::decode_utf16(utf16).next().and_then(Result::ok);
+                    if let (Some(code), Some(char)) = (code, char) {
+                        map.insert(code, char);
+                    }
+                }
+            }
+        }
+        map
+    }
+
+    // Find the named resource of the given category e.g. XObject checking the form scopes first
+    // and then walking up the page tree
+    fn resource<'b>(
+        &'b self, page_id: ObjectId, scope: &Scope<'b>, category: &[u8], name: &[u8],
+    ) -> Option<&'b Object> {
+        let mut current = Some(scope);
+        while let Some(scope) = current {
+            if let Some(found) = scope.resources.and_then(|x| self.lookup(x, category, name)) {
+                return Some(found);
+            }
+            current = scope.parent;
+        }
+
+        let mut node = self.doc.get_dictionary(page_id).ok();
+        while let Some(dict) = node {
+            let found = dict
+                .get(b"Resources")
+                .ok()
+                .and_then(|x| self.deref(x))
+                .and_then(|x| x.as_dict().ok())
+                .and_then(|x| self.lookup(x, category, name));
+            if found.is_some() {
+                return found;
+            }
+            node = dict.get(b"Parent").and_then(Object::as_reference).and_then(|x| self.doc.get_dictionary(x)).ok();
+        }
+        None
+    }
+
+    // Find the named resource of the given category in the resources dictionary
+    fn lookup<'b>(&'b self, resources: &'b Dictionary, category: &[u8], name: &[u8]) -> Option<&'b Object> {
+        resources
+            .get(category)
+            .ok()
+            .and_then(|x| self.deref(x))
+            .and_then(|x| x.as_dict().ok())
+            .and_then(|x| x.get(name).ok())
+            .and_then(|x| self.deref(x))
+    }
+
+    // Get the page's media box which may be inherited from the page tree
+    fn media_box(&self, page_id: ObjectId) -> [f32; 4] {
+        let mut node = self.doc.get_dictionary(page_id).ok();
+        while let Some(dict) = node {
+            let media_box = dict.get(b"MediaBox").ok().and_then(|x| self.deref(x)).and_then(|x| x.as_array().ok());
+            if let Some(values) = media_box {
+                let values: Vec<f32> = values.iter().filter_map(|x| self.deref(x).and_then(number)).collect();
+                if let [x0, y0, x1, y1] = values[..] {
+                    return [x0, y0, x1, y1];
+                }
+            }
+            node = dict.get(b"Parent").and_then(Object::as_reference).and_then(|x| self.doc.get_dictionary(x)).ok();
+        }
+
+        // Default to US letter
+        [0., 0., 612., 792.]
+    }
+
+    // Get a number from the dictionary following references
+    fn number(&self, dict: &Dictionary, key: &[u8]) -> Option<f32> {
+        self.deref(dict.get(key).ok()?).and_then(number)
+    }
+
+    // Follow references to the actual object
+    fn deref<'b>(&'b self, obj: &'b Object) -> Option<&'b Object> {
+        self.doc.dereference(obj).ok().map(|(_, x)| x)
+    }
+
+    // Line width in device pixels
+    fn line_width(&self, state: &GraphicsState) -> f32 {
+        let scale = (state.ctm[0] * state.ctm[3] - state.ctm[1] * state.ctm[2]).abs().sqrt();
+        (state.line_width * scale).max(1.)
+    }
+}
+
+// Get the numeric value of an integer or real object. Reals are f32 or f64 depending on the lopdf
+// version so the cast is needed for one and not the other.
+#[allow(clippy::unnecessary_cast)]
+fn number(obj: &Object) -> Option<f32> {
+    match obj {
+        Object::Integer(x) => Some(*x as f32),
+        Object::Real(x) => Some(*x as f32),
+        _ => None,
+    }
+}
+
+// Build a matrix from the given operands padding with identity values
+fn to_matrix(values: &[f32]) -> Matrix {
+    let mut matrix = IDENTITY;
+    for (i, value) in values.iter().take(6).enumerate() {
+        matrix[i] = *value;
+    }
+    matrix
+}
+
+// Multiply the matrices such that `a` is applied before `b`
+fn mul(a: &Matrix, b: &Matrix) -> Matrix {
+    [
+        a[0] * b[0] + a[1] * b[2],
+        a[0] * b[1] + a[1] * b[3],
+        a[2] * b[0] + a[3] * b[2],
+        a[2] * b[1] + a[3] * b[3],
+        a[4] * b[0] + a[5] * b[2] + b[4],
+        a[4] * b[1] + a[5] * b[3] + b[5],
+    ]
+}
+
+// Transform the point by the matrix
+fn apply(m: &Matrix, x: f32, y: f32) -> (f32, f32) {
+    (x * m[0] + y * m[2] + m[4], x * m[1] + y * m[3] + m[5])
+}
+
+// Approximate a cubic bezier curve with line segments excluding the start point
+fn bezier(p0: (f32, f32), p1: (f32, f32), p2: (f32, f32), p3: (f32, f32)) -> Vec<(f32, f32)> {
+    (1..=CURVE_SEGMENTS)
+        .map(|i| {
+            let t = i as f32 / CURVE_SEGMENTS as f32;
+            let u = 1. - t;
+            let (a, b, c, d) = (u * u * u, 3. * u * u * t, 3. * u * t * t, t * t * t);
+            (a * p0.0 + b * p1.0 + c * p2.0 + d * p3.0, a * p0.1 + b * p1.1 + c * p2.1 + d * p3.1)
+        })
+        .collect()
+}
+
+// Close all open subpaths
+fn close(path: &mut [Vec<(f32, f32)>]) {
+    for subpath in path.iter_mut() {
+        if subpath.len() > 1 && subpath.first() != subpath.last() {
+            subpath.push(subpath[0]);
+        }
+    }
+}
+
+// Fill each subpath as a polygon
+fn fill(img: &mut RgbaImage, path: &[Vec<(f32, f32)>], color: Rgba<u8>) {
+    for subpath in path {
+        let mut points: Vec<Point<i32>> = Vec::with_capacity(subpath.len());
+        for (x, y) in subpath {
+            let p = Point::new(x.round() as i32, y.round() as i32);
+            if points.last() != Some(&p) {
+                points.push(p);
+            }
+        }
+
+        // Imageproc requires the polygon to be open
+        while points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        if points.len() >= 3 {
+            draw_polygon_mut(img, &points, color);
+        }
+    }
+}
+
+// Stroke each line segment of the path with the given width in pixels
+fn stroke(img: &mut RgbaImage, path: &[Vec<(f32, f32)>], color: Rgba<u8>, width: f32) {
+    for subpath in path {
+        for segment in subpath.windows(2) {
+            let (a, b) = (segment[0], segment[1]);
+            let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+            let len = dx.hypot(dy);
+            if width <= 1.5 || len == 0. {
+                draw_line_segment_mut(img, a, b, color);
+                continue;
+            }
+
+            // Thick lines are drawn as a quad offset along the normal
+            let (nx, ny) = (-dy / len * width / 2., dx / len * width / 2.);
+            let quad = [(a.0 + nx, a.1 + ny), (b.0 + nx, b.1 + ny), (b.0 - nx, b.1 - ny), (a.0 - nx, a.1 - ny)];
+            fill(img, &[quad.to_vec()], color);
+        }
+    }
+}
+
+// Draw the image into the unit square of user space transformed by the matrix
+fn draw_image(img: &mut RgbaImage, src: &RgbaImage, ctm: &Matrix) {
+    let corners = [apply(ctm, 0., 0.), apply(ctm, 1., 0.), apply(ctm, 0., 1.), apply(ctm, 1., 1.)];
+    let min_x = corners.iter().map(|x| x.0).fold(f32::MAX, f32::min);
+    let max_x = corners.iter().map(|x| x.0).fold(f32::MIN, f32::max);
+    let min_y = corners.iter().map(|x| x.1).fold(f32::MAX, f32::min);
+    let max_y = corners.iter().map(|x| x.1).fold(f32::MIN, f32::max);
+    let (width, height) = ((max_x - min_x).round() as u32, (max_y - min_y).round() as u32);
+    if width == 0 || height == 0 {
+        return;
+    }
+
+    // Only axis aligned images are supported; flip to match the direction of the transform
+    let mut scaled = imageops::resize(src, width, height, FilterType::Triangle);
+    if ctm[0] < 0. {
+        scaled = imageops::flip_horizontal(&scaled);
+    }
+    if ctm[3] > 0. {
+        scaled = imageops::flip_vertical(&scaled);
+    }
+    for (x, y, pixel) in scaled.enumerate_pixels() {
+        blend(img, min_x.round() as i32 + x as i32, min_y.round() as i32 + y as i32, *pixel, 1.);
+    }
+}
+
+// Blend the color into the pixel at the given coverage
+fn blend(img: &mut RgbaImage, x: i32, y: i32, color: Rgba<u8>, coverage: f32) {
+    if x < 0 || y < 0 || x >= img.width() as i32 || y >= img.height() as i32 {
+        return;
+    }
+    let alpha = coverage * color.0[3] as f32 / 255.;
+    let pixel = img.get_pixel_mut(x as u32, y as u32);
+    for i in 0..3 {
+        pixel.0[i] = (pixel.0[i] as f32 * (1. - alpha) + color.0[i] as f32 * alpha).round() as u8;
+    }
+}
+
+fn gray(value: f32) -> Rgba<u8> {
+    rgb(value, value, value)
+}
+
+fn rgb(r: f32, g: f32, b: f32) -> Rgba<u8> {
+    let c = |x: f32| (x.clamp(0., 1.) * 255.).round() as u8;
+    Rgba([c(r), c(g), c(b), 255])
+}
+
+fn cmyk(c: f32, m: f32, y: f32, k: f32) -> Rgba<u8> {
+    rgb((1. - c) * (1. - k), (1. - m) * (1. - k), (1. - y) * (1. - k))
+}
@@ -0,0 +1,194 @@
+// Minimal TrueType subsetter for embedding fonts as FontFile2 streams.
+//
+// Glyph ids are preserved so that the PDF can keep using an Identity CIDToGIDMap. Glyphs that
+// aren't used are simply emptied out of the `glyf` table, which is where nearly all of the size
+// of a TrueType font lives.
+use std::collections::BTreeSet;
+
+// Composite glyph flags
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+// Tables needed by a PDF viewer to render a CIDFontType2 font
+const KEEP_TABLES: [&[u8; 4]; 9] = [b"cvt ", b"fpgm", b"glyf", b"head", b"hhea", b"hmtx", b"loca", b"maxp", b"prep"];
+
+/// A single table from the font's table directory
+struct Table<'a> {
+    tag: [u8; 4],
+    data: &'a [u8],
+}
+
+/// Subset the given TrueType font keeping only the given glyph ids and any glyphs they reference.
+/// Glyph 0 (.notdef) is always kept. Returns `None` if the font couldn't be parsed.
+pub fn subset(font: &[u8], glyphs: &BTreeSet<u16>) -> Option<Vec<u8>> {
+    let tables = read_tables(font)?;
+    let table = |tag: &[u8; 4]| tables.iter().find(|x| &x.tag == tag).map(|x| x.data);
+    let head = table(b"head")?;
+    let maxp = table(b"maxp")?;
+    let loca = table(b"loca")?;
+    let glyf = table(b"glyf")?;
+
+    // Read in the original glyph locations
+    let num_glyphs = read_u16(maxp, 4)? as usize;
+    let long_loca = read_u16(head, 50)? != 0;
+    let mut offsets = Vec::with_capacity(num_glyphs + 1);
+    for i in 0..=num_glyphs {
+        let offset = match long_loca {
+            true => read_u32(loca, i * 4)? as usize,
+            false => read_u16(loca, i * 2)? as usize * 2,
+        };
+        offsets.push(offset);
+    }
+    let glyph_data = |id: usize| glyf.get(offsets[id]..offsets[id + 1]);
+
+    // Pull in any glyphs referenced by composite glyphs
+    let mut keep: BTreeSet<u16> = glyphs.iter().copied().filter(|x| (*x as usize) < num_glyphs).collect();
+    keep.insert(0);
+    let mut pending: Vec<u16> = keep.iter().copied().collect();
+    while let Some(id) = pending.pop() {
+        for component in components(glyph_data(id as usize)?)? {
+            if (component as usize) < num_glyphs && keep.insert(component) {
+                pending.push(component);
+            }
+        }
+    }
+
+    // Rebuild the glyf and loca tables always using the long loca format
+    let mut new_glyf = Vec::new();
+    let mut new_loca = Vec::with_capacity((num_glyphs + 1) * 4);
+    for id in 0..num_glyphs {
+        new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+        if keep.contains(&(id as u16)) {
+            new_glyf.extend_from_slice(glyph_data(id)?);
+            while new_glyf.len() % 4 != 0 {
+                new_glyf.push(0);
+            }
+        }
+    }
+    new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+
+    // Switch the head table to long loca and clear the checksum adjustment to be set later
+    let mut new_head = head.to_vec();
+    new_head.get_mut(8..12)?.copy_from_slice(&[0; 4]);
+    new_head.get_mut(50..52)?.copy_from_slice(&1u16.to_be_bytes());
+
+    let mut out_tables: Vec<(&[u8; 4], &[u8])> = Vec::new();
+    for tag in KEEP_TABLES {
+        let data = match tag {
+            b"glyf" => &new_glyf[..],
+            b"head" => &new_head[..],
+            b"loca" => &new_loca[..],
+            _ => match table(tag) {
+                Some(data) => data,
+                None => continue,
+            },
+        };
+        out_tables.push((tag, data));
+    }
+    Some(write_font(&out_tables))
+}
+
+// Read the table directory of the font
+fn read_tables(font: &[u8]) -> Option<Vec<Table<'_>>> {
+    let num_tables = read_u16(font, 4)? as usize;
+    let mut tables = Vec::with_capacity(num_tables);
+    for i in 0..num_tables {
+        let record = 12 + i * 16;
+        let offset = read_u32(font, record + 8)? as usize;
+        let length = read_u32(font, record + 12)? as usize;
+        tables.push(Table {
+            tag: font.get(record..record + 4)?.try_into().ok()?,
+            data: font.get(offset..offset + length)?,
+        });
+    }
+    Some(tables)
+}
+
+// Get the glyph ids referenced by the given glyph if it is a composite glyph
+fn components(glyph: &[u8]) -> Option<Vec<u16>> {
+    let mut ids = Vec::new();
+    if glyph.is_empty() || read_u16(glyph, 0)? as i16 >= 0 {
+        return Some(ids);
+    }
+
+    // Skip the glyph header of number of contours and bounding box
+    let mut pos = 10;
+    loop {
+        let flags = read_u16(glyph, pos)?;
+        ids.push(read_u16(glyph, pos + 2)?);
+        pos += 4;
+        pos += if flags & ARG_1_AND_2_ARE_WORDS != 0 { 4 } else { 2 };
+        if flags & WE_HAVE_A_SCALE != 0 {
+            pos += 2;
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            pos += 4;
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            pos += 8;
+        }
+        if flags & MORE_COMPONENTS == 0 {
+            break;
+        }
+    }
+    Some(ids)
+}
+
+// Write out the given tables, which must be sorted by tag, as a new font
+fn write_font(tables: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+    let num_tables = tables.len() as u16;
+    let entry_selector = 15 - num_tables.leading_zeros() as u16;
+    let search_range: u16 = (1 << entry_selector) * 16;
+
+    let mut font = Vec::new();
+    font.extend_from_slice(&0x00010000u32.to_be_bytes());
+    font.extend_from_slice(&num_tables.to_be_bytes());
+    font.extend_from_slice(&search_range.to_be_bytes());
+    font.extend_from_slice(&entry_selector.to_be_bytes());
+    font.extend_from_slice(&(num_tables * 16 - search_range).to_be_bytes());
+
+    // Table records followed by the 4 byte aligned table data
+    let mut offset = 12 + tables.len() * 16;
+    let mut data = Vec::new();
+    let mut head_offset = None;
+    for (tag, table) in tables {
+        if *tag == b"head" {
+            head_offset = Some(offset);
+        }
+        font.extend_from_slice(*tag);
+        font.extend_from_slice(&checksum(table).to_be_bytes());
+        font.extend_from_slice(&(offset as u32).to_be_bytes());
+        font.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        data.extend_from_slice(table);
+        while data.len() % 4 != 0 {
+            data.push(0);
+        }
+        offset = 12 + tables.len() * 16 + data.len();
+    }
+    font.extend_from_slice(&data);
+
+    // The checksum adjustment makes the checksum of the whole font come out to a magic number
+    if let Some(head) = head_offset {
+        let adjustment = 0xB1B0AFBAu32.wrapping_sub(checksum(&font));
+        font[head + 8..head + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    font
+}
+
+// Sum of the data as big endian u32 values padding with zeros as needed
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
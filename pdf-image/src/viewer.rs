@@ -0,0 +1,155 @@
+// Display the pages of a PDF document using macroquad
+use lopdf::{Document, ObjectId};
+use macroquad::prelude::*;
+
+use crate::render::PageRenderer;
+
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 8.;
+const ZOOM_STEP: f32 = 1.25;
+const PAN_SPEED: f32 = 600.; // Pixels per second when panning with the arrow keys
+
+/// Page viewer with navigation and zoom. Pages are rasterized on demand whenever the page or
+/// zoom changes and cached as a texture until then.
+///
+/// * `Right`, `PageDown` and `Space` go to the next page; `Left` and `PageUp` the previous
+/// * `Home` and `End` go to the first and last pages
+/// * `+`, `-` and the mouse wheel zoom in and out; `0` fits the page to the window
+/// * `Up`, `Down` and dragging with the mouse pan the page
+pub struct Viewer {
+    doc: Document,
+    pages: Vec<ObjectId>,      // Page ids in page order
+    page: usize,               // Index of the current page
+    zoom: Option<f32>,         // Pixels per point or None to fit the page to the window
+    offset: Vec2,              // Pan offset of the page from the center of the window
+    last_mouse: Option<Vec2>,  // Mouse position at the last frame while dragging
+    tx: Option<Texture2D>,     // Cached texture of the current page
+    tx_zoom: f32,              // Zoom the cached texture was rendered at
+}
+
+impl Viewer {
+    /// Load the given PDF file for viewing
+    ///
+    /// ```rust
+    /// let mut viewer = Viewer::load("example.pdf").unwrap();
+    /// ```
+    pub fn load(path: &str) -> lopdf::Result<Self> {
+        let doc = Document::load(path)?;
+        let pages = PageRenderer::new(&doc).pages();
+        Ok(Self {
+            doc,
+            pages,
+            page: 0,
+            zoom: None,
+            offset: Vec2::ZERO,
+            last_mouse: None,
+            tx: None,
+            tx_zoom: 0.,
+        })
+    }
+
+    /// Handle user input for navigation and zoom
+    pub fn update(&mut self) {
+        if is_key_pressed(KeyCode::Right) || is_key_pressed(KeyCode::PageDown) || is_key_pressed(KeyCode::Space) {
+            self.goto(self.page + 1);
+        }
+        if is_key_pressed(KeyCode::Left) || is_key_pressed(KeyCode::PageUp) {
+            self.goto(self.page.saturating_sub(1));
+        }
+        if is_key_pressed(KeyCode::Home) {
+            self.goto(0);
+        }
+        if is_key_pressed(KeyCode::End) {
+            self.goto(self.pages.len().saturating_sub(1));
+        }
+
+        // Zoom
+        let (_, wheel) = mouse_wheel();
+        if is_key_pressed(KeyCode::Equal) || is_key_pressed(KeyCode::KpAdd) || wheel > 0. {
+            self.zoom = Some((self.zoom() * ZOOM_STEP).min(MAX_ZOOM));
+        }
+        if is_key_pressed(KeyCode::Minus) || is_key_pressed(KeyCode::KpSubtract) || wheel < 0. {
+            self.zoom = Some((self.zoom() / ZOOM_STEP).max(MIN_ZOOM));
+        }
+        if is_key_pressed(KeyCode::Key0) || is_key_pressed(KeyCode::Kp0) {
+            self.zoom = None;
+            self.offset = Vec2::ZERO;
+        }
+
+        // Pan
+        if is_key_down(KeyCode::Up) {
+            self.offset.y += PAN_SPEED * get_frame_time();
+        }
+        if is_key_down(KeyCode::Down) {
+            self.offset.y -= PAN_SPEED * get_frame_time();
+        }
+        let mouse = Vec2::from(mouse_position());
+        if is_mouse_button_down(MouseButton::Left) {
+            if let Some(last) = self.last_mouse {
+                self.offset += mouse - last;
+            }
+            self.last_mouse = Some(mouse);
+        } else {
+            self.last_mouse = None;
+        }
+    }
+
+    /// Draw the current page centered in the window with page and zoom information
+    pub fn draw(&mut self) {
+        if let Some(tx) = self.texture() {
+            let x = (screen_width() - tx.width()) / 2. + self.offset.x;
+            let y = (screen_height() - tx.height()) / 2. + self.offset.y;
+            draw_texture(tx, x.round(), y.round(), WHITE);
+        }
+
+        let status = format!("Page {} of {}  {:.0}%", self.page + 1, self.pages.len(), self.zoom() * 100.);
+        draw_rectangle(0., screen_height() - 24., screen_width(), 24., Color::new(0., 0., 0., 0.6));
+        draw_text(&status, 8., screen_height() - 7., 20., WHITE);
+    }
+
+    // Change to the given page if it exists
+    fn goto(&mut self, page: usize) {
+        if page < self.pages.len() && page != self.page {
+            self.page = page;
+            self.offset = Vec2::ZERO;
+            self.invalidate();
+        }
+    }
+
+    // Current zoom in pixels per point fitting the page to the window if not set
+    fn zoom(&self) -> f32 {
+        if let Some(zoom) = self.zoom {
+            return zoom;
+        }
+        match self.pages.get(self.page) {
+            Some(page_id) => {
+                let (width, height) = PageRenderer::new(&self.doc).page_size(*page_id);
+                (screen_width() / width).min(screen_height() / height).clamp(MIN_ZOOM, MAX_ZOOM)
+            },
+            None => 1.,
+        }
+    }
+
+    // Get the texture for the current page rendering it if needed
+    fn texture(&mut self) -> Option<Texture2D> {
+        let zoom = self.zoom();
+        if self.tx.is_some() && (zoom - self.tx_zoom).abs() > f32::EPSILON {
+            self.invalidate();
+        }
+        if self.tx.is_none() {
+            let page_id = *self.pages.get(self.page)?;
+            let img = PageRenderer::new(&self.doc).render(page_id, zoom);
+            let (w, h) = img.dimensions();
+            self.tx = Some(Texture2D::from_rgba8(w as u16, h as u16, img.as_raw()));
+            self.tx_zoom = zoom;
+        }
+        self.tx
+    }
+
+    // Release the cached texture so that it gets rendered again
+    fn invalidate(&mut self) {
+        if let Some(tx) = self.tx.take() {
+            tx.delete();
+        }
+    }
+}
@@ -0,0 +1,136 @@
+// Embed raster images into a PDF as image XObjects
+use std::io::Cursor;
+
+use image::{codecs::jpeg::JpegDecoder, ImageDecoder, ImageFormat, ImageResult};
+use lopdf::{content::Operation, Document, Object, ObjectId, Stream};
+
+/// Pixel data for the image in a form the PDF can use directly
+enum Data {
+    // JPEG files are embedded as is since PDF supports the DCTDecode filter natively
+    Jpeg { bytes: Vec<u8>, components: u8, inverted: bool },
+
+    // Everything else is decoded into 8 bit RGB with an optional separate alpha channel
+    Raw { rgb: Vec<u8>, alpha: Option<Vec<u8>> },
+}
+
+/// A raster image to be embedded into a PDF document as an image XObject
+pub struct ImageXObject {
+    pub width: u32,
+    pub height: u32,
+    data: Data,
+}
+
+impl ImageXObject {
+    /// Create a new image from the bytes of a PNG, JPEG or any other format the `image` crate
+    /// supports.
+    ///
+    /// ```rust
+    /// let bytes = load_file("assets/ferris.png").await.unwrap();
+    /// let img = ImageXObject::from_bytes(bytes).unwrap();
+    /// ```
+    pub fn from_bytes(bytes: Vec<u8>) -> ImageResult<Self> {
+        if image::guess_format(&bytes)? == ImageFormat::Jpeg {
+            // The decoder converts CMYK to RGB so the component count comes from the header
+            let (width, height) = JpegDecoder::new(Cursor::new(&bytes))?.dimensions();
+            let (components, inverted) = jpeg_components(&bytes);
+            return Ok(Self { width, height, data: Data::Jpeg { bytes, components, inverted } });
+        }
+
+        let img = image::load_from_memory(&bytes)?;
+        let alpha = match img.color().has_alpha() {
+            true => Some(img.to_rgba8().pixels().map(|x| x.0[3]).collect()),
+            false => None,
+        };
+        Ok(Self {
+            width: img.width(),
+            height: img.height(),
+            data: Data::Raw { rgb: img.to_rgb8().into_raw(), alpha },
+        })
+    }
+
+    /// Embed the image into the document returning the id to reference it by in a page's
+    /// `XObject` resources.
+    pub fn embed(&self, doc: &mut Document) -> ObjectId {
+        let mut dict = dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => self.width as i64,
+            "Height" => self.height as i64,
+            "BitsPerComponent" => 8,
+        };
+        let content = match &self.data {
+            Data::Jpeg { bytes, components, inverted } => {
+                match components {
+                    1 => dict.set("ColorSpace", "DeviceGray"),
+                    4 => {
+                        dict.set("ColorSpace", "DeviceCMYK");
+                        if *inverted {
+                            dict.set("Decode", [1, 0, 1, 0, 1, 0, 1, 0].map(Object::from).to_vec());
+                        }
+                    },
+                    _ => dict.set("ColorSpace", "DeviceRGB"),
+                }
+                dict.set("Filter", "DCTDecode");
+                bytes.clone()
+            },
+            Data::Raw { rgb, alpha } => {
+                dict.set("ColorSpace", "DeviceRGB");
+                if let Some(alpha) = alpha {
+                    let smask_id = doc.add_object(Stream::new(
+                        dictionary! {
+                            "Type" => "XObject",
+                            "Subtype" => "Image",
+                            "Width" => self.width as i64,
+                            "Height" => self.height as i64,
+                            "ColorSpace" => "DeviceGray",
+                            "BitsPerComponent" => 8,
+                        },
+                        alpha.clone(),
+                    ));
+                    dict.set("SMask", smask_id);
+                }
+                rgb.clone()
+            },
+        };
+        doc.add_object(Stream::new(dict, content))
+    }
+
+    /// Content stream operations to draw the image resource `name` at the given location and
+    /// size in points with the origin at the bottom left of the page.
+    pub fn draw(name: &str, x: f32, y: f32, width: f32, height: f32) -> Vec<Operation> {
+        vec![
+            Operation::new("q", vec![]),
+            Operation::new("cm", vec![width.into(), 0.into(), 0.into(), height.into(), x.into(), y.into()]),
+            Operation::new("Do", vec![Object::Name(name.as_bytes().to_vec())]),
+            Operation::new("Q", vec![]),
+        ]
+    }
+}
+
+// Scan the JPEG markers for the number of color components in the frame header and whether an
+// Adobe APP14 segment is present. Adobe writes CMYK data inverted so it must be decoded flipped.
+fn jpeg_components(bytes: &[u8]) -> (u8, bool) {
+    let (mut components, mut adobe) = (3, false);
+    let mut i = 2;
+    while i + 4 <= bytes.len() && bytes[i] == 0xFF {
+        let marker = bytes[i + 1];
+        let len = u16::from_be_bytes([bytes[i + 2], bytes[i + 3]]) as usize;
+        if len < 2 {
+            break;
+        }
+        let segment = &bytes[i + 4..(i + 2 + len).min(bytes.len())];
+        match marker {
+            // Start of frame markers excluding DHT, JPG and DAC which share the range
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                if let Some(x) = segment.get(5) {
+                    components = *x;
+                }
+            },
+            0xEE => adobe |= segment.starts_with(b"Adobe"),
+            0xDA => break, // image data follows the start of scan
+            _ => {},
+        }
+        i += 2 + len;
+    }
+    (components, adobe)
+}
@@ -0,0 +1,230 @@
+// Persistent on-disk thumbnail cache generating missing thumbnails on a thread pool
+//
+// Thumbnails are laid out like the freedesktop thumbnail spec: each is a PNG named after the MD5
+// of the file's URI with the URI, modification time and size of the original stored in tEXt
+// chunks to detect stale entries. The spec's normal/large directories hold thumbnails scaled to
+// fit while ours are cropped to fill a square with a configurable filter, so they go in their own
+// directory next to them named after the size and filter instead to not confuse other programs.
+// Animations are stored as APNG which other programs will see as their first frame.
+use std::error::Error;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::UNIX_EPOCH;
+
+use gif_animation::AnimationError;
+use image::{imageops::FilterType, DynamicImage, RgbaImage};
+use threadpool::ThreadPool;
+
+use crate::metadata;
+
+// Bumped whenever thumbnail generation changes to regenerate thumbnails cached by older versions
+const VERSION: u32 = 2;
+
+// Numbers the temporary files as workers can store the same thumbnail at once when a file is requested twice
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+pub type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Thumbnail frames of an image with a single frame for still images
+pub struct Thumbnail {
+    pub frames: Vec<RgbaImage>, // Thumbnail of every frame
+    pub delays: Vec<f32>,       // Delay of every frame in seconds
+}
+
+/// Identifies the version of a file a thumbnail was generated from
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Key {
+    uri: String,  // URI of the absolute path of the file
+    mtime: u64,   // Modification time in seconds since the epoch
+    size: u64,    // Size of the file in bytes
+    version: u32, // Version of the thumbnail generation
+}
+
+impl Key {
+    fn new(path: &Path) -> Result<Self> {
+        let meta = fs::metadata(path)?;
+        Ok(Self {
+            uri: uri(&fs::canonicalize(path)?),
+            mtime: meta.modified()?.duration_since(UNIX_EPOCH)?.as_secs(),
+            size: meta.len(),
+            version: VERSION,
+        })
+    }
+
+    // Name of the thumbnail file for this key
+    fn filename(&self) -> String {
+        format!("{:x}.png", md5::compute(self.uri.as_bytes()))
+    }
+}
+
+/// Thumbnail cache handing out thumbnails as they become available.
+///
+/// ```rust
+/// let cache = ThumbnailCache::new(200, FilterType::Triangle);
+/// cache.request("assets/ferris.png");
+/// for (path, thumbnail) in cache.poll() {
+///     ...
+/// }
+/// ```
+pub struct ThumbnailCache {
+    dir: PathBuf,       // Directory the thumbnails are stored in
+    size: u32,          // Width and height of the thumbnails
+    filter: FilterType, // Filter to resize with
+    pool: ThreadPool,   // Workers loading and generating thumbnails
+    tx: Sender<(PathBuf, Result<Thumbnail>)>,
+    rx: Receiver<(PathBuf, Result<Thumbnail>)>,
+}
+
+impl ThumbnailCache {
+    /// Create a cache for square thumbnails of the given size generated with the given filter
+    /// stored under the user's cache directory
+    pub fn new(size: u32, filter: FilterType) -> Self {
+        let root = dirs::cache_dir().unwrap_or_else(std::env::temp_dir).join("thumbnails");
+        let dir = root.join(format!("{}x{}-{:?}", size, size, filter).to_lowercase());
+        let (tx, rx) = channel();
+        Self {
+            dir,
+            size,
+            filter,
+            pool: ThreadPool::default(),
+            tx,
+            rx,
+        }
+    }
+
+    /// Get the directory the thumbnails are stored in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Queue loading the thumbnail for the given file generating it in the background if it
+    /// isn't cached or the file has changed since
+    pub fn request<T: AsRef<Path>>(&self, path: T) {
+        let path = path.as_ref().to_path_buf();
+        let (dir, size, filter, tx) = (self.dir.clone(), self.size, self.filter, self.tx.clone());
+        self.pool.execute(move || {
+            let thumbnail = Self::get(&dir, &path, size, filter);
+            let _ = tx.send((path, thumbnail));
+        });
+    }
+
+    /// Get the thumbnails finished since the last call without blocking
+    pub fn poll(&self) -> Vec<(PathBuf, Result<Thumbnail>)> {
+        self.rx.try_iter().collect()
+    }
+
+    /// Remove all cached thumbnails of this size and filter
+    pub fn clear(&self) -> Result<()> {
+        match fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    // Load the cached thumbnail if still valid or generate and store a new one. Failing to store
+    // the thumbnail e.g. due to a read-only cache isn't fatal.
+    fn get(dir: &Path, path: &Path, size: u32, filter: FilterType) -> Result<Thumbnail> {
+        let key = Key::new(path)?;
+        let cached = dir.join(key.filename());
+        if let Ok(thumbnail) = Self::load(&cached, &key) {
+            return Ok(thumbnail);
+        }
+        let thumbnail = Self::generate(path, size, filter)?;
+        if let Err(e) = Self::store(&cached, &key, &thumbnail) {
+            eprintln!("Couldn't cache thumbnail for {}: {}", path.display(), e);
+        }
+        Ok(thumbnail)
+    }
+
+    // Load a cached thumbnail failing if it was generated from a different version of the file
+    fn load(cached: &Path, key: &Key) -> Result<Thumbnail> {
+        let bytes = fs::read(cached)?;
+        let reader = png::Decoder::new(Cursor::new(&bytes)).read_info()?;
+        let text = |keyword: &str| {
+            let chunks = &reader.info().uncompressed_latin1_text;
+            chunks.iter().find(|x| x.keyword == keyword).map(|x| x.text.clone())
+        };
+        let stored = Key {
+            uri: text("Thumb::URI").unwrap_or_default(),
+            mtime: text("Thumb::MTime").and_then(|x| x.parse().ok()).unwrap_or_default(),
+            size: text("Thumb::Size").and_then(|x| x.parse().ok()).unwrap_or_default(),
+            version: text("Thumb::X-Version").and_then(|x| x.parse().ok()).unwrap_or_default(),
+        };
+        if stored != *key {
+            return Err("stale thumbnail".into());
+        }
+        let mut animation = gif_animation::open(bytes)?;
+        Ok(Thumbnail { frames: animation.frames()?, delays: animation.delays().to_vec() })
+    }
+
+    // Decode every frame of the file and resize them to fill the thumbnail size once upright
+    fn generate(path: &Path, size: u32, filter: FilterType) -> Result<Thumbnail> {
+        let bytes = fs::read(path)?;
+        let orientation = metadata::orientation(&bytes);
+
+        // Decode every frame of animated formats and fall back on the image crate for the rest
+        let (frames, delays): (Vec<DynamicImage>, _) = match gif_animation::open(bytes.clone()) {
+            Ok(mut animation) => {
+                let frames = animation.frames()?.into_iter().map(DynamicImage::ImageRgba8).collect();
+                (frames, animation.delays().to_vec())
+            },
+            Err(AnimationError::Unsupported) => (vec![image::load_from_memory(&bytes)?], vec![0.]),
+            Err(e) => return Err(e.into()),
+        };
+        if frames.is_empty() {
+            return Err(AnimationError::NoFrames.into());
+        }
+        let frames = frames
+            .into_iter()
+            .map(|x| metadata::orient(x, orientation).resize_to_fill(size, size, filter).into_rgba8())
+            .collect();
+        Ok(Thumbnail { frames, delays })
+    }
+
+    // Write the thumbnail to a temporary file first and move it into place so that other
+    // workers and programs never see a partial thumbnail
+    fn store(cached: &Path, key: &Key, thumbnail: &Thumbnail) -> Result<()> {
+        let (width, height) = thumbnail.frames[0].dimensions();
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.add_text_chunk("Thumb::URI".into(), key.uri.clone())?;
+        encoder.add_text_chunk("Thumb::MTime".into(), key.mtime.to_string())?;
+        encoder.add_text_chunk("Thumb::Size".into(), key.size.to_string())?;
+        encoder.add_text_chunk("Thumb::X-Version".into(), key.version.to_string())?;
+        if thumbnail.frames.len() > 1 {
+            encoder.set_animated(thumbnail.frames.len() as u32, 0)?;
+        }
+        let mut writer = encoder.write_header()?;
+        for (frame, delay) in thumbnail.frames.iter().zip(&thumbnail.delays) {
+            if thumbnail.frames.len() > 1 {
+                writer.set_frame_delay((delay * 1000.).round() as u16, 1000)?;
+            }
+            writer.write_image_data(frame.as_raw())?;
+        }
+        writer.finish()?;
+
+        fs::create_dir_all(cached.parent().ok_or("invalid cache path")?)?;
+        let id = TMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let tmp = cached.with_extension(format!("{}-{}.tmp", std::process::id(), id));
+        fs::write(&tmp, bytes)?;
+        fs::rename(&tmp, cached)?;
+        Ok(())
+    }
+}
+
+// Convert an absolute path into a file URI percent encoding everything but unreserved characters
+fn uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
@@ -0,0 +1,221 @@
+// Scrollable grid layout of square cells with a filename label under each
+use macroquad::prelude::*;
+
+const MIN_COLUMNS: usize = 1;
+const MAX_COLUMNS: usize = 16;
+const PADDING: f32 = 5.;            // Space around each cell
+const LABEL: f32 = 24.;             // Height of the label under each cell
+const HEADER: f32 = 36.;            // Height of the title above each group
+const SCROLL_SPEED: f32 = 60.;      // Pixels per mouse wheel step
+const KEY_SCROLL_SPEED: f32 = 800.; // Pixels per second when scrolling with the arrow keys
+
+/// Consecutive items shown together under a title starting on a new row
+struct Group {
+    title: Option<String>, // Title to show above the group
+    start: usize,          // Index of the first item in the group
+    len: usize,            // Number of items in the group
+}
+
+/// Grid of square cells filling the width of the window optionally split into titled groups.
+///
+/// * The mouse wheel, `Up`, `Down`, `PageUp`, `PageDown`, `Home` and `End` scroll the grid
+/// * `+` and `-` change the number of columns resizing the cells to fit
+pub struct Grid {
+    columns: usize,     // Number of cells per row
+    scroll: f32,        // Distance in pixels the grid is scrolled down
+    thickness: f32,     // Thickness of the cell borders
+    groups: Vec<Group>, // Groups of items in order
+}
+
+impl Grid {
+    /// Create a new grid with the given number of columns
+    pub fn new(columns: usize) -> Self {
+        Self {
+            columns: columns.clamp(MIN_COLUMNS, MAX_COLUMNS),
+            scroll: 0.,
+            thickness: 2.,
+            groups: Vec::new(),
+        }
+    }
+
+    /// Show the given number of items as a single untitled group
+    pub fn set_items(&mut self, items: usize) {
+        self.groups = vec![Group { title: None, start: 0, len: items }];
+    }
+
+    /// Show the items as titled groups of the given sizes in order
+    pub fn set_groups(&mut self, groups: Vec<(String, usize)>) {
+        let mut start = 0;
+        self.groups = groups
+            .into_iter()
+            .map(|(title, len)| {
+                start += len;
+                Group { title: Some(title), start: start - len, len }
+            })
+            .collect();
+    }
+
+    /// Handle user input for scrolling and resizing
+    pub fn update(&mut self) {
+        let (_, wheel) = mouse_wheel();
+        if wheel != 0. {
+            self.scroll -= wheel.signum() * SCROLL_SPEED;
+        }
+        if is_key_down(KeyCode::Up) {
+            self.scroll -= KEY_SCROLL_SPEED * get_frame_time();
+        }
+        if is_key_down(KeyCode::Down) {
+            self.scroll += KEY_SCROLL_SPEED * get_frame_time();
+        }
+        if is_key_pressed(KeyCode::PageUp) {
+            self.scroll -= screen_height();
+        }
+        if is_key_pressed(KeyCode::PageDown) {
+            self.scroll += screen_height();
+        }
+        if is_key_pressed(KeyCode::Home) {
+            self.scroll = 0.;
+        }
+        if is_key_pressed(KeyCode::End) {
+            self.scroll = f32::MAX;
+        }
+
+        // Keep the item at the top of the window in view when resizing
+        let top = self.visible().first().copied();
+        if is_key_pressed(KeyCode::Equal) || is_key_pressed(KeyCode::KpAdd) {
+            self.columns = (self.columns - 1).max(MIN_COLUMNS);
+            if let Some(top) = top {
+                self.scroll += self.cell(top).y - PADDING;
+            }
+        }
+        if is_key_pressed(KeyCode::Minus) || is_key_pressed(KeyCode::KpSubtract) {
+            self.columns = (self.columns + 1).min(MAX_COLUMNS);
+            if let Some(top) = top {
+                self.scroll += self.cell(top).y - PADDING;
+            }
+        }
+
+        let max_scroll = (self.height() - screen_height()).max(0.);
+        self.scroll = self.scroll.clamp(0., max_scroll);
+    }
+
+    /// Get the indices of the items at least partially visible in the window
+    pub fn visible(&self) -> Vec<usize> {
+        self.within(0.)
+    }
+
+    /// Get the indices of the items in the visible rows and the given number of rows above and below them
+    pub fn nearby(&self, rows: usize) -> Vec<usize> {
+        self.within(rows as f32 * self.row_height())
+    }
+
+    // Indices of the items in rows at least partially within the given distance of the window
+    fn within(&self, margin: f32) -> Vec<usize> {
+        let mut items = Vec::new();
+        let mut top = 0.;
+        for group in self.groups.iter() {
+            let first = top + self.header(group) - self.scroll;
+            let rows = group.len.div_ceil(self.columns);
+            for row in 0..rows {
+                let y = first + row as f32 * self.row_height();
+                if y + self.row_height() >= -margin && y < screen_height() + margin {
+                    let start = group.start + row * self.columns;
+                    items.extend(start..(start + self.columns).min(group.start + group.len));
+                }
+            }
+            top += self.header(group) + rows as f32 * self.row_height();
+        }
+        items
+    }
+
+    /// Get the square area of the window the given item's image is drawn in
+    pub fn cell(&self, index: usize) -> Rect {
+        let size = self.cell_size();
+        let mut top = 0.;
+        for group in self.groups.iter() {
+            if index < group.start + group.len {
+                let (row, col) = ((index - group.start) / self.columns, (index - group.start) % self.columns);
+                let x = PADDING + col as f32 * (size + PADDING);
+                let y = top + self.header(group) + PADDING + row as f32 * self.row_height() - self.scroll;
+                return Rect::new(x, y, size, size);
+            }
+            top += self.header(group) + self.rows_height(group);
+        }
+        Rect::new(0., top - self.scroll, size, size)
+    }
+
+    /// Get the area of the window the given item's label is drawn in
+    pub fn label(&self, index: usize) -> Rect {
+        let cell = self.cell(index);
+        Rect::new(cell.x, cell.bottom(), cell.w, LABEL)
+    }
+
+    /// Get the item under the given window position if any
+    pub fn item_at(&self, pos: Vec2) -> Option<usize> {
+        self.visible().into_iter().find(|i| {
+            let (cell, label) = (self.cell(*i), self.label(*i));
+            cell.contains(pos) || label.contains(pos)
+        })
+    }
+
+    /// Scroll the grid so that the given item is fully visible
+    pub fn scroll_to(&mut self, index: usize) {
+        let (cell, label) = (self.cell(index), self.label(index));
+        if cell.y < 0. {
+            self.scroll += cell.y - PADDING;
+        } else if label.bottom() > screen_height() {
+            self.scroll += label.bottom() - screen_height() + PADDING;
+        }
+    }
+
+    /// Draw the borders of the visible cells and the titles of the visible groups
+    pub fn draw(&self, color: Color, font: Font) {
+        for i in self.visible() {
+            let (cell, label) = (self.cell(i), self.label(i));
+            let (x, y) = (cell.x - PADDING / 2., cell.y - PADDING / 2.);
+            draw_rectangle_lines(x, y, cell.w + PADDING, cell.h + label.h + PADDING, self.thickness, color);
+        }
+        let mut top = -self.scroll;
+        for group in self.groups.iter() {
+            if let Some(title) = &group.title {
+                if top + HEADER >= 0. && top < screen_height() {
+                    draw_text_ex(title, PADDING, top + HEADER - 8., TextParams {
+                        font_size: 24,
+                        font,
+                        ..Default::default()
+                    });
+                }
+            }
+            top += self.header(group) + self.rows_height(group);
+        }
+    }
+
+    // Width and height of the square cells filling the window width
+    fn cell_size(&self) -> f32 {
+        (screen_width() - PADDING) / self.columns as f32 - PADDING
+    }
+
+    // Distance between the top of consecutive rows
+    fn row_height(&self) -> f32 {
+        self.cell_size() + LABEL + PADDING
+    }
+
+    // Height of the title above the given group
+    fn header(&self, group: &Group) -> f32 {
+        if group.title.is_some() {
+            HEADER
+        } else {
+            0.
+        }
+    }
+
+    // Height of the rows of the given group
+    fn rows_height(&self, group: &Group) -> f32 {
+        group.len.div_ceil(self.columns) as f32 * self.row_height()
+    }
+
+    // Height of all groups together
+    fn height(&self) -> f32 {
+        self.groups.iter().map(|x| self.header(x) + self.rows_height(x)).sum::<f32>() + PADDING
+    }
+}
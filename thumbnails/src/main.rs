@@ -1,84 +1,289 @@
-use image::{imageops::FilterType, DynamicImage};
+use std::collections::HashSet;
+
+use image::imageops::FilterType;
 use macroquad::prelude::*;
 use rivia_vfs::prelude::*;
 
+mod cache;
+mod grid;
+mod metadata;
+mod sort;
+mod viewer;
+use cache::{Thumbnail, ThumbnailCache};
+use grid::Grid;
+use metadata::Metadata;
+use sort::SortBy;
+use viewer::Viewer;
+
+const THUMBNAIL_SIZE: u32 = 200;
+const COLUMNS: usize = 4;
+
+// Rows above and below the window whose thumbnails stay loaded so scrolling back a little doesn't reload them
+const KEEP_ROWS: usize = 2;
+
+// Largest texture dimension commonly supported by GPUs
+const MAX_TEXTURE_SIZE: f32 = 4096.;
+
+// File extensions of the images to browse
+const SUPPORTED: [&str; 8] = ["bmp", "gif", "jpeg", "jpg", "png", "tif", "tiff", "webp"];
+
 pub struct Image {
     path: PathBuf,
-    image: DynamicImage,
-    texture: Texture2D,
-    thumbnail: Texture2D,
+    texture: Option<Texture2D>, // Window sized texture while the image is being viewed
+    thumbnails: Vec<Texture2D>, // Thumbnail of every frame for animations; empty until loaded
+    delays: Vec<f32>,           // Delay of every frame in seconds
+    requested: bool,            // Whether the thumbnail has been requested from the cache
+    metadata: Option<Metadata>, // Metadata once read in the background
 }
 impl Image {
-    pub async fn load(path: &str) -> Self {
-        let bytes = load_file(path).await.expect("Couldn't load file");
-        let img = image::load_from_memory(&bytes).unwrap_or_else(|e| panic!("{}", e));
-        let texture = Self::texturize(&img);
-
-        // Different filter types yielded comparable results with different times except Nearest
-        // FilterType::Nearest),    // crappy quality
-        // FilterType::Triangle),   // 293 ms
-        // FilterType::CatmullRom), // 449 ms
-        // FilterType::Gaussian),   // 613 ms
-        // FilterType::Lanczos3),   // 607 ms
-        let thumbnail = Self::texturize(&img.resize_to_fill(200, 200, FilterType::Triangle));
+    pub fn new(path: PathBuf) -> Self {
         Self {
-            path: PathBuf::from(path),
-            image: img,
-            thumbnail,
-            texture,
+            path,
+            texture: None,
+            thumbnails: Vec::new(),
+            delays: Vec::new(),
+            requested: false,
+            metadata: None,
+        }
+    }
+
+    /// Get the filename of the image
+    pub fn name(&self) -> String {
+        self.path.base().unwrap_or_default()
+    }
+
+    /// Decode the image into a texture turned upright according to its EXIF orientation and
+    /// scaled down to fit the window since the viewer never draws it any larger
+    pub fn load_texture(&mut self) {
+        let img = std::fs::read(&self.path).map_err(image::ImageError::IoError).and_then(|bytes| {
+            let img = image::load_from_memory(&bytes)?;
+            Ok(metadata::orient(img, metadata::orientation(&bytes)))
+        });
+        match img {
+            Ok(img) => {
+                let width = screen_width().clamp(1., MAX_TEXTURE_SIZE) as u32;
+                let height = screen_height().clamp(1., MAX_TEXTURE_SIZE) as u32;
+                let img = match img.width() > width || img.height() > height {
+                    true => img.resize(width, height, FilterType::Triangle),
+                    false => img,
+                };
+                let img = img.into_rgba8();
+                self.texture = Some(Texture2D::from_rgba8(img.width() as u16, img.height() as u16, img.as_raw()));
+            },
+            Err(e) => eprintln!("Couldn't load {}: {}", self.path.display(), e),
+        }
+    }
+
+    /// Release the full size texture
+    pub fn release_texture(&mut self) {
+        if let Some(texture) = self.texture.take() {
+            texture.delete();
+        }
+    }
+
+    // Replace the thumbnail textures with the given thumbnail's frames
+    fn set_thumbnail(&mut self, thumbnail: Thumbnail) {
+        self.release_thumbnail();
+        self.requested = true;
+        self.thumbnails = thumbnail
+            .frames
+            .iter()
+            .map(|x| Texture2D::from_rgba8(x.width() as u16, x.height() as u16, x.as_raw()))
+            .collect();
+        self.delays = thumbnail.delays;
+    }
+
+    // Release the thumbnail textures to have them requested from the cache again once back in view
+    fn release_thumbnail(&mut self) {
+        for texture in self.thumbnails.drain(..) {
+            texture.delete();
         }
+        self.delays.clear();
+        self.requested = false;
     }
 
-    // Convert an image into a texture
-    fn texturize(img: &DynamicImage) -> Texture2D {
-        let width = img.width() as u16;
-        let height = img.height() as u16;
-        let bytes = img.to_rgba8().into_raw();
-        Texture2D::from_rgba8(width, height, &bytes)
+    // Get the thumbnail of the frame showing at the given time looping animations forever
+    fn thumbnail(&self, time: f32) -> Option<Texture2D> {
+        let duration: f32 = self.delays.iter().sum();
+        let mut time = if duration > 0. { time % duration } else { 0. };
+        for (thumbnail, delay) in self.thumbnails.iter().zip(&self.delays) {
+            if time < *delay {
+                return Some(*thumbnail);
+            }
+            time -= delay;
+        }
+        self.thumbnails.first().copied()
     }
 }
 
+// Recursively find the supported images in the given directory sorted by path
+fn discover(dir: &str) -> RvResult<Vec<Image>> {
+    let mut images = Vec::new();
+    for path in vfs::all_files(vfs::abs(dir)?)? {
+        let ext = path.ext().unwrap_or_default().to_lowercase();
+        if SUPPORTED.contains(&ext.as_str()) {
+            images.push(Image::new(path));
+        }
+    }
+    images.sort_by(|x, y| x.path.cmp(&y.path));
+    Ok(images)
+}
+
+// Shorten the given text to fit within the given width
+fn fit(text: &str, font: Font, font_size: u16, width: f32) -> String {
+    let mut text = text.to_string();
+    if measure_text(&text, Some(font), font_size, 1.).width <= width {
+        return text;
+    }
+    while !text.is_empty() && measure_text(&format!("{}...", text), Some(font), font_size, 1.).width > width {
+        text.pop();
+    }
+    format!("{}...", text)
+}
+
 #[macroquad::main("Thumbnails Example")]
 async fn main() {
+    // Browse the given directory or the example assets
+    let dir = std::env::args().nth(1).unwrap_or_else(|| "assets".to_string());
+    let mut images = discover(&dir).unwrap_or_else(|e| panic!("Couldn't scan {}: {}", dir, e));
     let font = load_ttf_font("assets/Audiowide-Regular.ttf").await.unwrap();
-    let textures = vec![
-        Image::load("assets/ferris.gif").await,
-        Image::load("assets/ferris.jpg").await,
-        Image::load("assets/ferris.png").await,
-        Image::load("assets/ferris.tiff").await,
-        Image::load("assets/ferris.webp").await,
-    ];
+
+    // Different filter types yielded comparable results with different times except Nearest
+    // FilterType::Nearest),    // crappy quality
+    // FilterType::Triangle),   // 293 ms
+    // FilterType::CatmullRom), // 449 ms
+    // FilterType::Gaussian),   // 613 ms
+    // FilterType::Lanczos3),   // 607 ms
+    // Thumbnails are only generated the first time and then loaded from the cache
+    let cache = ThumbnailCache::new(THUMBNAIL_SIZE, FilterType::Triangle);
+    println!("Caching thumbnails in {}", cache.dir().display());
+    let mut grid = Grid::new(COLUMNS);
+    let mut viewer: Option<Viewer> = None;
+
+    // Read the metadata of all images in the background for sorting and grouping
+    let metadata = metadata::spawn(images.iter().map(|x| x.path.clone()).collect());
+    let (mut sort, mut grouped) = (SortBy::Name, false);
+    let mut pending = images.len(); // Number of images still waiting on metadata
+    let mut dirty = true; // Whether the images need sorting and laying out again
 
     loop {
         clear_background(BLACK);
 
-        // Allow for a 5 pixel wide border around each image
-        let mut x = 0;
-        let mut y = 5;
-        let mut col = 0;
-        for img in textures.iter() {
-            col += 1;
-            if col == 1 {
-                x += 5;
+        // Pick up thumbnails finished in the background since the last frame
+        for (path, thumbnail) in cache.poll() {
+            match thumbnail {
+                Ok(thumbnail) => {
+                    // Skip thumbnails of images that scrolled out of view while being loaded
+                    if let Some(img) = images.iter_mut().find(|x| x.path == path && x.requested) {
+                        img.set_thumbnail(thumbnail);
+                    }
+                },
+                Err(e) => eprintln!("Couldn't load thumbnail for {}: {}", path.display(), e),
             }
-            draw_texture(img.thumbnail, x as f32, y as f32, WHITE);
-            draw_text_ex(
-                &img.path.ext().unwrap(),
-                x as f32,
-                (y + 25) as f32,
-                TextParams {
-                    font_size: 30,
-                    font,
-                    ..Default::default()
+        }
+
+        // Sort again once all metadata is in as dates, sizes and types aren't known until then
+        for (path, result) in metadata.try_iter() {
+            pending -= 1;
+            dirty |= pending == 0;
+            match result {
+                Ok(result) => {
+                    if let Some(img) = images.iter_mut().find(|x| x.path == path) {
+                        img.metadata = Some(result);
+                    }
                 },
-            );
-            x += 204;
-            if col == 4 {
-                col = 0;
-                x = 0;
-                y += 205;
+                Err(e) => eprintln!("Couldn't read metadata for {}: {}", path.display(), e),
+            }
+        }
+
+        // Show the full size image until the viewer is closed
+        if let Some(current) = viewer.as_mut() {
+            if current.update(&mut images) {
+                current.draw(&images, font);
+            } else {
+                grid.scroll_to(current.index());
+                viewer = None;
             }
+            next_frame().await;
+            continue;
         }
+
+        // Press C to clear the cache and generate the thumbnails again
+        if is_key_pressed(KeyCode::C) {
+            if let Err(e) = cache.clear() {
+                eprintln!("Couldn't clear thumbnail cache: {}", e);
+            }
+            images.iter_mut().for_each(|x| x.requested = false);
+        }
+
+        // Press S to change the sort order and G to toggle grouping
+        if is_key_pressed(KeyCode::S) {
+            sort = sort.next();
+            dirty = true;
+        }
+        if is_key_pressed(KeyCode::G) {
+            grouped ^= true;
+            dirty = true;
+        }
+        if dirty {
+            sort.sort(&mut images);
+            match grouped {
+                true => grid.set_groups(sort.groups(&images)),
+                false => grid.set_items(images.len()),
+            }
+            dirty = false;
+        }
+
+        // Only request thumbnails as they scroll into view and release them once well out of view
+        grid.update();
+        let nearby: HashSet<usize> = grid.nearby(KEEP_ROWS).into_iter().collect();
+        for (i, img) in images.iter_mut().enumerate() {
+            if (img.requested || !img.thumbnails.is_empty()) && !nearby.contains(&i) {
+                img.release_thumbnail();
+            }
+        }
+        let visible = grid.visible();
+        for i in visible.iter() {
+            if !images[*i].requested {
+                cache.request(&images[*i].path);
+                images[*i].requested = true;
+            }
+        }
+        if is_mouse_button_pressed(MouseButton::Left) {
+            if let Some(i) = grid.item_at(mouse_position().into()) {
+                viewer = Some(Viewer::open(&mut images, i));
+            }
+        }
+
+        grid.draw(DARKGRAY, font);
+        for i in visible {
+            let (cell, label) = (grid.cell(i), grid.label(i));
+            match images[i].thumbnail(get_time() as f32) {
+                Some(thumbnail) => draw_texture_ex(thumbnail, cell.x, cell.y, WHITE, DrawTextureParams {
+                    dest_size: Some(cell.size()),
+                    ..Default::default()
+                }),
+                None => draw_rectangle(cell.x, cell.y, cell.w, cell.h, Color::new(0.2, 0.2, 0.2, 1.)),
+            }
+            draw_text_ex(&fit(&images[i].name(), font, 16, label.w), label.x, label.y + 18., TextParams {
+                font_size: 16,
+                font,
+                ..Default::default()
+            });
+        }
+        if images.is_empty() {
+            draw_text(&format!("No images found in {}", dir), 10., 30., 30., WHITE);
+        }
+
+        let status = format!("Sort: {:?} (S)  Group: {} (G)", sort, if grouped { "on" } else { "off" });
+        let width = measure_text(&status, Some(font), 16, 1.).width;
+        let (x, y) = (screen_width() - width - 15., screen_height() - 26.);
+        draw_rectangle(x - 5., y, width + 15., 26., Color::new(0., 0., 0., 0.7));
+        draw_text_ex(&status, x, y + 19., TextParams {
+            font_size: 16,
+            font,
+            ..Default::default()
+        });
         next_frame().await
     }
 }
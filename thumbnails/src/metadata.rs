@@ -0,0 +1,118 @@
+// Image metadata read from the file system and EXIF for display and sorting
+use std::fs;
+use std::io::{BufRead, BufReader, Cursor, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+
+use chrono::{DateTime, Local, NaiveDateTime};
+use exif::{In, Tag, Value};
+use image::DynamicImage;
+
+use crate::cache::Result;
+
+/// Metadata of an image
+#[derive(Debug, Clone)]
+pub struct Metadata {
+    pub orientation: u32,             // EXIF orientation from 1 to 8 with 1 being upright
+    pub taken: Option<NaiveDateTime>, // Capture date from EXIF
+    pub modified: NaiveDateTime,      // Modification date of the file
+    pub camera: Option<String>,       // Camera make and model from EXIF
+    pub dimensions: (u32, u32),       // Width and height once upright
+    pub size: u64,                    // File size in bytes
+    pub format: String,               // File type e.g. JPG
+}
+
+impl Metadata {
+    /// Read the metadata of the given image file without decoding the pixels
+    pub fn read(path: &Path) -> Result<Self> {
+        let meta = fs::metadata(path)?;
+        let exif = read_exif(&mut BufReader::new(fs::File::open(path)?));
+        let orientation = exif.as_ref().map(orientation_of).unwrap_or(1);
+        let (width, height) = image::image_dimensions(path)?;
+        let taken = exif
+            .as_ref()
+            .and_then(|x| ascii(x, Tag::DateTimeOriginal).or_else(|| ascii(x, Tag::DateTime)))
+            .and_then(|x| NaiveDateTime::parse_from_str(&x, "%Y:%m:%d %H:%M:%S").ok());
+        Ok(Self {
+            orientation,
+            taken,
+            modified: DateTime::<Local>::from(meta.modified()?).naive_local(),
+            camera: exif.as_ref().and_then(camera_of),
+            dimensions: if orientation >= 5 { (height, width) } else { (width, height) },
+            size: meta.len(),
+            format: path.extension().map(|x| x.to_string_lossy().to_uppercase()).unwrap_or_default(),
+        })
+    }
+
+    /// Get the capture date falling back on the modification date
+    pub fn date(&self) -> NaiveDateTime {
+        self.taken.unwrap_or(self.modified)
+    }
+}
+
+/// Read the metadata of the given files in order on a background thread
+pub fn spawn(paths: Vec<PathBuf>) -> Receiver<(PathBuf, Result<Metadata>)> {
+    let (tx, rx) = channel();
+    thread::spawn(move || {
+        for path in paths {
+            let metadata = Metadata::read(&path);
+            if tx.send((path, metadata)).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+/// Get the EXIF orientation of the given image file contents defaulting to upright
+pub fn orientation(bytes: &[u8]) -> u32 {
+    read_exif(&mut Cursor::new(bytes)).as_ref().map(orientation_of).unwrap_or(1)
+}
+
+/// Rotate and flip the image as given by its EXIF orientation so that it is upright
+pub fn orient(img: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => img.fliph(),
+        3 => img.rotate180(),
+        4 => img.flipv(),
+        5 => img.rotate90().fliph(),
+        6 => img.rotate90(),
+        7 => img.rotate270().fliph(),
+        8 => img.rotate270(),
+        _ => img,
+    }
+}
+
+// Images without EXIF data are common so treat any failure as no EXIF data
+fn read_exif<R: BufRead + Seek>(reader: &mut R) -> Option<exif::Exif> {
+    exif::Reader::new().read_from_container(reader).ok()
+}
+
+fn orientation_of(exif: &exif::Exif) -> u32 {
+    let field = exif.get_field(Tag::Orientation, In::PRIMARY);
+    field.and_then(|x| x.value.get_uint(0)).filter(|x| (1..=8).contains(x)).unwrap_or(1)
+}
+
+// Make and model with the make left out if the model already starts with it as many do
+fn camera_of(exif: &exif::Exif) -> Option<String> {
+    let model = ascii(exif, Tag::Model)?;
+    match ascii(exif, Tag::Make) {
+        Some(make) if !model.to_lowercase().starts_with(&make.to_lowercase()) => {
+            Some(format!("{} {}", make, model))
+        },
+        _ => Some(model),
+    }
+}
+
+// Get the given ASCII field as a trimmed string
+fn ascii(exif: &exif::Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => {
+            let value = String::from_utf8_lossy(values.first()?);
+            let value = value.trim_matches(|x: char| x == '\0' || x.is_whitespace());
+            Some(value.to_string()).filter(|x| !x.is_empty())
+        },
+        _ => None,
+    }
+}
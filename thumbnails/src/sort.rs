@@ -0,0 +1,72 @@
+// Sorting and grouping of the images in the grid
+use crate::Image;
+
+const KB: u64 = 1024;
+const MB: u64 = 1024 * KB;
+
+/// Order to show the images in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    Name, // Filename in alphabetical order
+    Date, // Capture date newest first
+    Size, // File size largest first
+    Type, // File type then name
+}
+
+impl SortBy {
+    /// Get the next sort order cycling back to the first
+    pub fn next(self) -> Self {
+        match self {
+            SortBy::Name => SortBy::Date,
+            SortBy::Date => SortBy::Size,
+            SortBy::Size => SortBy::Type,
+            SortBy::Type => SortBy::Name,
+        }
+    }
+
+    /// Sort the images. Images whose metadata isn't known yet go last in name order.
+    pub fn sort(self, images: &mut [Image]) {
+        images.sort_by(|x, y| {
+            let order = match (self, &x.metadata, &y.metadata) {
+                (SortBy::Name, ..) => x.name().to_lowercase().cmp(&y.name().to_lowercase()),
+                (_, None, None) => std::cmp::Ordering::Equal,
+                (_, Some(_), None) => std::cmp::Ordering::Less,
+                (_, None, Some(_)) => std::cmp::Ordering::Greater,
+                (SortBy::Date, Some(x), Some(y)) => y.date().cmp(&x.date()),
+                (SortBy::Size, Some(x), Some(y)) => y.size.cmp(&x.size),
+                (SortBy::Type, Some(x), Some(y)) => x.format.cmp(&y.format),
+            };
+            order.then_with(|| x.path.cmp(&y.path))
+        });
+    }
+
+    /// Get the title of the group the image belongs to
+    pub fn group(self, img: &Image) -> String {
+        let metadata = match (self, &img.metadata) {
+            (SortBy::Name, _) => return img.name().chars().next().unwrap_or('#').to_uppercase().to_string(),
+            (_, None) => return "Unknown".to_string(),
+            (_, Some(metadata)) => metadata,
+        };
+        match self {
+            SortBy::Date => metadata.date().format("%B %Y").to_string(),
+            SortBy::Size if metadata.size < 100 * KB => "Under 100 KB".to_string(),
+            SortBy::Size if metadata.size < MB => "100 KB to 1 MB".to_string(),
+            SortBy::Size if metadata.size < 10 * MB => "1 MB to 10 MB".to_string(),
+            SortBy::Size => "Over 10 MB".to_string(),
+            _ => metadata.format.clone(),
+        }
+    }
+
+    /// Split the sorted images into titled groups of consecutive images
+    pub fn groups(self, images: &[Image]) -> Vec<(String, usize)> {
+        let mut groups: Vec<(String, usize)> = Vec::new();
+        for img in images {
+            let title = self.group(img);
+            match groups.last_mut() {
+                Some((last, len)) if *last == title => *len += 1,
+                _ => groups.push((title, 1)),
+            }
+        }
+        groups
+    }
+}
@@ -0,0 +1,120 @@
+// Full size view of a single image with navigation to the neighbouring images
+use chrono::NaiveDateTime;
+use macroquad::prelude::*;
+
+use crate::Image;
+
+/// Full size image viewer. Only the texture of the image being viewed is kept in memory.
+///
+/// * `Right` and `Space` go to the next image; `Left` and `Backspace` the previous
+/// * `I` toggles the metadata overlay
+/// * `Escape` or clicking closes the viewer
+pub struct Viewer {
+    index: usize, // Index of the image being viewed
+    info: bool,   // Whether to show the metadata overlay
+}
+
+impl Viewer {
+    /// Open the viewer on the given image
+    pub fn open(images: &mut [Image], index: usize) -> Self {
+        images[index].load_texture();
+        Self { index, info: false }
+    }
+
+    /// Get the index of the image being viewed
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Handle user input for navigation returning false once the viewer should close
+    pub fn update(&mut self, images: &mut [Image]) -> bool {
+        if is_key_pressed(KeyCode::Escape) || is_mouse_button_pressed(MouseButton::Left) {
+            images[self.index].release_texture();
+            return false;
+        }
+        if is_key_pressed(KeyCode::I) {
+            self.info ^= true;
+        }
+        let count = images.len();
+        if is_key_pressed(KeyCode::Right) || is_key_pressed(KeyCode::Space) {
+            self.goto(images, (self.index + 1) % count);
+        }
+        if is_key_pressed(KeyCode::Left) || is_key_pressed(KeyCode::Backspace) {
+            self.goto(images, (self.index + count - 1) % count);
+        }
+        true
+    }
+
+    /// Draw the image scaled down to fit the window with its filename and position
+    pub fn draw(&self, images: &[Image], font: Font) {
+        let img = &images[self.index];
+        match img.texture {
+            Some(texture) => {
+                let scale = (screen_width() / texture.width()).min(screen_height() / texture.height()).min(1.);
+                let size = vec2(texture.width(), texture.height()) * scale;
+                let x = ((screen_width() - size.x) / 2.).round();
+                let y = ((screen_height() - size.y) / 2.).round();
+                draw_texture_ex(texture, x, y, WHITE, DrawTextureParams {
+                    dest_size: Some(size),
+                    ..Default::default()
+                });
+            },
+            None => draw_text("Couldn't load image", 10., screen_height() / 2., 30., RED),
+        }
+
+        let status = format!("{} ({} of {})", img.name(), self.index + 1, images.len());
+        draw_rectangle(0., screen_height() - 30., screen_width(), 30., Color::new(0., 0., 0., 0.6));
+        draw_text_ex(&status, 10., screen_height() - 8., TextParams {
+            font_size: 20,
+            font,
+            ..Default::default()
+        });
+        if self.info {
+            Self::draw_info(img, font);
+        }
+    }
+
+    // Draw the metadata of the image in a panel at the top left
+    fn draw_info(img: &Image, font: Font) {
+        let lines = match &img.metadata {
+            Some(metadata) => {
+                let (width, height) = metadata.dimensions;
+                let format = |x: Option<NaiveDateTime>| x.map(|x| x.format("%Y-%m-%d %H:%M:%S").to_string());
+                vec![
+                    format!("Taken: {}", format(metadata.taken).unwrap_or_else(|| "unknown".to_string())),
+                    format!("Modified: {}", format(Some(metadata.modified)).unwrap_or_default()),
+                    format!("Camera: {}", metadata.camera.as_deref().unwrap_or("unknown")),
+                    format!("Dimensions: {} x {}", width, height),
+                    format!("Orientation: {}", metadata.orientation),
+                    format!("File size: {}", file_size(metadata.size)),
+                    format!("Type: {}", metadata.format),
+                ]
+            },
+            None => vec!["Loading metadata...".to_string()],
+        };
+        let params = TextParams { font_size: 18, font, ..Default::default() };
+        let width = lines.iter().map(|x| measure_text(x, Some(font), 18, 1.).width).fold(0., f32::max);
+        draw_rectangle(10., 10., width + 20., lines.len() as f32 * 24. + 12., Color::new(0., 0., 0., 0.7));
+        for (i, line) in lines.iter().enumerate() {
+            draw_text_ex(line, 20., 34. + i as f32 * 24., params);
+        }
+    }
+
+    // Change to the given image releasing the texture of the current one
+    fn goto(&mut self, images: &mut [Image], index: usize) {
+        if index != self.index {
+            images[self.index].release_texture();
+            self.index = index;
+            images[self.index].load_texture();
+        }
+    }
+}
+
+// Format a file size in bytes for display
+fn file_size(size: u64) -> String {
+    match size {
+        x if x < 1024 => format!("{} B", x),
+        x if x < 1024 * 1024 => format!("{:.1} KB", x as f32 / 1024.),
+        x => format!("{:.1} MB", x as f32 / (1024. * 1024.)),
+    }
+}